@@ -0,0 +1,12 @@
+# Boot rom for reset_test.s, programmed at the reset vector. It counts the
+# boots in the last word of ram, leaves the address it started at in s0 and
+# jumps to the program at address 0.
+
+        auipc   s0, 0
+        li      a0, -1
+        li      t0, 0xFFFFC
+        lw      t1, 0(t0)
+        addi    t1, t1, 1
+        sw      t1, 0(t0)
+        li      t0, 0
+        jr      t0
\ No newline at end of file
@@ -0,0 +1,83 @@
+# CLINT self check, for the default timer ratio of one tick per clock. The
+# handler records mcause in s1 and silences the interrupt source.
+# Run with:
+#   cargo run -- programs/clint_test.s --stop-on-ecall
+
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+        li      s0, 0x02000000          # CLINT base
+
+        # mtime counts up
+        li      gp, 1
+        li      t0, 0xBFF8
+        add     s2, s0, t0              # mtime
+        lw      t0, 0(s2)
+        nop
+        lw      t1, 0(s2)
+        bgeu    t0, t1, fail
+
+        # The timer pending bit follows mtime >= mtimecmp, and cannot be written
+        li      gp, 2
+        li      t0, 0x4000
+        add     s3, s0, t0              # mtimecmp
+        sw      zero, 4(s3)
+        sw      zero, 0(s3)
+        csrr    t0, 0x344               # mip
+        andi    t0, t0, 0x80
+        beqz    t0, fail
+        li      t1, -1
+        sw      t1, 4(s3)
+        sw      t1, 0(s3)
+        csrsi   0x344, 8
+        csrr    t0, 0x344
+        bnez    t0, fail
+
+        # A software interrupt is taken once enabled
+        li      gp, 3
+        li      s1, 0
+        li      t0, 1
+        sw      t0, 0(s0)               # msip
+        lw      t1, 0(s0)
+        bne     t0, t1, fail
+        csrsi   0x304, 8                # mie.MSIE
+        csrsi   0x300, 8                # mstatus.MIE
+        nop
+        li      t0, 0x80000003
+        bne     s1, t0, fail
+        csrci   0x300, 8
+
+        # A timer interrupt is taken once mtime reaches mtimecmp
+        li      gp, 4
+        li      s1, 0
+        lw      t0, 0(s2)
+        addi    t0, t0, 40
+        sw      zero, 4(s3)
+        sw      t0, 0(s3)
+        li      t0, 0x80
+        csrs    0x304, t0               # mie.MTIE
+        csrsi   0x300, 8                # mstatus.MIE
+        li      t1, 100
+wait:   bnez    s1, woken
+        addi    t1, t1, -1
+        bnez    t1, wait
+        j       fail
+woken:  li      t0, 0x80000007
+        bne     s1, t0, fail
+        csrci   0x300, 8
+
+pass:   li      a0, 0
+        csrw    0x305, zero
+        ecall
+
+fail:   mv      a0, gp
+        csrw    0x305, zero
+        ecall
+
+        .align  2
+handler:
+        csrr    s1, 0x342               # mcause
+        sw      zero, 0(s0)             # msip
+        li      t6, -1
+        sw      t6, 4(s3)               # mtimecmp
+        sw      t6, 0(s3)
+        mret
\ No newline at end of file
@@ -0,0 +1,219 @@
+# Counter self check.
+#
+# The handler records mcause in s1 and skips the trapping instruction. An ecall
+# from supervisor or user mode returns to machine mode.
+# Run with:
+#   cargo run -- programs/counter_test.s -i 1000
+
+        li      a0, -1
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+
+        # A CSR instruction takes 3 clocks, and reads the counters in its second
+        li      gp, 1
+        rdcycle t0
+        rdcycle t1
+        sub     t2, t1, t0
+        li      t3, 3
+        bne     t2, t3, fail
+        rdcycleh t0
+        bnez    t0, fail
+
+        # An instruction is counted once it retires
+        li      gp, 2
+        rdinstret t0
+        rdinstret t1
+        sub     t2, t1, t0
+        li      t3, 1
+        bne     t2, t3, fail
+        rdinstreth t0
+        bnez    t0, fail
+
+        # A write to a counter replaces the count of the writing instruction
+        li      gp, 3
+        csrw    0xB02, zero             # minstret
+        csrr    t0, 0xB02
+        bnez    t0, fail
+        csrw    0xB00, zero             # mcycle
+        csrr    t0, 0xB00               # counted only in the fetch of csrr
+        li      t1, 1
+        bne     t0, t1, fail
+        li      t0, -1
+        csrw    0xB80, zero
+        csrw    0xB00, t0               # the low half carries into the high half
+        nop
+        csrr    t0, 0xB80               # mcycleh
+        li      t1, 1
+        bne     t0, t1, fail
+
+        # time is the CLINT mtime, the load reads it two clocks before rdtime does
+        li      gp, 4
+        li      t0, 0x0200BFF8
+        lw      t1, 0(t0)
+        rdtime  t2
+        sub     t2, t2, t1
+        li      t3, 2
+        bne     t2, t3, fail
+        rdtimeh t0
+        bnez    t0, fail
+
+        # mcountinhibit stops a counter
+        li      gp, 5
+        csrwi   0x320, 5                # cycle and instret
+        rdcycle t0
+        rdinstret t1
+        rdcycle t2
+        rdinstret t3
+        csrwi   0x320, 0
+        bne     t0, t2, fail
+        bne     t1, t3, fail
+        csrr    t0, 0x320
+        bnez    t0, fail
+        csrwi   0x320, 2                # time cannot be inhibited
+        csrr    t0, 0x320
+        bnez    t0, fail
+
+        # The performance monitor counters count the event selected in mhpmevent
+        li      gp, 6
+        la      s3, scratch
+        li      t0, 3                   # loads
+        csrw    0x323, t0
+        li      t0, 4                   # stores
+        csrw    0x324, t0
+        li      t0, 6                   # taken branches
+        csrw    0x325, t0
+        li      t0, 7                   # branches not taken
+        csrw    0x326, t0
+        li      t0, 0x10B               # cycles in WriteCsr
+        csrw    0x327, t0
+        li      t0, 8                   # jumps
+        csrw    0x328, t0               # mhpmevent8
+        csrw    0xB03, zero
+        csrw    0xB04, zero
+        csrw    0xB05, zero
+        csrw    0xB06, zero
+        csrw    0xB07, zero
+        csrw    0xB08, zero
+        lw      t0, 0(s3)
+        lw      t0, 4(s3)
+        lb      t0, 1(s3)
+        sw      t0, 0(s3)
+        sh      t0, 4(s3)
+        beqz    zero, taken
+        j       fail
+taken:  bnez    zero, fail
+        bnez    zero, fail
+        jal     t0, jumped
+jumped: csrr    a1, 0xB07               # read everything first, as the checks are branches
+        csrr    a2, 0xB03
+        csrr    a3, 0xB04
+        csrr    a4, 0xB05
+        csrr    a5, 0xB06
+        csrr    a6, 0xB08
+        csrr    a7, 0xC03               # hpmcounter3
+        li      t1, 1                   # only the write of mhpmcounter8 is after mhpmcounter7 was written
+        bne     a1, t1, fail
+        li      t1, 3
+        bne     a2, t1, fail
+        li      t1, 2
+        bne     a3, t1, fail
+        li      t1, 1
+        bne     a4, t1, fail
+        li      t1, 2
+        bne     a5, t1, fail
+        li      t1, 1
+        bne     a6, t1, fail
+        li      t1, 3
+        bne     a7, t1, fail
+
+        # Exceptions are counted, and do not retire
+        li      gp, 7
+        li      t0, 9
+        csrw    0x323, t0
+        csrw    0xB03, zero
+        csrw    0xB02, zero
+        ebreak
+        csrr    a1, 0xB02               # the 8 instructions of the handler retired, the ebreak did not
+        csrr    a2, 0xB03
+        li      t1, 8
+        bne     a1, t1, fail
+        li      t1, 1
+        bne     a2, t1, fail
+
+        # The user level counters are read only
+        li      gp, 8
+        li      s1, 0
+        csrw    0xC00, zero
+        li      t1, 2
+        bne     s1, t1, fail
+
+        # Below machine mode the counters must be enabled in mcounteren, and for user mode in scounteren
+        li      gp, 9
+        csrwi   0x306, 1                # mcounteren: cycle only
+        li      t0, 0x1800
+        csrc    0x300, t0
+        li      t0, 0x800
+        csrs    0x300, t0               # MPP = S
+        la      t0, supervisor
+        csrw    0x341, t0
+        mret
+supervisor:
+        li      s1, 0
+        rdcycle t0
+        bnez    s1, fail
+        rdtime  t0
+        li      t1, 2
+        bne     s1, t1, fail
+        li      s1, 0
+        csrr    t0, 0xB00               # mcycle
+        bne     s1, t1, fail
+        li      s1, 0
+        rdcycle t0                      # scounteren is clear
+        li      t0, 0x100
+        csrc    0x100, t0               # SPP = U
+        la      t0, user
+        csrw    0x141, t0
+        sret
+user:
+        li      s1, 0
+        rdcycle t0
+        li      t1, 2
+        bne     s1, t1, fail
+        ecall
+        csrwi   0x106, 1                # scounteren: cycle only
+        li      t0, 0x1800
+        csrc    0x300, t0               # MPP = U
+        la      t0, user_enabled
+        csrw    0x341, t0
+        mret
+user_enabled:
+        li      s1, 0
+        rdcycle t0
+        bnez    s1, fail
+        rdinstret t0
+        li      t1, 2
+        bne     s1, t1, fail
+        ecall
+
+pass:   li      a0, 0
+        j       done
+
+fail:   mv      a0, gp
+done:   j       done
+
+        .align  2
+scratch:
+        .word   0x12345678
+        .word   0x9ABCDEF0
+
+handler:
+        csrr    s1, 0x342               # mcause
+        csrr    t6, 0x341
+        addi    t6, t6, 4
+        csrw    0x341, t6
+        addi    t6, s1, -8
+        srli    t6, t6, 1
+        bnez    t6, return
+        li      t6, 0x1800              # an ecall from S or U returns to machine mode
+        csrs    0x300, t6
+return: mret
\ No newline at end of file
@@ -0,0 +1,163 @@
+# CSR registry self check.
+#
+# The handler records mcause in s1 and mtval in s2 and skips the trapping
+# instruction. An ecall from supervisor or user mode returns to machine mode.
+# Run with:
+#   cargo run -- programs/csr_test.s -i 1000
+
+        li      a0, -1
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+
+        # The machine information registers are populated
+        li      gp, 1
+        csrr    t0, 0x301               # misa: RV32 ACIMSU
+        li      t1, 0x40141105
+        bne     t0, t1, fail
+        csrr    t0, 0xF14               # mhartid
+        bnez    t0, fail
+        csrr    t0, 0xF11               # mvendorid
+        bnez    t0, fail
+        csrr    t0, 0xF13               # mimpid
+        beqz    t0, fail
+
+        # Writing a read only CSR is illegal, but reading it with csrrs x0 is not
+        li      gp, 2
+        li      s1, 0
+        csrs    0xF14, zero
+        bnez    s1, fail
+        la      t0, write_mhartid
+write_mhartid:
+        csrw    0xF14, t0
+        li      t1, 2
+        bne     s1, t1, fail
+        lw      t1, 0(t0)
+        bne     s2, t1, fail
+
+        # Undefined CSRs are illegal to read or write
+        li      gp, 3
+        li      s1, 0
+        csrr    t0, 0x7C0
+        li      t1, 2
+        bne     s1, t1, fail
+        li      s1, 0
+        csrwi   0x7C0, 1
+        bne     s1, t1, fail
+
+        # misa cannot be changed
+        li      gp, 4
+        csrw    0x301, zero
+        csrr    t0, 0x301
+        li      t1, 0x40141105
+        bne     t0, t1, fail
+
+        # Bits which are not implemented read as zero
+        li      gp, 5
+        li      t1, -1
+        csrw    0x304, t1               # mie
+        csrr    t0, 0x304
+        li      t2, 0xAAA
+        bne     t0, t2, fail
+        csrw    0x304, zero
+        csrw    0x303, t1               # mideleg
+        csrr    t0, 0x303
+        li      t2, 0x222
+        bne     t0, t2, fail
+        csrw    0x303, zero
+        csrw    0x302, t1               # medeleg: not an ecall from machine mode
+        csrr    t0, 0x302
+        li      t2, 0xB3FF
+        bne     t0, t2, fail
+        csrw    0x302, zero
+        csrw    0x341, t1               # mepc: instructions are 2 byte aligned
+        csrr    t0, 0x341
+        li      t2, -2
+        bne     t0, t2, fail
+
+        # The machine interrupt pending bits are driven by the devices
+        li      gp, 6
+        csrw    0x344, t1               # mip
+        csrr    t0, 0x344
+        li      t2, 0x222
+        bne     t0, t2, fail
+        csrw    0x344, zero
+
+        # WARL fields keep a legal value
+        li      gp, 7
+        csrr    s3, 0x305
+        ori     t0, s3, 3               # mtvec mode 3 is reserved
+        csrw    0x305, t0
+        csrr    t0, 0x305
+        csrw    0x305, s3
+        andi    t0, t0, 3
+        li      t2, 1
+        bne     t0, t2, fail
+        li      t0, 0x1800
+        csrs    0x300, t0               # mstatus.MPP = M
+        li      t0, 0x800
+        csrc    0x300, t0               # MPP = 0b10 is reserved, so this is ignored
+        csrr    t0, 0x300
+        srli    t0, t0, 11
+        andi    t0, t0, 3
+        li      t2, 3
+        bne     t0, t2, fail
+
+        # Supervisor mode can use the supervisor CSRs but not the machine CSRs
+        li      gp, 8
+        li      t0, 0x1800
+        csrc    0x300, t0
+        li      t0, 0x800
+        csrs    0x300, t0               # MPP = S
+        la      t0, supervisor
+        csrw    0x341, t0
+        mret
+supervisor:
+        li      s1, 0
+        csrw    0x140, t0               # sscratch
+        csrr    t1, 0x140
+        bne     t0, t1, fail
+        csrr    t0, 0x100               # sstatus
+        bnez    s1, fail
+        csrr    t0, 0x300               # mstatus
+        li      t1, 2
+        bne     s1, t1, fail
+        li      s1, 0
+        csrr    t0, 0xF14               # mhartid
+        bne     s1, t1, fail
+
+        # User mode cannot use the supervisor CSRs
+        li      gp, 9
+        li      t0, 0x100
+        csrc    0x100, t0               # SPP = U
+        la      t0, user
+        csrw    0x141, t0
+        sret
+user:
+        li      s1, 0
+        csrr    t0, 0x140               # sscratch
+        li      t1, 2
+        bne     s1, t1, fail
+        li      s1, 0
+        csrw    0x180, zero             # satp
+        bne     s1, t1, fail
+        ecall
+
+pass:   li      a0, 0
+        j       done
+
+fail:   mv      a0, gp
+done:   j       done
+
+        .align  2
+handler:
+        csrr    s1, 0x342               # mcause
+        csrr    s2, 0x343               # mtval
+        csrr    t6, 0x341
+        addi    t6, t6, 4
+        csrw    0x341, t6
+        addi    t6, s1, -8
+        srli    t6, t6, 1
+        bnez    t6, return
+        li      t6, 0x1800              # an ecall from S or U returns to machine mode
+        csrs    0x300, t6
+return: mret
\ No newline at end of file
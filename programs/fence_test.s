@@ -0,0 +1,29 @@
+# FENCE and FENCE.I self check, patching an instruction and running it after a
+# fence.i.
+# Run with:
+#   cargo run -- programs/fence_test.s --stop-on-ecall
+
+        # Fences do not change any register
+        li      gp, 1
+        li      t0, 5
+        fence
+        fence   rw, w
+        fence.tso
+        li      t1, 5
+        bne     t0, t1, fail
+
+        # A patched instruction runs after fence.i
+        li      gp, 2
+        la      t0, patch
+        li      t1, 0x02A00513          # addi a0, zero, 42
+        sw      t1, 0(t0)
+        fence.i
+patch:  li      a0, 1
+        li      t1, 42
+        bne     a0, t1, fail
+
+pass:   li      a0, 0
+        ecall
+
+fail:   mv      a0, gp
+        ecall
\ No newline at end of file
@@ -0,0 +1,23 @@
+# Compute the first ten fibonacci numbers into memory at 0x1000 and leave
+# the tenth in a0. Run with:
+#   cargo run -- programs/fibonacci.s --stop-on-ecall
+
+        li      t0, 10              # numbers left to compute
+        la      t1, results
+        li      a0, 0
+        li      a1, 1
+
+loop:   sw      a0, 0(t1)
+        add     a2, a0, a1
+        mv      a0, a1
+        mv      a1, a2
+        addi    t1, t1, 4
+        addi    t0, t0, -1
+        bnez    t0, loop
+
+        lw      a0, -4(t1)
+        ecall
+
+        .org    0x1000
+results:
+        .word   0, 0, 0, 0, 0, 0, 0, 0, 0, 0
\ No newline at end of file
@@ -0,0 +1,82 @@
+# Illegal instruction self check. The handler records mcause in s1 and mtval in
+# s3, and skips over the illegal instruction (2 bytes for a compressed parcel, 4
+# otherwise).
+# Run with:
+#   cargo run -- programs/illegal_test.s --stop-on-ecall
+
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+
+        # The all zero parcel is illegal, with the parcel as the trap value
+        li      gp, 1
+        li      s3, -1
+        .half   0x0000
+        li      t0, 2
+        bne     s1, t0, fail
+        bnez    s3, fail
+
+        # Shifts with a reserved funct7 do not run
+        li      gp, 2
+        li      a0, 1
+        .word   0x02051513              # slli a0, a0, 0 with funct7 0000001
+        li      t0, 0x02051513
+        bne     s3, t0, fail
+        .word   0x42055513              # srai a0, a0, 0 with funct7 0100001
+        li      t0, 0x42055513
+        bne     s3, t0, fail
+        .word   0x04055513              # srli a0, a0, 0 with funct7 0000010
+        li      t0, 0x04055513
+        bne     s3, t0, fail
+        li      t0, 1
+        bne     a0, t0, fail
+
+        # Reserved funct3 of branches, loads and the system opcode
+        li      gp, 3
+        .word   0x00002063              # branch with funct3 010
+        li      t0, 0x00002063
+        bne     s3, t0, fail
+        .word   0x0000B503              # ld a0, 0(ra)
+        li      t0, 0x0000B503
+        bne     s3, t0, fail
+        .word   0x00004073              # system with funct3 100
+        li      t0, 0x00004073
+        bne     s3, t0, fail
+
+        # ecall with a nonzero rd, and an unknown opcode
+        li      gp, 4
+        .word   0x000000F3              # ecall with rd = ra
+        li      t0, 0x000000F3
+        bne     s3, t0, fail
+        li      t0, 2
+        bne     s1, t0, fail
+        .word   0xFFFFFFFF
+        li      t0, -1
+        bne     s3, t0, fail
+
+        # A reserved compressed encoding (c.addi4spn with a zero immediate)
+        li      gp, 5
+        .half   0x0004
+        li      t0, 0x0004
+        bne     s3, t0, fail
+
+pass:   li      a0, 0
+        csrw    0x305, zero
+        ecall
+
+fail:   mv      a0, gp
+        csrw    0x305, zero
+        ecall
+
+        .align  2
+handler:
+        csrr    s1, 0x342               # mcause
+        csrr    s3, 0x343               # mtval
+        csrr    t6, 0x341               # mepc
+        andi    t5, s3, 3
+        li      t4, 3
+        bne     t5, t4, compressed
+        addi    t6, t6, 2
+compressed:
+        addi    t6, t6, 2
+        csrw    0x341, t6
+        mret
\ No newline at end of file
@@ -0,0 +1,96 @@
+# Memory map self check, for a board with the CLINT moved and no PLIC.
+#
+# The handler records mcause in s1 and mtval in s2 and skips the trapping
+# instruction, or for an instruction access fault resumes at s9.
+# Run with:
+#   cargo run -- programs/memmap_test.s -i 1000 -M ram512k@0,ram1m@0x80000000,rom1m@0x20000000,clint@0x40000000
+
+        li      a0, -1
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+
+        # Memory placed high up can be used up to its last word
+        li      gp, 1
+        li      s3, 0x80000000
+        li      t0, 0x1234
+        sw      t0, 0(s3)
+        lw      t1, 0(s3)
+        bne     t0, t1, fail
+        li      t2, 0xFFFFC
+        add     t2, s3, t2
+        sw      t0, 0(t2)
+        lw      t1, 0(t2)
+        bne     t0, t1, fail
+
+        # Loads and stores outside every region raise access faults
+        li      gp, 2
+        li      s1, 0
+        li      t2, 0x100000
+        add     t2, s3, t2              # just past the 1m ram
+        lw      t1, 0(t2)
+        li      t0, 5
+        bne     s1, t0, fail
+        bne     s2, t2, fail
+        li      s1, 0
+        li      t2, 0x80000             # the 512k ram does not alias above itself
+        sw      t0, 0(t2)
+        li      t0, 7
+        bne     s1, t0, fail
+        bne     s2, t2, fail
+
+        # So do fetches
+        li      gp, 3
+        li      s1, 0
+        la      s9, fetched
+        li      t2, 0x10000000
+        jr      t2
+fetched:
+        li      t0, 1
+        bne     s1, t0, fail
+        bne     s2, t2, fail
+
+        # The ROM reads as zero and ignores writes, without faulting
+        li      gp, 4
+        li      s1, 0
+        li      t2, 0x20000000
+        sw      t0, 0(t2)
+        lw      t1, 0(t2)
+        bnez    s1, fail
+        bnez    t1, fail
+
+        # The CLINT is at its new base, and nothing is left at the default CLINT and PLIC bases
+        li      gp, 5
+        li      t2, 0x4000BFF8          # mtime
+        lw      t0, 0(t2)
+        lw      t1, 0(t2)
+        bgeu    t0, t1, fail
+        li      s1, 0
+        li      t2, 0x0200BFF8
+        lw      t0, 0(t2)
+        li      t0, 5
+        bne     s1, t0, fail
+        li      s1, 0
+        li      t2, 0x0C000004
+        lw      t0, 0(t2)
+        li      t0, 5
+        bne     s1, t0, fail
+
+pass:   li      a0, 0
+        j       done
+
+fail:   mv      a0, gp
+done:   j       done
+
+        .align  2
+handler:
+        csrr    s1, 0x342               # mcause
+        csrr    s2, 0x343               # mtval
+        li      t6, 1
+        beq     s1, t6, fetch_fault
+        csrr    t6, 0x341
+        addi    t6, t6, 4
+        csrw    0x341, t6
+        mret
+fetch_fault:
+        csrw    0x341, s9
+        mret
\ No newline at end of file
@@ -0,0 +1,90 @@
+# Misaligned access self check for the default trap policy. The handler records
+# mcause in s1 and mtval in s3, and skips over the trapping instruction.
+# Run with:
+#   cargo run -- programs/misaligned_test.s --stop-on-ecall
+
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+        la      s0, data
+
+        # Aligned accesses and bytes do not trap
+        li      gp, 1
+        li      s1, 0
+        lw      t0, 0(s0)
+        lh      t0, 2(s0)
+        lbu     t0, 1(s0)
+        sb      t0, 1(s0)
+        bnez    s1, fail
+
+        # Misaligned loads trap with the address as the trap value
+        li      gp, 2
+        li      a0, 7
+        lw      a0, 1(s0)
+        li      t0, 4
+        bne     s1, t0, fail
+        addi    t0, s0, 1
+        bne     s3, t0, fail
+        li      t0, 7
+        bne     a0, t0, fail
+        li      s1, 0
+        lhu     a0, 3(s0)
+        li      t0, 4
+        bne     s1, t0, fail
+
+        # Misaligned stores trap and leave memory alone
+        li      gp, 3
+        li      s1, 0
+        li      t1, -1
+        sw      t1, 2(s0)
+        li      t0, 6
+        bne     s1, t0, fail
+        addi    t0, s0, 2
+        bne     s3, t0, fail
+        lw      t0, 0(s0)
+        li      t2, 0x44332211
+        bne     t0, t2, fail
+        li      s1, 0
+        sh      t1, 5(s0)
+        li      t0, 6
+        bne     s1, t0, fail
+
+        # Misaligned atomics trap
+        li      gp, 4
+        addi    t2, s0, 2
+        li      s1, 0
+        lr.w    t0, (t2)
+        li      t0, 4
+        bne     s1, t0, fail
+        li      s1, 0
+        amoadd.w t0, t1, (t2)
+        li      t0, 6
+        bne     s1, t0, fail
+
+        # The low bit of a jalr target is cleared
+        li      gp, 5
+        la      t0, landed
+        addi    t0, t0, 1
+        jalr    t0
+        j       fail
+landed:
+        li      s1, 0
+
+pass:   li      a0, 0
+        csrw    0x305, zero
+        ecall
+
+fail:   mv      a0, gp
+        csrw    0x305, zero
+        ecall
+
+        .align  2
+handler:
+        csrr    s1, 0x342               # mcause
+        csrr    s3, 0x343               # mtval
+        csrr    t6, 0x341               # mepc
+        addi    t6, t6, 4
+        csrw    0x341, t6
+        mret
+
+        .align  2
+data:   .word   0x44332211, 0x88776655
\ No newline at end of file
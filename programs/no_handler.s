@@ -0,0 +1,8 @@
+# Runs an illegal instruction before installing a trap handler, so the trap has
+# nowhere to go and the simulation faults. Run with:
+#   cargo run -- programs/no_handler.s
+
+        li      a0, 1
+        .word   0                       # illegal
+        li      a0, 0
+        ecall
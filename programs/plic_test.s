@@ -0,0 +1,133 @@
+# PLIC self check. The handler claims a source, records its id in s1 and mcause
+# in s2, and completes it. The interrupts are raised from the command line:
+#   cargo run -- programs/plic_test.s --stop-on-ecall -q 3@200 -q 5@800 -q 6@1400 -q 7@1400
+
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+        li      s0, 0x0C000000          # PLIC base
+        li      s3, 0x0C200000          # threshold, claim at +4
+        li      s4, 0x0C001000          # pending
+        li      s5, 0x0C002000          # enable
+
+        # Priorities are clamped to 7, source 0 and its enable bit are hardwired to zero
+        li      gp, 1
+        li      t0, 9
+        sw      t0, 12(s0)
+        lw      t1, 12(s0)
+        li      t0, 7
+        bne     t0, t1, fail
+        sw      t0, 0(s0)
+        lw      t1, 0(s0)
+        bnez    t1, fail
+        li      t0, -1
+        sw      t0, 0(s5)
+        lw      t1, 0(s5)
+        li      t0, -2
+        bne     t0, t1, fail
+        sw      zero, 0(s5)
+        sw      zero, 0(s3)
+
+        # A raised source is pending but does not interrupt while disabled
+        li      gp, 2
+        li      t0, 0x800
+        csrs    0x304, t0               # mie.MEIE
+        csrsi   0x300, 8                # mstatus.MIE
+        li      t1, 1000
+wait3:  lw      t2, 0(s4)
+        andi    t2, t2, 8
+        bnez    t2, raised3
+        addi    t1, t1, -1
+        bnez    t1, wait3
+        j       fail
+raised3:
+        csrr    t0, 0x344               # mip
+        srli    t0, t0, 11              # mip.MEIP
+        andi    t0, t0, 1
+        bnez    t0, fail
+
+        # Enabling the source takes the external interrupt and the claim returns its id
+        li      gp, 3
+        li      s1, 0
+        li      t0, 8
+        sw      t0, 0(s5)          # enable source 3
+        nop
+        li      t0, 3
+        bne     s1, t0, fail
+        li      t0, 0x8000000B
+        bne     s2, t0, fail
+        lw      t0, 0(s4)
+        bnez    t0, fail
+
+        # A source at or below the threshold does not interrupt until the threshold is lowered
+        li      gp, 4
+        li      s1, 0
+        li      t0, 2
+        sw      t0, 20(s0)              # source 5 priority
+        sw      t0, 0(s3)               # threshold
+        li      t0, 0x28
+        sw      t0, 0(s5)          # enable sources 3 and 5
+        li      t1, 1000
+wait5:  lw      t2, 0(s4)
+        andi    t2, t2, 0x20
+        bnez    t2, raised5
+        addi    t1, t1, -1
+        bnez    t1, wait5
+        j       fail
+raised5:
+        bnez    s1, fail
+        li      t0, 1
+        sw      t0, 0(s3)
+        nop
+        li      t0, 5
+        bne     s1, t0, fail
+
+        # The highest priority source is claimed first
+        li      gp, 5
+        li      s1, 0
+        sw      zero, 0(s3)
+        li      t0, 1
+        sw      t0, 24(s0)              # source 6 priority
+        li      t0, 3
+        sw      t0, 28(s0)              # source 7 priority
+        li      t0, 0xC0
+        sw      t0, 0(s5)          # enable sources 6 and 7
+        csrci   0x300, 8
+        li      t1, 1000
+wait67: lw      t2, 0(s4)
+        andi    t2, t2, 0xC0
+        bnez    t2, raised67
+        addi    t1, t1, -1
+        bnez    t1, wait67
+        j       fail
+raised67:
+        lw      t0, 4(s3)               # claim without an interrupt
+        li      t1, 7
+        bne     t0, t1, fail
+        lw      t0, 4(s3)
+        li      t1, 6
+        bne     t0, t1, fail
+        lw      t0, 4(s3)
+        bnez    t0, fail
+        li      t0, 7
+        sw      t0, 4(s3)               # complete
+        li      t0, 6
+        sw      t0, 4(s3)
+        lw      t0, 0(s4)
+        bnez    t0, fail
+        csrr    t0, 0x344
+        bnez    t0, fail
+
+pass:   li      a0, 0
+        csrw    0x305, zero
+        ecall
+
+fail:   mv      a0, gp
+        csrw    0x305, zero
+        ecall
+
+        .align  2
+handler:
+        lw      s1, 4(s3)               # claim
+        csrr    s2, 0x342               # mcause
+        sw      s1, 4(s3)               # complete
+        mret
\ No newline at end of file
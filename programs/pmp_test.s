@@ -0,0 +1,193 @@
+# PMP self check.
+#
+# The handler records mcause in s1 and mtval in s2 and skips the trapping
+# instruction. An ecall from user mode returns to machine mode, as does an
+# instruction access fault, which resumes at s9.
+#
+# The protected layout is:
+#   entry 0  NA4    0x20000            read only
+#   entry 1  NAPOT  0x20000 - 0x20FFF  read and write
+#   entry 3  TOR    0x00000 - 0x0FFFF  read and execute (the program)
+# Run with:
+#   cargo run -- programs/pmp_test.s -i 1000
+
+        li      a0, -1
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+        li      s3, 0x20000
+
+        # Reserved configurations are not kept, and the address registers hold any value
+        li      gp, 1
+        li      t0, 0x7F62              # entry 4 write only, entry 5 with the reserved bits set
+        csrw    0x3A1, t0               # pmpcfg1
+        csrr    t0, 0x3A1
+        li      t1, 0x1F00
+        bne     t0, t1, fail
+        csrw    0x3A1, zero
+        li      t0, 0x12345678
+        csrw    0x3B4, t0               # pmpaddr4
+        csrr    t1, 0x3B4
+        bne     t0, t1, fail
+
+        # Once any entry is enabled, user mode may not use memory no entry matches
+        li      gp, 2
+        li      t0, 0x8000              # 0x20000 >> 2
+        csrw    0x3B0, t0               # pmpaddr0
+        li      t0, 0x11                # NA4, R
+        csrw    0x3A0, t0               # pmpcfg0
+        li      s1, 0
+        la      s9, after_user_fetch
+        la      t0, user_fetch
+        csrw    0x341, t0
+        li      t0, 0x1800
+        csrc    0x300, t0               # MPP = U
+        mret
+user_fetch:
+        j       fail
+after_user_fetch:
+        li      t1, 1                   # instruction access fault
+        bne     s1, t1, fail
+        la      t0, user_fetch
+        bne     s2, t0, fail
+
+        # Entries are matched in order, the lowest numbered match decides
+        li      gp, 3
+        li      t0, 0x81FF              # 4KiB at 0x20000
+        csrw    0x3B1, t0
+        csrw    0x3B2, zero
+        li      t0, 0x4000              # top of 0x10000
+        csrw    0x3B3, t0
+        li      t0, 0x0D001B11          # TOR RX, OFF, NAPOT RW, NA4 R
+        csrw    0x3A0, t0
+        csrr    t1, 0x3A0
+        bne     t0, t1, fail
+        li      s1, 0
+        la      t0, user
+        csrw    0x341, t0
+        li      t0, 0x1800
+        csrc    0x300, t0
+        mret
+user:
+        lw      t0, 0(s3)
+        bnez    s1, fail
+        sw      t0, 0(s3)               # entry 0 is read only
+        li      t1, 7
+        bne     s1, t1, fail
+        bne     s2, s3, fail
+        li      s1, 0
+        sw      t0, 4(s3)               # entry 1 is writable
+        li      t1, 0xFFC
+        add     t1, s3, t1
+        sw      t0, 0(t1)
+        bnez    s1, fail
+        li      t1, 0x1000              # the word after entry 1 is not matched
+        add     t1, s3, t1
+        lw      t0, 0(t1)
+        li      t2, 5
+        bne     s1, t2, fail
+        bne     s2, t1, fail
+        li      s1, 0
+        lw      t0, -4(s3)              # nor is the word before it
+        bne     s1, t2, fail
+        ecall
+
+        # User mode may not execute from an entry without X
+        li      gp, 4
+        li      s1, 0
+        la      s9, after_data_fetch
+        la      t0, user_jump
+        csrw    0x341, t0
+        li      t0, 0x1800
+        csrc    0x300, t0
+        mret
+user_jump:
+        jr      s3
+after_data_fetch:
+        li      t1, 1
+        bne     s1, t1, fail
+        bne     s2, s3, fail
+
+        # Machine mode is not held to unlocked entries
+        li      gp, 5
+        li      s1, 0
+        sw      zero, 0(s3)
+        li      t1, 0x1000
+        add     t1, s3, t1
+        lw      t0, 0(t1)
+        bnez    s1, fail
+
+        # Machine mode loads with MPRV set are checked at the privilege in MPP
+        li      gp, 6
+        li      t0, 0x1800
+        csrc    0x300, t0               # MPP = U
+        li      t0, 0x20000
+        csrs    0x300, t0               # MPRV
+        lw      t0, 0(t1)
+        li      t0, 0x20000
+        csrc    0x300, t0
+        li      t2, 5
+        bne     s1, t2, fail
+        bne     s2, t1, fail
+
+        # A locked entry applies to machine mode, and cannot be changed
+        li      gp, 7
+        li      s1, 0
+        csrsi   0x3A0, 0                # no change
+        li      t0, 0x80
+        csrs    0x3A0, t0               # lock entry 0
+        sw      zero, 0(s3)
+        li      t1, 7
+        bne     s1, t1, fail
+        li      t0, 0xFF
+        csrc    0x3A0, t0               # ignored
+        csrr    t0, 0x3A0
+        li      t1, 0x0D001B91
+        bne     t0, t1, fail
+        csrw    0x3B0, zero             # ignored
+        csrr    t0, 0x3B0
+        li      t1, 0x8000
+        bne     t0, t1, fail
+
+        # Locking a TOR entry also locks the address register below it
+        li      gp, 8
+        li      t0, 0x80000000
+        csrs    0x3A0, t0               # lock entry 3, which still permits the program
+        li      t0, 0x100
+        csrw    0x3B2, t0               # ignored
+        csrw    0x3B3, t0               # ignored
+        csrr    t0, 0x3B2
+        bnez    t0, fail
+        csrr    t0, 0x3B3
+        li      t1, 0x4000
+        bne     t0, t1, fail
+        li      t0, 0x1B00
+        csrc    0x3A0, t0               # entry 1 is not locked
+        csrr    t0, 0x3A0
+        li      t1, 0x8D000091
+        bne     t0, t1, fail
+
+pass:   li      a0, 0
+        j       done
+
+fail:   mv      a0, gp
+done:   j       done
+
+        .align  2
+handler:
+        csrr    s1, 0x342               # mcause
+        csrr    s2, 0x343               # mtval
+        li      t6, 1
+        beq     s1, t6, fetch_fault
+        csrr    t6, 0x341
+        addi    t6, t6, 4
+        csrw    0x341, t6
+        li      t6, 8
+        bne     s1, t6, return
+        li      t6, 0x1800              # an ecall from U returns to machine mode
+        csrs    0x300, t6
+return: mret
+fetch_fault:
+        csrw    0x341, s9
+        li      t6, 0x1800
+        csrs    0x300, t6
+        mret
\ No newline at end of file
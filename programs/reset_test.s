@@ -0,0 +1,106 @@
+# Reset self check, booted by programs/boot_rom.s from a rom at 0x20000000. The
+# first boot leaves state behind and waits in user mode for the warm reset
+# scheduled on the command line, and the second boot checks what it cleared and
+# what it kept. The boot rom sets a0 to -1, so it stays -1 if the program never
+# finishes.
+# Run with:
+#   cargo run -- programs/reset_test.s -M ram512k@0,ram512k@0x80000,rom1m@0x20000000,clint@0x02000000
+#                -R 0x20000000 -b programs/boot_rom.s -w 1000 -i 2000
+
+        li      s3, 0x02004000          # mtimecmp
+        li      s4, 0x0200BFF8          # mtime
+        li      t0, 0xFFFFC
+        lw      s1, 0(t0)               # boots counted by the rom
+        li      t1, 2
+        beq     s1, t1, warm_boot
+
+        # The hart powers on at the reset vector, running the rom
+        li      gp, 1
+        li      t1, 1
+        bne     s1, t1, fail
+        li      t0, 0x20000000
+        bne     s0, t0, fail
+
+        # The rom was programmed before power on, but ignores stores while running
+        li      gp, 2
+        lw      t1, 0(t0)
+        beqz    t1, fail
+        sw      zero, 0(t0)
+        lw      t2, 0(t0)
+        bne     t1, t2, fail
+
+        # Leave state behind in memory, registers, CSRs and the CLINT, then wait in user mode
+        li      gp, 3
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+        csrw    0x340, t0               # mscratch
+        li      t0, 0xFFFF8
+        li      t1, 1234
+        sw      t1, 0(t0)
+        li      s2, 0x5555
+        li      t0, 0x1234
+        csrw    0x3B0, t0               # pmpaddr0
+        li      t0, 0x80
+        csrw    0x3A0, t0               # pmpcfg0: entry 0 locked and off
+        sw      zero, 4(s3)
+        li      t0, 5
+        sw      t0, 0(s3)               # mtimecmp = 5
+        li      t0, 0x1800
+        csrc    0x300, t0               # MPP = U
+        la      t0, user
+        csrw    0x341, t0
+        mret
+user:
+        j       user
+
+warm_boot:
+        # The warm reset starts the rom again with the registers cleared
+        li      gp, 4
+        bnez    s2, fail
+        li      t0, 0x20000000
+        bne     s0, t0, fail
+
+        # Memory keeps its contents
+        li      gp, 5
+        li      t0, 0xFFFF8
+        lw      t1, 0(t0)
+        li      t2, 1234
+        bne     t1, t2, fail
+
+        # The hart is back in machine mode with its CSRs at their power on values, and the pmp entry unlocked
+        li      gp, 6
+        csrr    t0, 0x305
+        bnez    t0, fail
+        csrr    t0, 0x340
+        bnez    t0, fail
+        csrr    t0, 0x300               # mstatus
+        bnez    t0, fail
+        csrr    t0, 0x3A0
+        bnez    t0, fail
+        li      t1, 0x4321
+        csrw    0x3B0, t1
+        csrr    t0, 0x3B0
+        bne     t0, t1, fail
+
+        # The CLINT is reset and the counters start again from 0
+        li      gp, 7
+        lw      t0, 0(s3)
+        lw      t1, 4(s3)
+        li      t2, -1
+        bne     t0, t2, fail
+        bne     t1, t2, fail
+        lw      t0, 0(s4)
+        csrr    t1, 0xB00               # mcycle
+        li      t2, 1000
+        bgeu    t0, t2, fail
+        bgeu    t1, t2, fail
+
+pass:   li      a0, 0
+        j       done
+
+fail:   mv      a0, gp
+done:   j       done
+
+        .align  2
+handler:
+        j       fail
\ No newline at end of file
@@ -0,0 +1,116 @@
+# RV32A self check.
+# Run with:
+#   cargo run -- programs/rv32a_test.s --stop-on-ecall
+
+        la      s0, data
+
+        # LR/SC pair succeeds
+        li      gp, 1
+        li      t0, 5
+        sw      t0, 0(s0)
+        lr.w    t1, (s0)
+        bne     t1, t0, fail
+        addi    t1, t1, 1
+        sc.w    t2, t1, (s0)
+        bnez    t2, fail
+        lw      t3, 0(s0)
+        li      t4, 6
+        bne     t3, t4, fail
+
+        # SC without a reservation fails and leaves memory alone
+        li      gp, 2
+        li      t1, 99
+        sc.w    t2, t1, (s0)
+        li      t4, 1
+        bne     t2, t4, fail
+        lw      t3, 0(s0)
+        li      t4, 6
+        bne     t3, t4, fail
+
+        # A store to the reserved word breaks the reservation
+        li      gp, 3
+        lr.w.aq t1, (s0)
+        sw      zero, 0(s0)
+        sc.w.rl t2, t1, (s0)
+        beqz    t2, fail
+
+        # SC to a different address than the reservation fails
+        li      gp, 4
+        lr.w    t1, (s0)
+        addi    t0, s0, 4
+        sc.w    t2, t1, (t0)
+        beqz    t2, fail
+
+        # Swap and add
+        li      gp, 5
+        li      t0, 10
+        sw      t0, 0(s0)
+        li      t1, 20
+        amoswap.w t2, t1, (s0)
+        bne     t2, t0, fail
+        lw      t3, 0(s0)
+        bne     t3, t1, fail
+        li      t1, 3
+        amoadd.w.aqrl t2, t1, (s0)
+        li      t4, 20
+        bne     t2, t4, fail
+        lw      t3, 0(s0)
+        li      t4, 23
+        bne     t3, t4, fail
+
+        # Logic
+        li      gp, 6
+        li      t0, 0b1100
+        sw      t0, 0(s0)
+        li      t1, 0b1010
+        amoxor.w t2, t1, (s0)
+        bne     t2, t0, fail
+        lw      t3, 0(s0)
+        li      t4, 0b0110
+        bne     t3, t4, fail
+        amoor.w zero, t1, (s0)
+        lw      t3, 0(s0)
+        li      t4, 0b1110
+        bne     t3, t4, fail
+        amoand.w zero, t1, (s0)
+        lw      t3, 0(s0)
+        bne     t3, t1, fail
+
+        # Signed and unsigned min/max
+        li      gp, 7
+        li      t0, -1
+        sw      t0, 0(s0)
+        li      t1, 1
+        amomin.w t2, t1, (s0)
+        bne     t2, t0, fail
+        lw      t3, 0(s0)
+        bne     t3, t0, fail
+        amominu.w zero, t1, (s0)
+        lw      t3, 0(s0)
+        bne     t3, t1, fail
+        amomaxu.w zero, t0, (s0)
+        lw      t3, 0(s0)
+        bne     t3, t0, fail
+        amomax.w zero, t1, (s0)
+        lw      t3, 0(s0)
+        bne     t3, t1, fail
+
+        # rd equal to rs2 gets the old value
+        li      gp, 8
+        li      t0, 7
+        sw      t0, 0(s0)
+        li      t1, 1
+        amoadd.w t1, t1, (s0)
+        bne     t1, t0, fail
+        lw      t3, 0(s0)
+        li      t4, 8
+        bne     t3, t4, fail
+
+pass:   li      a0, 0
+        ecall
+
+fail:   mv      a0, gp
+        ecall
+
+        .align  2
+data:   .word   0, 0
\ No newline at end of file
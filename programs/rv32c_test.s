@@ -0,0 +1,113 @@
+# RV32C self check. The built-in assembler only generates 32 bit instructions,
+# so the compressed instructions are written as .half parcels with their
+# assembly alongside. A skipped parcel of zero is the illegal instruction, so
+# running it faults the simulation.
+# Run with:
+#   cargo run -- programs/rv32c_test.s --stop-on-ecall
+
+        la      sp, stack
+
+        # Register arithmatic, with the 32 bit instructions between the parcels
+        # straddling word boundaries
+        li      gp, 1
+        .half   0x4515                  # c.li      a0, 5
+        .half   0x050D                  # c.addi    a0, 3
+        li      t0, 8
+        bne     a0, t0, fail
+        .half   0x85AA                  # c.mv      a1, a0
+        .half   0x95AA                  # c.add     a1, a0
+        li      t0, 16
+        bne     a1, t0, fail
+        .half   0x8D89                  # c.sub     a1, a0
+        bne     a1, a0, fail
+
+        # Shifts and immediates
+        li      gp, 2
+        .half   0x0592                  # c.slli    a1, 4
+        .half   0x8189                  # c.srli    a1, 2
+        li      t0, 32
+        bne     a1, t0, fail
+        .half   0x767D                  # c.lui     a2, 0xfffff
+        .half   0x8611                  # c.srai    a2, 4
+        li      t0, 0xFFFFFF00
+        bne     a2, t0, fail
+        .half   0x9A41                  # c.andi    a2, -16
+        bne     a2, t0, fail
+
+        # Logic
+        li      gp, 3
+        .half   0x8DB1                  # c.xor     a1, a2
+        li      t0, 0xFFFFFF20
+        bne     a1, t0, fail
+        .half   0x8DD1                  # c.or      a1, a2
+        bne     a1, t0, fail
+        .half   0x8DF1                  # c.and     a1, a2
+        bne     a1, a2, fail
+
+        # Loads and stores
+        li      gp, 4
+        .half   0xC42A                  # c.swsp    a0, 8(sp)
+        .half   0x46A2                  # c.lwsp    a3, 8(sp)
+        bne     a3, a0, fail
+        .half   0x0818                  # c.addi4spn a4, sp, 16
+        addi    t0, sp, 16
+        bne     a4, t0, fail
+        .half   0xC34C                  # c.sw      a1, 4(a4)
+        .half   0x435C                  # c.lw      a5, 4(a4)
+        bne     a5, a1, fail
+        lw      t0, 20(sp)
+        bne     t0, a1, fail
+
+        # Stack pointer adjustment
+        li      gp, 5
+        mv      t1, sp
+        .half   0x7139                  # c.addi16sp sp, -64
+        addi    t0, t1, -64
+        bne     sp, t0, fail
+        .half   0x6121                  # c.addi16sp sp, 64
+        bne     sp, t1, fail
+
+        # Jumps link to the following parcel
+        li      gp, 6
+jal_at: auipc   t0, 0
+        .half   0x2011                  # c.jal     4
+        .half   0x0000
+        addi    t0, t0, 6
+        bne     ra, t0, fail
+        la      t1, jalr_to
+        .half   0x9302                  # c.jalr    t1
+jalr_ra:
+        .half   0x0000
+        .half   0x0000
+jalr_to:
+        la      t0, jalr_ra
+        bne     ra, t0, fail
+        .half   0xA011                  # c.j       4
+        .half   0x0000
+        la      t1, jr_to
+        .half   0x8302                  # c.jr      t1
+        .half   0x0000
+jr_to:
+
+        # Branches
+        li      gp, 7
+        li      a0, 0
+        .half   0xC111                  # c.beqz    a0, 4
+        .half   0x0000
+        .half   0xE111                  # c.bnez    a0, 4
+        .half   0x0001                  # c.nop
+        li      a0, 1
+        .half   0xE111                  # c.bnez    a0, 4
+        .half   0x0000
+        .half   0xC111                  # c.beqz    a0, 4
+        .half   0x0001                  # c.nop
+
+pass:   li      a0, 0
+        ecall
+
+fail:   mv      a0, gp
+        ecall
+
+        .align  2
+        .space  64
+stack:  .space  64
\ No newline at end of file
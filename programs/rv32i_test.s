@@ -0,0 +1,113 @@
+# RV32I self check.
+# Run with:
+#   cargo run -- programs/rv32i_test.s --stop-on-ecall
+
+        li      sp, 0x8000
+
+        # Arithmatic
+        li      gp, 1
+        li      t0, 7
+        li      t1, -3
+        add     t2, t0, t1
+        li      t3, 4
+        bne     t2, t3, fail
+
+        li      gp, 2
+        sub     t2, t0, t1
+        li      t3, 10
+        bne     t2, t3, fail
+
+        li      gp, 3
+        slt     t2, t1, t0
+        beqz    t2, fail
+        sltu    t2, t1, t0
+        bnez    t2, fail
+
+        li      gp, 4
+        li      t0, 0x80000000
+        srai    t2, t0, 4
+        li      t3, 0xF8000000
+        bne     t2, t3, fail
+        srli    t2, t0, 4
+        li      t3, 0x08000000
+        bne     t2, t3, fail
+        slli    t2, t3, 4
+        bne     t2, t0, fail
+
+        li      gp, 5
+        li      t0, 0x0F0F
+        li      t1, 0x00FF
+        and     t2, t0, t1
+        li      t3, 0x000F
+        bne     t2, t3, fail
+        or      t2, t0, t1
+        li      t3, 0x0FFF
+        bne     t2, t3, fail
+        xor     t2, t0, t1
+        li      t3, 0x0FF0
+        bne     t2, t3, fail
+
+        # Branches
+        li      gp, 6
+        li      t0, 5
+        li      t1, 5
+        bne     t0, t1, fail
+        beq     t0, t1, branch_ok
+        j       fail
+branch_ok:
+        li      gp, 7
+        li      t1, -1
+        blt     t0, t1, fail
+        bge     t1, t0, fail
+        bltu    t1, t0, fail
+        bgeu    t0, t1, fail
+
+        # Loads and stores
+        li      gp, 8
+        li      t0, 0x12345678
+        sw      t0, 0(sp)
+        lw      t1, 0(sp)
+        bne     t0, t1, fail
+
+        li      gp, 9
+        li      t0, 0xFF
+        sb      t0, 4(sp)
+        lb      t1, 4(sp)
+        li      t2, -1
+        bne     t1, t2, fail
+        lbu     t1, 4(sp)
+        bne     t1, t0, fail
+        lw      t1, 0(sp)
+        li      t2, 0x12345678
+        bne     t1, t2, fail
+
+        li      gp, 10
+        li      t0, 0x8001
+        sh      t0, 8(sp)
+        lh      t1, 8(sp)
+        li      t2, 0xFFFF8001
+        bne     t1, t2, fail
+        lhu     t1, 8(sp)
+        bne     t1, t0, fail
+
+        # Jumps
+        li      gp, 11
+        call    function
+        li      t1, 42
+        bne     a1, t1, fail
+
+        li      gp, 12
+        auipc   t0, 0
+        la      t1, here
+here:   addi    t0, t0, 12
+        bne     t0, t1, fail
+
+pass:   li      a0, 0
+        ecall
+
+fail:   mv      a0, gp
+        ecall
+
+function:
+        li      a1, 42
+        ret
\ No newline at end of file
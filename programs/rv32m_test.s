@@ -0,0 +1,65 @@
+# RV32M self check.
+# Run with:
+#   cargo run -- programs/rv32m_test.s --stop-on-ecall
+
+        li      gp, 1
+        li      t0, -7
+        li      t1, 3
+        mul     t2, t0, t1
+        li      t3, -21
+        bne     t2, t3, fail
+
+        li      gp, 2
+        li      t0, 0x80000000
+        li      t1, 0x80000000
+        mulh    t2, t0, t1
+        li      t3, 0x40000000
+        bne     t2, t3, fail
+        mulhu   t2, t0, t1
+        bne     t2, t3, fail
+        mulhsu  t2, t0, t1
+        li      t3, 0xC0000000
+        bne     t2, t3, fail
+
+        li      gp, 3
+        li      t0, -7
+        li      t1, 2
+        div     t2, t0, t1
+        li      t3, -3
+        bne     t2, t3, fail
+        rem     t2, t0, t1
+        li      t3, -1
+        bne     t2, t3, fail
+        divu    t2, t0, t1
+        li      t3, 0x7FFFFFFC
+        bne     t2, t3, fail
+        remu    t2, t0, t1
+        li      t3, 1
+        bne     t2, t3, fail
+
+        # Division by zero
+        li      gp, 4
+        div     t2, t0, zero
+        li      t3, -1
+        bne     t2, t3, fail
+        divu    t2, t0, zero
+        bne     t2, t3, fail
+        rem     t2, t0, zero
+        bne     t2, t0, fail
+        remu    t2, t0, zero
+        bne     t2, t0, fail
+
+        # Overflow
+        li      gp, 5
+        li      t0, 0x80000000
+        li      t1, -1
+        div     t2, t0, t1
+        bne     t2, t0, fail
+        rem     t2, t0, t1
+        bnez    t2, fail
+
+pass:   li      a0, 0
+        ecall
+
+fail:   mv      a0, gp
+        ecall
\ No newline at end of file
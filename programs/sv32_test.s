@@ -0,0 +1,295 @@
+# Privilege mode and Sv32 self check.
+#
+# The machine mode handler records mcause in s1 and mtval in s2, then skips
+# the trapping instruction (or returns to ra for an instruction page fault).
+# An ecall from user mode returns to 'user_done' in supervisor mode, and if s9
+# holds an address, the handler jumps to it to run a machine mode routine. The
+# supervisor handler records scause in s10 and stval in s11 in the same way.
+#
+# The page tables are built at 0x10000 (root) and 0x11000:
+#   0x00000000 - 0x003FFFFF  megapage, identity mapped supervisor RWX
+#   0x00400000  -> 0x20000   RW, accessed and dirty clear
+#   0x00401000  -> 0x21000   read only
+#   0x00402000  -> 0x22000   user RW
+#   0x00403000  -> user_page user RX
+#   0x00404000               invalid
+#   0x00800000               misaligned megapage
+# Run with:
+#   cargo run -- programs/sv32_test.s -i 2000
+
+        li      a0, -1
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+
+        # Build the page tables
+        li      s0, 0x10000             # root table
+        li      s3, 0x11000             # second level table
+        li      t0, 0xCF                # megapage at 0, D A X W R V
+        sw      t0, 0(s0)
+        srli    t0, s3, 2
+        ori     t0, t0, 0x01            # pointer to the second level
+        sw      t0, 4(s0)
+        li      t0, 0x1004CF            # megapage at physical 0x00401000 (misaligned)
+        sw      t0, 8(s0)
+        li      t0, 0x8007              # 0x20000 W R V
+        sw      t0, 0(s3)
+        li      t0, 0x84C3              # 0x21000 D A R V
+        sw      t0, 4(s3)
+        li      t0, 0x88D7              # 0x22000 D A U W R V
+        sw      t0, 8(s3)
+        la      t0, user_page
+        srli    t0, t0, 2
+        ori     t0, t0, 0x5B            # A U X R V
+        sw      t0, 12(s3)
+        sw      zero, 16(s3)
+
+        li      t0, 0x20000
+        li      t1, 0x1234
+        sw      t1, 0(t0)
+        li      t0, 0x22000
+        li      t1, 0x5678
+        sw      t1, 0(t0)
+
+        # mret drops to supervisor mode, where ecall has cause 9
+        li      gp, 1
+        li      t0, 0x1800
+        csrc    0x300, t0
+        li      t0, 0x800
+        csrs    0x300, t0               # mstatus.MPP = S
+        la      t0, in_supervisor
+        csrw    0x341, t0               # mepc
+        mret
+in_supervisor:
+        ecall
+        li      t0, 9
+        bne     s1, t0, fail
+
+        # Machine mode registers and instructions are not available to supervisor mode
+        li      gp, 2
+        li      s1, 0
+        mret
+        li      t0, 2
+        bne     s1, t0, fail
+
+        # A load through the page tables sets the accessed bit only
+        li      gp, 3
+        li      t0, 0x80000010          # Sv32, root at 0x10000
+        csrw    0x180, t0               # satp
+        sfence.vma
+        li      s4, 0x00400000
+        lw      t0, 0(s4)
+        li      t1, 0x1234
+        bne     t0, t1, fail
+        lw      t0, 0(s3)
+        andi    t0, t0, 0xC0
+        li      t1, 0x40
+        bne     t0, t1, fail
+
+        # A store sets the dirty bit and reaches the physical page
+        li      gp, 4
+        li      t1, 0x4321
+        sw      t1, 4(s4)
+        lw      t0, 0(s3)
+        andi    t0, t0, 0xC0
+        li      t1, 0xC0
+        bne     t0, t1, fail
+        li      t0, 0x20004
+        lw      t0, 0(t0)
+        li      t1, 0x4321
+        bne     t0, t1, fail
+
+        # A store to a read only page raises a store page fault with the virtual address
+        li      gp, 5
+        li      s1, 0
+        li      t0, 0x00401000
+        lw      t1, 0(t0)
+        sw      t1, 0(t0)
+        li      t1, 15
+        bne     s1, t1, fail
+        bne     s2, t0, fail
+
+        # User pages are only accessible to supervisor loads and stores with SUM set
+        li      gp, 6
+        li      s1, 0
+        li      t0, 0x00402000
+        lw      t1, 0(t0)
+        li      t2, 13
+        bne     s1, t2, fail
+        li      t2, 0x40000
+        csrs    0x100, t2               # sstatus.SUM
+        lw      t1, 0(t0)
+        csrc    0x100, t2
+        li      t2, 0x5678
+        bne     t1, t2, fail
+
+        # Invalid entries and misaligned megapages fault
+        li      gp, 7
+        li      s1, 0
+        li      t0, 0x00404000
+        lw      t1, 0(t0)
+        li      t2, 13
+        bne     s1, t2, fail
+        li      s1, 0
+        li      t0, 0x00800000
+        lw      t1, 0(t0)
+        bne     s1, t2, fail
+        bne     s2, t0, fail
+
+        # Fetching from an invalid page raises an instruction page fault
+        li      gp, 8
+        li      s1, 0
+        li      t0, 0x00404000
+        jalr    ra, t0, 0
+        li      t1, 12
+        bne     s1, t1, fail
+        bne     s2, t0, fail
+
+        # sret drops to user mode, which can use user pages only, and whose ecall has cause 8
+        li      gp, 9
+        li      s5, 0
+        li      t0, 0x100
+        csrc    0x100, t0               # sstatus.SPP = U
+        li      t0, 0x00403000
+        csrw    0x141, t0               # sepc
+        sret
+user_done:
+        li      t0, 0x5678
+        bne     s5, t0, fail
+        li      t0, 13
+        bne     s6, t0, fail
+        li      t0, 2
+        bne     s7, t0, fail
+
+        # Delegated exceptions go to the supervisor handler instead of the machine handler
+        li      gp, 10
+        la      t0, supervisor_handler
+        csrw    0x105, t0               # stvec
+        j       delegate
+
+        # Machine mode loads and stores are translated at the MPP privilege when MPRV is set
+mprv:   li      gp, 12
+        li      t0, 0x20000
+        li      t1, 0x1111
+        sw      t1, 0(t0)
+        li      t0, 0x20000             # MPRV, MPP = S
+        li      t1, 0x800
+        or      t0, t0, t1
+        csrs    0x300, t0
+        lw      t1, 0(s4)
+        csrc    0x300, t0
+        li      t0, 0x1111
+        bne     t0, t1, fail
+        li      s1, 0
+        li      t0, 0x00400000          # without MPRV the address is physical, and nothing is mapped there
+        lw      t1, 0(t0)
+        li      t1, 5                   # load access fault
+        bne     s1, t1, fail
+        bne     s2, t0, fail
+
+pass:   li      a0, 0
+        j       done
+
+fail:   mv      a0, gp
+done:   j       done
+
+        .align  2
+handler:
+        bnez    s9, machine_call
+        csrr    s1, 0x342               # mcause
+        csrr    s2, 0x343               # mtval
+        li      t6, 8
+        beq     s1, t6, from_user
+        li      t6, 12
+        beq     s1, t6, fetch_fault
+        csrr    t6, 0x341
+        addi    t6, t6, 4
+        csrw    0x341, t6
+        mret
+fetch_fault:
+        csrw    0x341, ra
+        mret
+from_user:
+        la      t6, user_done
+        csrw    0x341, t6
+        li      t6, 0x800               # MPP = S
+        csrs    0x300, t6
+        mret
+machine_call:
+        mv      t6, s9
+        li      s9, 0
+        jr      t6
+
+        .align  2
+supervisor_handler:
+        csrr    s10, 0x142              # scause
+        csrr    s11, 0x143              # stval
+        blt     s10, zero, supervisor_interrupt
+        csrr    t6, 0x141
+        addi    t6, t6, 4
+        csrw    0x141, t6
+        sret
+supervisor_interrupt:
+        csrci   0x144, 2                # sip.SSIP
+        sret
+
+        # Delegation is set up from machine mode, reached through an ecall
+delegate:
+        la      s9, delegate_machine
+        ecall
+delegated:
+        li      s1, 0
+        li      s10, 0
+        li      t0, 0x00404000
+        lw      t1, 0(t0)
+        li      t1, 13
+        bne     s10, t1, fail
+        bne     s11, t0, fail
+        bnez    s1, fail
+
+        # A delegated software interrupt is taken by the supervisor handler when SIE is set
+        li      gp, 11
+        li      s10, 0
+        csrsi   0x104, 2                # sie.SSIE
+        csrsi   0x144, 2                # sip.SSIP
+        nop
+        bnez    s10, fail
+        csrsi   0x100, 2                # sstatus.SIE
+        nop
+        li      t0, 0x80000001
+        bne     s10, t0, fail
+        csrr    t0, 0x144
+        bnez    t0, fail
+
+        # Return to machine mode for the MPRV test
+        la      s9, to_machine
+        ecall
+
+        .align  2
+delegate_machine:
+        li      t0, 0x2000
+        csrw    0x302, t0               # medeleg.LoadPageFault
+        li      t0, 0x2
+        csrw    0x303, t0               # mideleg.SSI
+        la      t0, delegated
+        csrw    0x341, t0
+        mret
+
+        .align  2
+to_machine:
+        li      t0, 0x1800
+        csrs    0x300, t0               # mstatus.MPP = M
+        la      t0, mprv
+        csrw    0x341, t0
+        mret
+
+        .align  12
+user_page:
+        li      t0, 0x00402000
+        lw      s5, 0(t0)
+        li      s1, 0
+        lw      t0, 0(zero)             # supervisor page
+        mv      s6, s1
+        li      s1, 0
+        sfence.vma
+        mv      s7, s1
+        ecall
\ No newline at end of file
@@ -0,0 +1,147 @@
+# Memory mapped device self check, using the countdown timer attached on the
+# command line.
+#
+# The handler records mcause in s1. For the external interrupt it claims the
+# source into s7, acknowledges the timer and completes the source.
+# Run with:
+#   cargo run -- programs/timer_test.s -T 0x10000000@4 -i 1000
+
+        li      a0, -1
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+        li      s5, 0x10000000          # timer base
+        li      s6, 0x0C000000          # PLIC base
+
+        # Accesses narrower than a register read and write only the bytes they cover
+        li      gp, 1
+        li      t0, 0x12345678
+        sw      t0, 4(s5)               # reload
+        lbu     t1, 5(s5)
+        li      t2, 0x56
+        bne     t1, t2, fail
+        lhu     t1, 6(s5)
+        li      t2, 0x1234
+        bne     t1, t2, fail
+        li      t0, 0xAB
+        sb      t0, 4(s5)
+        li      t0, 0x9876
+        sh      t0, 6(s5)
+        lw      t1, 4(s5)
+        li      t2, 0x987656AB
+        bne     t1, t2, fail
+
+        # Bits which are not implemented read as zero, as do offsets with no register, without faulting
+        li      gp, 2
+        li      s1, 0
+        li      t0, 0xF8
+        sw      t0, 0(s5)               # control
+        lw      t1, 0(s5)
+        bnez    t1, fail
+        li      t0, 0x800
+        add     t0, s5, t0
+        sw      t0, 0(t0)
+        lw      t1, 0(t0)
+        bnez    t1, fail
+        bnez    s1, fail
+
+        # A one shot timer counts down, flags it has expired and stops
+        li      gp, 3
+        li      t0, 100
+        sw      t0, 8(s5)               # count
+        csrr    s2, 0xB00               # mcycle
+        li      t0, 1
+        sw      t0, 0(s5)               # enable
+wait_expired:
+        lw      t1, 12(s5)              # status
+        beqz    t1, wait_expired
+        csrr    t0, 0xB00
+        sub     t0, t0, s2
+        li      t2, 100
+        bltu    t0, t2, fail
+        lw      t1, 0(s5)
+        bnez    t1, fail
+        lw      t1, 8(s5)
+        bnez    t1, fail
+
+        # The expired flag is cleared by writing a one to it
+        li      gp, 4
+        sw      zero, 12(s5)
+        lw      t1, 12(s5)
+        beqz    t1, fail
+        li      t0, 1
+        sw      t0, 12(s5)
+        lw      t1, 12(s5)
+        bnez    t1, fail
+
+        # A periodic timer reloads, counting the expiries missed while it was flagged, which clear once read
+        li      gp, 5
+        li      t0, 20
+        sw      t0, 4(s5)
+        sw      t0, 8(s5)
+        li      t0, 3                   # enable, periodic
+        sw      t0, 0(s5)
+        li      t0, 40
+spin:   addi    t0, t0, -1
+        bnez    t0, spin
+        sw      zero, 0(s5)
+        lw      t1, 16(s5)              # overruns
+        li      t2, 2
+        bltu    t1, t2, fail
+        lw      t1, 16(s5)
+        bnez    t1, fail
+        li      t0, 1
+        sw      t0, 12(s5)
+
+        # The timer raises its interrupt through the PLIC, ending a wait skipped by the simulator
+        li      gp, 6
+        li      s1, 0
+        li      t0, 1
+        sw      t0, 16(s6)              # priority of source 4
+        li      t0, 0x2000
+        add     t0, s6, t0
+        li      t1, 0x10
+        sw      t1, 0(t0)               # enable source 4
+        li      t0, 0x800
+        csrw    0x304, t0               # mie.MEIE
+        li      t0, 0x10D               # cycles waiting for an interrupt
+        csrw    0x323, t0               # mhpmevent3
+        csrw    0xB03, zero
+        li      t0, 100000
+        sw      t0, 8(s5)
+        li      t0, 5                   # enable, interrupt
+        sw      t0, 0(s5)
+        csrsi   0x300, 8                # mstatus.MIE
+        wfi
+        csrci   0x300, 8
+        li      t0, 0x8000000B
+        bne     s1, t0, fail
+        li      t0, 4
+        bne     s7, t0, fail
+        csrr    t0, 0xB03
+        li      t1, 99900
+        bltu    t0, t1, fail
+        lw      t1, 12(s5)
+        bnez    t1, fail
+
+pass:   li      a0, 0
+        j       done
+
+fail:   mv      a0, gp
+done:   j       done
+
+        .align  2
+handler:
+        csrr    s1, 0x342               # mcause
+        bltz    s1, interrupt
+        csrr    t6, 0x341
+        addi    t6, t6, 4
+        csrw    0x341, t6
+        mret
+interrupt:
+        li      t5, 0x200004
+        add     t5, s6, t5
+        lw      s7, 0(t5)               # claim
+        li      t6, 1
+        sw      t6, 12(s5)              # acknowledge the timer
+        sw      s7, 0(t5)               # complete
+        mret
\ No newline at end of file
@@ -0,0 +1,11 @@
+# Points mtvec at an address with no memory behind it, so fetching the handler
+# raises an access fault which traps to the same address again, and the
+# simulation faults. Run with:
+#   cargo run -- programs/trap_loop.s
+
+        li      t0, 0x40000000
+        csrw    0x305, t0               # mtvec
+        li      a0, 1
+        .word   0                       # illegal
+        li      a0, 0
+        ecall
@@ -0,0 +1,106 @@
+# Machine mode trap self check. The handler records mcause in s1, mepc in s2,
+# mtval in s3 and mstatus in s4, and skips over the trapping instruction of
+# exceptions.
+# Run with:
+#   cargo run -- programs/trap_test.s --max-instructions 1000
+
+        la      t0, handler
+        csrw    0x305, t0               # mtvec (direct)
+
+        # ecall records its cause and address, and mret returns past it
+        li      gp, 1
+ecall_at:
+        ecall
+        li      t0, 11
+        bne     s1, t0, fail
+        la      t0, ecall_at
+        bne     s2, t0, fail
+        bnez    s3, fail
+
+        # ebreak gives its address as the trap value
+        li      gp, 2
+ebreak_at:
+        ebreak
+        li      t0, 3
+        bne     s1, t0, fail
+        la      t0, ebreak_at
+        bne     s2, t0, fail
+        bne     s3, t0, fail
+
+        # The interrupt enable is stacked into MPIE and restored by mret
+        li      gp, 3
+        csrsi   0x300, 8                # mstatus.MIE
+        ecall
+        andi    t0, s4, 0x88
+        li      t1, 0x80
+        bne     t0, t1, fail
+        srli    t0, s4, 11
+        andi    t0, t0, 3
+        li      t1, 3
+        bne     t0, t1, fail
+        csrr    t0, 0x300
+        andi    t0, t0, 0x88
+        li      t1, 0x88
+        bne     t0, t1, fail
+        csrci   0x300, 8
+
+        # Exceptions go to the base in vectored mode
+        li      gp, 4
+        la      t0, vectors
+        ori     t0, t0, 1
+        csrw    0x305, t0               # mtvec (vectored)
+        li      s1, 0
+        ecall
+        li      t0, 11
+        bne     s1, t0, fail
+
+        # A software interrupt is only taken once enabled, and uses its vector
+        li      gp, 5
+        li      s1, 0
+        csrsi   0x304, 8                # mie.MSIE
+        li      t0, 0x02000000          # CLINT msip
+        li      t1, 1
+        sw      t1, 0(t0)
+        nop
+        bnez    s1, fail
+        csrsi   0x300, 8                # mstatus.MIE
+interrupted:
+        nop
+        li      t0, 0x80000003
+        bne     s1, t0, fail
+        la      t0, interrupted
+        bne     s2, t0, fail
+        li      t0, 1
+        bne     s5, t0, fail
+        csrci   0x300, 8
+
+pass:   li      a0, 0
+        j       done
+
+fail:   mv      a0, gp
+done:   j       done
+
+        .align  2
+handler:
+        csrr    s1, 0x342               # mcause
+        csrr    s2, 0x341               # mepc
+        csrr    s3, 0x343               # mtval
+        csrr    s4, 0x300               # mstatus
+        bltz    s1, handled
+        addi    t6, s2, 4
+        csrw    0x341, t6
+        mret
+handled:
+        li      t6, 0x02000000          # CLINT msip
+        sw      zero, 0(t6)
+        mret
+
+        .align  2
+vectors:
+        j       handler
+        j       fail
+        j       fail
+        j       software
+software:
+        li      s5, 1
+        j       handler
\ No newline at end of file
@@ -0,0 +1,172 @@
+# WFI self check.
+#
+# The handler records mcause in s1 and mepc in s2. It silences the timer by
+# pushing mtimecmp out and claims and completes external interrupts. Exceptions
+# skip the trapping instruction, and an ecall from S or U returns to machine
+# mode. The external interrupt of test 3 is scheduled on the command line.
+# Run with:
+#   cargo run -- programs/wfi_test.s -i 1000 -q 3@200000
+
+        li      a0, -1
+        la      t0, handler
+        csrw    0x305, t0               # mtvec
+        li      s0, 0x02000000          # CLINT base
+        li      t0, 0x4000
+        add     s3, s0, t0              # mtimecmp
+        li      t0, 0xBFF8
+        add     s4, s0, t0              # mtime
+        li      t0, 0x10D               # cycles waiting for an interrupt
+        csrw    0x323, t0               # mhpmevent3
+        li      t0, -1
+        sw      t0, 4(s3)
+
+        # The timer interrupt ends the wait and is taken after the wfi
+        li      gp, 1
+        li      s1, 0
+        lw      t0, 0(s4)
+        li      t1, 100000
+        add     s5, t0, t1
+        sw      s5, 0(s3)
+        sw      zero, 4(s3)
+        li      t0, 0x80
+        csrw    0x304, t0               # mie.MTIE
+        csrsi   0x300, 8                # mstatus.MIE
+wait_timer:
+        wfi
+        csrci   0x300, 8
+        li      t0, 0x80000007
+        bne     s1, t0, fail
+        la      t0, wait_timer
+        addi    t0, t0, 4
+        bne     s2, t0, fail
+        lw      t0, 0(s4)
+        bltu    t0, s5, fail
+        csrr    t0, 0xB03               # nearly all of the wait was idle
+        li      t1, 99900
+        bltu    t0, t1, fail
+
+        # An interrupt enabled in mie ends the wait even when interrupts are globally disabled
+        li      gp, 2
+        li      s1, 0
+        lw      t0, 0(s4)
+        addi    s5, t0, 1000
+        sw      s5, 0(s3)
+        sw      zero, 4(s3)
+        wfi
+        bnez    s1, fail
+        lw      t0, 0(s4)
+        bltu    t0, s5, fail
+        li      t0, -1
+        sw      t0, 4(s3)
+
+        # A device interrupt scheduled at clock 200000 ends the wait
+        li      gp, 3
+        li      s1, 0
+        li      s6, 0x0C000000          # PLIC base
+        li      t0, 1
+        sw      t0, 12(s6)              # priority of source 3
+        li      t0, 0x2000
+        add     t0, s6, t0
+        li      t1, 0x8
+        sw      t1, 0(t0)               # enable source 3
+        li      t0, 0x800
+        csrw    0x304, t0               # mie.MEIE
+        csrsi   0x300, 8
+        wfi
+        csrci   0x300, 8
+        li      t0, 0x8000000B
+        bne     s1, t0, fail
+        li      t0, 3
+        bne     s7, t0, fail
+        csrr    t0, 0xB00               # mcycle
+        li      t1, 200000
+        bltu    t0, t1, fail
+
+        # User mode may not wait
+        li      gp, 4
+        li      s1, 0
+        csrw    0x304, zero
+        li      t0, 0x1800
+        csrc    0x300, t0               # MPP = U
+        la      t0, user
+        csrw    0x341, t0
+        mret
+user:
+        wfi
+        li      t0, 2
+        bne     s1, t0, fail
+        ecall
+
+        # Supervisor mode may wait unless mstatus.TW is set
+        li      gp, 5
+        li      s1, 0
+        li      t0, 0x1800
+        csrc    0x300, t0
+        li      t0, 0x200800
+        csrs    0x300, t0               # MPP = S, TW
+        la      t0, supervisor
+        csrw    0x341, t0
+        mret
+supervisor:
+        wfi
+        li      t0, 2
+        bne     s1, t0, fail
+        ecall
+        li      s1, 0
+        li      t0, 0x200000
+        csrc    0x300, t0
+        li      t0, 0x800
+        csrs    0x300, t0               # MPP = S
+        la      t0, supervisor_wait
+        csrw    0x341, t0
+        lw      t0, 0(s4)
+        addi    s5, t0, 1000
+        sw      s5, 0(s3)
+        sw      zero, 4(s3)
+        li      t0, 0x80
+        csrw    0x304, t0               # machine interrupts are always enabled in supervisor mode
+        mret
+supervisor_wait:
+        wfi
+        ecall
+        li      t0, 0x80000007
+        bne     s1, t0, fail
+        lw      t0, 0(s4)
+        bltu    t0, s5, fail
+
+pass:   li      a0, 0
+        j       done
+
+fail:   mv      a0, gp
+done:   j       done
+
+        .align  2
+handler:
+        csrr    t6, 0x342               # mcause
+        bltz    t6, interrupt
+        mv      s8, t6                  # an ecall keeps the cause of an earlier interrupt in s1
+        li      t5, 8
+        bltu    t6, t5, exception
+        li      t5, 0x1800              # an ecall from S or U returns to machine mode
+        csrs    0x300, t5
+        j       skip
+exception:
+        mv      s1, t6
+skip:   csrr    t6, 0x341
+        addi    t6, t6, 4
+        csrw    0x341, t6
+        mret
+interrupt:
+        mv      s1, t6
+        csrr    s2, 0x341
+        li      t5, 0x8000000B
+        beq     t6, t5, external
+        li      t6, -1                  # timer
+        sw      t6, 4(s3)
+        mret
+external:
+        li      t5, 0x200004
+        add     t5, s6, t5
+        lw      s7, 0(t5)               # claim
+        sw      s7, 0(t5)               # complete
+        mret
\ No newline at end of file
@@ -0,0 +1,18 @@
+# Trap handler at address 0 self check. mtvec is written with 0, the reset
+# vector and load address, so the trap taken to it must run the handler rather
+# than fault as if none were installed. a0 is 0 once the handler has run.
+# Run with:
+#   cargo run -- programs/zero_vector_test.s --stop-on-ecall
+
+        # mcause is 0 out of reset, so only the trap reaches the handler
+        csrr    t0, 0x342               # mcause
+        bnez    t0, handler
+
+        csrw    0x305, zero             # mtvec
+        li      a0, 1
+        .word   0                       # illegal
+        ecall
+
+handler:
+        li      a0, 0
+        ecall
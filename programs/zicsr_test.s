@@ -0,0 +1,70 @@
+# Zicsr self check, using mscratch (0x340) as a scratch CSR.
+# Run with:
+#   cargo run -- programs/zicsr_test.s --stop-on-ecall
+
+        # csrrw swaps the old value into rd
+        li      gp, 1
+        li      t0, 0x1234
+        csrw    0x340, t0
+        li      t1, 0x00F0
+        csrrw   t2, 0x340, t1
+        bne     t2, t0, fail
+        csrr    t3, 0x340
+        bne     t3, t1, fail
+
+        # rd equal to rs1 reads the old value before the write
+        li      gp, 2
+        li      t0, 0x55
+        csrrw   t0, 0x340, t0
+        bne     t0, t1, fail
+        csrr    t3, 0x340
+        li      t4, 0x55
+        bne     t3, t4, fail
+
+        # Set and clear bits
+        li      gp, 3
+        li      t0, 0x0F00
+        csrrs   t1, 0x340, t0
+        bne     t1, t4, fail
+        csrr    t3, 0x340
+        li      t4, 0x0F55
+        bne     t3, t4, fail
+        li      t0, 0x0305
+        csrrc   t1, 0x340, t0
+        bne     t1, t4, fail
+        csrr    t3, 0x340
+        li      t4, 0x0C50
+        bne     t3, t4, fail
+
+        # Set and clear with x0 only read
+        li      gp, 4
+        csrrs   t1, 0x340, zero
+        bne     t1, t4, fail
+        csrrc   t1, 0x340, zero
+        bne     t1, t4, fail
+        csrr    t3, 0x340
+        bne     t3, t4, fail
+
+        # Immediate forms
+        li      gp, 5
+        csrrwi  t1, 0x340, 31
+        bne     t1, t4, fail
+        csrrci  t1, 0x340, 5
+        li      t4, 31
+        bne     t1, t4, fail
+        csrrsi  t1, 0x340, 0
+        li      t4, 26
+        bne     t1, t4, fail
+        csrsi   0x340, 1
+        csrr    t3, 0x340
+        li      t4, 27
+        bne     t3, t4, fail
+        csrci   0x340, 27
+        csrr    t3, 0x340
+        bnez    t3, fail
+
+pass:   li      a0, 0
+        ecall
+
+fail:   mv      a0, gp
+        ecall
\ No newline at end of file
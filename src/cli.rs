@@ -0,0 +1,335 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::riscv::{MisalignedPolicy, RegionKind, PLIC_SOURCES};
+
+/// Usage text printed for --help or bad arguments
+pub const USAGE: &str = "Usage: riscv [OPTIONS] <PROGRAM>
+
+Options:
+  -f, --format <FORMAT>        Program format: elf, bin, hex, srec or asm (default: detect)
+  -a, --load-addr <ADDR>       Base address for bin, hex, srec and asm images (default: 0)
+  -p, --pc <ADDR>              Program counter to start at, overriding the image entry point
+  -R, --reset-vector <ADDR>    Address the hart starts at after a reset (default: 0)
+  -b, --boot-rom <FILE>        Image programmed into memory at the reset vector before power on, which the hart
+                               then boots from instead of the program's entry point
+  -w, --warm-reset <CLOCK>     Reset the hart and interrupt controllers at clock cycle CLOCK, keeping memory (may be repeated)
+  -c, --max-cycles <N>         Stop after N clock cycles
+  -i, --max-instructions <N>   Stop after N instructions have retired
+  -t, --trace <LEVEL>          Trace verbosity: 0 none, 1 instructions, 2 every cycle (default: 0)
+  -e, --stop-on-ecall          Stop when an ecall instruction is executed
+  -m, --misaligned <POLICY>    Misaligned loads and stores: trap, emulate or split (default: trap)
+  -r, --timer-ratio <N>        Clock cycles per tick of the CLINT mtime counter (default: 1)
+  -q, --irq <SOURCE@CLOCK>     Trigger PLIC interrupt SOURCE at clock cycle CLOCK (may be repeated)
+  -M, --map <KIND@BASE,...>    Place a device in the memory map instead of the default map (may be repeated),
+                               KIND is ram512k, ram1m, rom1m, clint or plic
+  -T, --timer <BASE@SOURCE>    Attach a countdown timer at BASE raising PLIC interrupt SOURCE (may be repeated)
+  -d, --disassemble <ADDR:N>   List N instructions from ADDR after loading instead of running
+  -h, --help                   Print this message";
+
+/// Format of a program image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat
+{
+    Elf,
+    Binary,
+    IntelHex,
+    SRecord,
+    Assembly
+}
+
+impl ImageFormat
+{
+    /// Parse a format name given on the command line
+    pub fn from_name(name: &str) -> Option<Self>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "elf" => Some(ImageFormat::Elf),
+            "bin" | "binary" => Some(ImageFormat::Binary),
+            "hex" | "ihex" => Some(ImageFormat::IntelHex),
+            "srec" | "s19" | "s28" | "s37" | "mot" => Some(ImageFormat::SRecord),
+            "asm" | "s" => Some(ImageFormat::Assembly),
+            _ => None
+        }
+    }
+
+    /// Guess the format of an image from its file extension, falling back to the ELF magic number
+    pub fn detect(path: &str, data: &[u8]) -> Self
+    {
+        let by_extension = Path::new(path).extension()
+                            .and_then(|ext| ext.to_str())
+                            .and_then(ImageFormat::from_name);
+
+        match by_extension
+        {
+            Some(format) => format,
+            None if data.starts_with(&[0x7F, b'E', b'L', b'F']) => ImageFormat::Elf,
+            None => ImageFormat::Binary
+        }
+    }
+}
+
+/// Error generated while parsing the command line
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgumentError
+{
+    /// --help was given
+    HelpRequested,
+    /// An option was not recognised
+    UnknownOption(String),
+    /// An option was given without its value
+    MissingValue(String),
+    /// The value of an option could not be parsed
+    BadValue(String, String),
+    /// No program file was given
+    MissingProgram,
+    /// More than one program file was given
+    ExtraProgram(String)
+}
+
+impl fmt::Display for ArgumentError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            ArgumentError::HelpRequested => write!(f, "{}", USAGE),
+            ArgumentError::UnknownOption(option) => write!(f, "Unknown option '{}'", option),
+            ArgumentError::MissingValue(option) => write!(f, "Option '{}' requires a value", option),
+            ArgumentError::BadValue(option, value) => write!(f, "Bad value '{}' for option '{}'", value, option),
+            ArgumentError::MissingProgram => write!(f, "No program file given"),
+            ArgumentError::ExtraProgram(program) => write!(f, "Unexpected argument '{}', a program file was already given", program)
+        }
+    }
+}
+
+/// Parse the name of a misaligned access policy given on the command line
+pub fn parse_misaligned_policy(name: &str) -> Option<MisalignedPolicy>
+{
+    match name.to_ascii_lowercase().as_str()
+    {
+        "trap" => Some(MisalignedPolicy::Trap),
+        "emulate" => Some(MisalignedPolicy::Emulate),
+        "split" => Some(MisalignedPolicy::Split),
+        _ => None
+    }
+}
+
+/// Parse a number in decimal or, with a 0x prefix, hexadecimal
+pub fn parse_number(text: &str) -> Option<u64>
+{
+    let text = text.replace('_', "");
+
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()
+    }
+    else
+    {
+        text.parse().ok()
+    }
+}
+
+/// Command line options for the emulator
+#[derive(Debug, Clone)]
+pub struct Options
+{
+    pub program: String,
+    pub format: Option<ImageFormat>,
+    pub load_address: u32,
+    pub reset_pc: Option<u32>,
+    pub reset_vector: u32,
+    pub boot_rom: Option<String>,
+    pub warm_resets: Vec<u64>,
+    pub max_cycles: Option<u64>,
+    pub max_instructions: Option<u64>,
+    pub trace: u8,
+    pub stop_on_ecall: bool,
+    pub misaligned: MisalignedPolicy,
+    pub timer_ratio: u32,
+    pub interrupts: Vec<(u32, u64)>,
+    pub memory_map: Vec<(RegionKind, u32)>,
+    pub timers: Vec<(u32, u32)>,
+    pub disassemble: Option<(u32, u32)>
+}
+
+impl Options
+{
+    /// Parse the options from the command line arguments (not including the program name)
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, ArgumentError>
+    {
+        let mut program = None;
+        let mut format = None;
+        let mut load_address = 0;
+        let mut reset_pc = None;
+        let mut reset_vector = 0;
+        let mut boot_rom = None;
+        let mut warm_resets = Vec::new();
+        let mut max_cycles = None;
+        let mut max_instructions = None;
+        let mut trace = 0;
+        let mut stop_on_ecall = false;
+        let mut misaligned = MisalignedPolicy::Trap;
+        let mut timer_ratio = 1;
+        let mut interrupts = Vec::new();
+        let mut memory_map = Vec::new();
+        let mut timers = Vec::new();
+        let mut disassemble = None;
+
+        while let Some(arg) = args.next()
+        {
+            if !arg.starts_with('-')
+            {
+                if program.is_some()
+                {
+                    return Err(ArgumentError::ExtraProgram(arg));
+                }
+
+                program = Some(arg);
+                continue;
+            }
+
+            match arg.as_str()
+            {
+                "-h" | "--help" => return Err(ArgumentError::HelpRequested),
+                "-e" | "--stop-on-ecall" =>
+                {
+                    stop_on_ecall = true;
+                    continue;
+                },
+                "-f" | "--format" | "-a" | "--load-addr" | "-p" | "--pc" | "-R" | "--reset-vector" |
+                "-b" | "--boot-rom" | "-w" | "--warm-reset" | "-c" | "--max-cycles" |
+                "-i" | "--max-instructions" | "-t" | "--trace" | "-m" | "--misaligned" | "-r" | "--timer-ratio" |
+                "-q" | "--irq" | "-M" | "--map" | "-T" | "--timer" | "-d" | "--disassemble" => {},
+                _ => return Err(ArgumentError::UnknownOption(arg))
+            }
+
+            let value = args.next().ok_or_else(|| ArgumentError::MissingValue(arg.clone()))?;
+            let bad_value = || ArgumentError::BadValue(arg.clone(), value.clone());
+
+            match arg.as_str()
+            {
+                "-f" | "--format" => format = Some(ImageFormat::from_name(&value).ok_or_else(bad_value)?),
+                "-a" | "--load-addr" => load_address = parse_address(&value).ok_or_else(bad_value)?,
+                "-p" | "--pc" => reset_pc = Some(parse_address(&value).ok_or_else(bad_value)?),
+                "-R" | "--reset-vector" => reset_vector = parse_address(&value).ok_or_else(bad_value)?,
+                "-b" | "--boot-rom" => boot_rom = Some(value),
+                "-w" | "--warm-reset" => warm_resets.push(parse_number(&value).ok_or_else(bad_value)?),
+                "-c" | "--max-cycles" => max_cycles = Some(parse_number(&value).ok_or_else(bad_value)?),
+                "-i" | "--max-instructions" => max_instructions = Some(parse_number(&value).ok_or_else(bad_value)?),
+                "-t" | "--trace" => trace = parse_number(&value).filter(|v| *v <= 2).ok_or_else(bad_value)? as u8,
+                "-m" | "--misaligned" => misaligned = parse_misaligned_policy(&value).ok_or_else(bad_value)?,
+                "-r" | "--timer-ratio" => timer_ratio = parse_address(&value).filter(|v| *v > 0).ok_or_else(bad_value)?,
+                "-q" | "--irq" =>
+                {
+                    let (source, clock) = value.split_once('@').ok_or_else(bad_value)?;
+                    let source = parse_address(source).filter(|v| *v > 0 && *v < PLIC_SOURCES).ok_or_else(bad_value)?;
+                    interrupts.push((source, parse_number(clock).ok_or_else(bad_value)?));
+                },
+                "-T" | "--timer" =>
+                {
+                    let (base, source) = value.split_once('@').ok_or_else(bad_value)?;
+                    let source = parse_address(source).filter(|v| *v > 0 && *v < PLIC_SOURCES).ok_or_else(bad_value)?;
+                    timers.push((parse_address(base).ok_or_else(bad_value)?, source));
+                },
+                "-M" | "--map" =>
+                {
+                    for region in value.split(',')
+                    {
+                        let (kind, base) = region.split_once('@').ok_or_else(bad_value)?;
+                        memory_map.push((RegionKind::from_name(kind).ok_or_else(bad_value)?, parse_address(base).ok_or_else(bad_value)?));
+                    }
+                },
+                _ =>
+                {
+                    let (addr, count) = value.split_once(':').ok_or_else(bad_value)?;
+                    disassemble = Some((parse_address(addr).ok_or_else(bad_value)?, parse_address(count).ok_or_else(bad_value)?));
+                }
+            }
+        }
+
+        Ok(Self
+        {
+            program: program.ok_or(ArgumentError::MissingProgram)?,
+            format,
+            load_address,
+            reset_pc,
+            reset_vector,
+            boot_rom,
+            warm_resets,
+            max_cycles,
+            max_instructions,
+            trace,
+            stop_on_ecall,
+            misaligned,
+            timer_ratio,
+            interrupts,
+            memory_map,
+            timers,
+            disassemble
+        })
+    }
+}
+
+/// Parse a 32 bit address
+fn parse_address(text: &str) -> Option<u32>
+{
+    parse_number(text).filter(|v| *v <= u32::MAX as u64).map(|v| v as u32)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, ArgumentError>
+    {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn takes_one_program_among_the_options()
+    {
+        let options = parse(&["-i", "10", "programs/fibonacci.s", "-e"]).unwrap();
+
+        assert_eq!(options.program, "programs/fibonacci.s");
+        assert_eq!(options.max_instructions, Some(10));
+        assert!(options.stop_on_ecall);
+    }
+
+    #[test]
+    fn a_second_program_is_rejected()
+    {
+        assert!(matches!(parse(&["a.s", "b.s"]), Err(ArgumentError::ExtraProgram(arg)) if arg == "b.s"));
+        assert!(matches!(parse(&["-e"]), Err(ArgumentError::MissingProgram)));
+    }
+    #[test]
+    fn bad_values_are_rejected()
+    {
+        let cases =
+        [
+            ("-t", "3"),
+            ("-q", "0@5"),
+            ("-q", "32@5"),
+            ("-r", "0"),
+            ("-M", "foo@0"),
+            ("-d", "0x10"),
+            ("-a", "0x100000000")
+        ];
+
+        for (option, value) in cases
+        {
+            assert_eq!(parse(&["a.s", option, value]).unwrap_err(), ArgumentError::BadValue(option.to_string(), value.to_string()));
+        }
+    }
+
+    #[test]
+    fn options_need_their_values_and_must_exist()
+    {
+        assert_eq!(parse(&["a.s", "-c"]).unwrap_err(), ArgumentError::MissingValue("-c".to_string()));
+        assert_eq!(parse(&["a.s", "--map"]).unwrap_err(), ArgumentError::MissingValue("--map".to_string()));
+        assert_eq!(parse(&["a.s", "-x"]).unwrap_err(), ArgumentError::UnknownOption("-x".to_string()));
+        assert_eq!(parse(&["a.s", "--trace-all", "1"]).unwrap_err(), ArgumentError::UnknownOption("--trace-all".to_string()));
+    }
+}
\ No newline at end of file
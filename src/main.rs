@@ -3,31 +3,235 @@
 // Please allow me to use the new line brace formatting
 #![allow(clippy::suspicious_else_formatting)]
 
+mod cli;
 mod riscv;
 
-fn main()
+use std::cell::RefCell;
+use std::error::Error;
+use std::process;
+use std::rc::Rc;
+
+use cli::{ArgumentError, ImageFormat, Options};
+use riscv::{ChipCPU, ChipMode, Fault, Timer, TIMER_SIZE, disassemble_at, register_name};
+
+/// Encoding of the ecall instruction
+const ECALL: u32 = 0x00000073;
+
+/// Load an image into the cpu, detecting its format from the path unless one is given
+fn load_image(cpu: &mut ChipCPU, path: &str, format: Option<ImageFormat>, base: u32, data: &[u8]) -> Result<(), Box<dyn Error>>
 {
-    println!("RISCV Emulator");
+    match format.unwrap_or_else(|| ImageFormat::detect(path, data))
+    {
+        ImageFormat::Elf => cpu.load_elf(data)?,
+        ImageFormat::Binary =>
+        {
+            cpu.load_binary(base, data)?;
+            cpu.program_counter.value = base;
+        },
+        ImageFormat::IntelHex => cpu.load_ihex(base, &String::from_utf8_lossy(data))?,
+        ImageFormat::SRecord => cpu.load_srec(base, &String::from_utf8_lossy(data))?,
+        ImageFormat::Assembly => cpu.load_assembly(base, &String::from_utf8_lossy(data))?
+    }
+
+    Ok(())
+}
 
-    let mut cpu = riscv::ChipCPU::new();
+/// Read an image file, exiting if it cannot be read
+fn read_image(path: &str) -> Vec<u8>
+{
+    match std::fs::read(path)
+    {
+        Ok(data) => data,
+        Err(error) =>
+        {
+            eprintln!("Unable to read '{}': {}", path, error);
+            process::exit(2);
+        }
+    }
+}
+
+/// Load the boot rom and the program named in the options into the cpu, exiting if either cannot be loaded
+fn load_images(cpu: &mut ChipCPU, options: &Options)
+{
+    let mut images = Vec::new();
+
+    if let Some(path) = &options.boot_rom
+    {
+        images.push((path, None, options.reset_vector));
+    }
 
-    cpu.debug_display = true;
+    images.push((&options.program, options.format, options.load_address));
 
-    cpu.write_to_memory(0, vec![
-        0x13,
-0x00,
-0x00,
-0x00,
-0x73,
-0x00,
-0x00,
-0x00,
+    for (path, format, base) in images
+    {
+        if let Err(error) = load_image(cpu, path, format, base, &read_image(path))
+        {
+            eprintln!("Unable to load '{}': {}", path, error);
+            process::exit(2);
+        }
+    }
 
-    ]);
+    // With a boot rom the board powers on into it rather than being started at the program's entry point
+    if options.boot_rom.is_some()
+    {
+        cpu.reset();
+    }
 
-    for _ in 0..100
+    if let Some(pc) = options.reset_pc
     {
-        cpu.clock_to_instruction();
-        // println!("{:?}", cpu);
+        cpu.program_counter.value = pc;
     }
 }
+
+/// Run the simulation until one of the stop conditions is met, or until the hart faults
+fn run(cpu: &mut ChipCPU, options: &Options) -> Result<(), Fault>
+{
+    let mut instruction_pc = cpu.program_counter.value;
+
+    loop
+    {
+        // Skip the clocks spent waiting for an interrupt, up to the next scheduled interrupt or the cycle limit
+        if cpu.get_mode() == ChipMode::WaitForInterrupt
+        {
+            let next_event = options.interrupts.iter().map(|(_, clock)| *clock)
+                                .chain(options.warm_resets.iter().copied())
+                                .filter(|clock| *clock >= cpu.get_clock() as u64)
+                                .chain(options.max_cycles)
+                                .min();
+
+            if !cpu.fast_forward(next_event.map(|clock| clock.min(usize::MAX as u64) as usize))
+            {
+                eprintln!("The hart is waiting for an interrupt which can never arrive");
+                break;
+            }
+        }
+
+        if options.max_cycles.is_some_and(|max| cpu.get_clock() as u64 >= max) ||
+           options.max_instructions.is_some_and(|max| cpu.get_retired_instructions() as u64 >= max)
+        {
+            break;
+        }
+
+        if options.warm_resets.contains(&(cpu.get_clock() as u64))
+        {
+            cpu.reset();
+        }
+
+        if cpu.get_mode() == ChipMode::LoadInstruction
+        {
+            instruction_pc = cpu.program_counter.value;
+        }
+
+        for (source, _) in options.interrupts.iter().filter(|(_, clock)| *clock == cpu.get_clock() as u64)
+        {
+            cpu.interrupt_line(*source).trigger();
+        }
+
+        cpu.clock_processor();
+
+        if cpu.get_mode() == ChipMode::LoadInstruction
+        {
+            if options.trace == 1
+            {
+                let inst = cpu.read_instruction_value();
+                println!("0x{:08X}:  {:08X}  {}", instruction_pc, inst, disassemble_at(inst, instruction_pc));
+            }
+
+            if options.stop_on_ecall && cpu.read_instruction_value() == ECALL
+            {
+                break;
+            }
+        }
+
+        if let Some(fault) = cpu.get_fault()
+        {
+            return Err(fault);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the final state of the registers
+fn print_state(cpu: &ChipCPU)
+{
+    let idle = cpu.get_idle_clocks();
+    let percent = if cpu.get_clock() > 0 {100.0 * idle as f64 / cpu.get_clock() as f64} else {0.0};
+
+    println!("Cycles: {}   Instructions: {}   Idle: {} ({:.1}%)", cpu.get_clock(), cpu.get_retired_instructions(), idle, percent);
+    println!("PC: 0x{:08X}", cpu.program_counter.value);
+
+    for i in 0..8usize
+    {
+        for j in 0..4usize
+        {
+            let reg = 4 * i + j;
+            print!("   x{:02} {:>4}: 0x{:08X}", reg, register_name(reg as u8), cpu.read_register_value(reg));
+        }
+
+        println!();
+    }
+}
+
+fn main()
+{
+    let options = match Options::parse(std::env::args().skip(1))
+    {
+        Ok(options) => options,
+        Err(ArgumentError::HelpRequested) =>
+        {
+            println!("{}", cli::USAGE);
+            return;
+        },
+        Err(error) =>
+        {
+            eprintln!("{}\n\n{}", error, cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut cpu = ChipCPU::new();
+
+    cpu.debug_display = options.trace >= 2;
+    cpu.misaligned_policy = options.misaligned;
+    cpu.set_timer_ratio(options.timer_ratio);
+    cpu.set_reset_vector(options.reset_vector);
+
+    if !options.memory_map.is_empty()
+    {
+        if let Err(error) = cpu.set_memory_map(&options.memory_map)
+        {
+            eprintln!("Bad memory map: {}", error);
+            process::exit(2);
+        }
+    }
+
+    for (base, source) in &options.timers
+    {
+        let timer = Rc::new(RefCell::new(Timer::new(cpu.interrupt_line(*source))));
+
+        if let Err(error) = cpu.attach_device("timer", *base, TIMER_SIZE, timer)
+        {
+            eprintln!("Bad memory map: {}", error);
+            process::exit(2);
+        }
+    }
+
+    load_images(&mut cpu, &options);
+
+    if let Some((start, count)) = options.disassemble
+    {
+        print!("{}", cpu.disassemble_range(start, count));
+        return;
+    }
+
+    let result = run(&mut cpu, &options);
+
+    print_state(&cpu);
+
+    if let Err(fault) = result
+    {
+        eprintln!("Simulation faulted: {}", fault);
+        process::exit(1);
+    }
+}
\ No newline at end of file
@@ -3,6 +3,9 @@ use std::rc::Rc;
 
 use super::super::Bus;
 use super::{AluAdderModule, AluSubtractionModule, AluAndModule, AluOrModule, AluXorModule, AluSltiModule, AluSltiuModule, AluShiftLeftModule, AluShiftRightModule};
+use super::{AluMultiplyModule, AluMultiplyHighModule, AluMultiplyHighSignedUnsignedModule, AluMultiplyHighUnsignedModule};
+use super::{AluDivideModule, AluDivideUnsignedModule, AluRemainderModule, AluRemainderUnsignedModule};
+use super::{AluMinimumModule, AluMaximumModule, AluMinimumUnsignedModule, AluMaximumUnsignedModule};
 
 /// Arithmatic Logic Unit
 pub struct ArithmaticLogicUnit
@@ -21,6 +24,21 @@ pub struct ArithmaticLogicUnit
     shiftleft: AluShiftLeftModule,
     shiftright: AluShiftRightModule,
 
+    mul: AluMultiplyModule,
+    mulh: AluMultiplyHighModule,
+    mulhsu: AluMultiplyHighSignedUnsignedModule,
+    mulhu: AluMultiplyHighUnsignedModule,
+    div: AluDivideModule,
+    divu: AluDivideUnsignedModule,
+    rem: AluRemainderModule,
+    remu: AluRemainderUnsignedModule,
+
+    min: AluMinimumModule,
+    max: AluMaximumModule,
+    minu: AluMinimumUnsignedModule,
+    maxu: AluMaximumUnsignedModule,
+
+    /// Bits 0-2 select the operation (funct3), bit 3 selects the multiply/divide modules (funct7 = 0000001), bit 4 selects the atomic min/max modules
     pub mode: usize,
     pub sub_flag: bool
 }
@@ -42,6 +60,20 @@ impl ArithmaticLogicUnit
             shiftleft: AluShiftLeftModule::new(src0.clone(), src1.clone(), output.clone()),
             shiftright: AluShiftRightModule::new(src0.clone(), src1.clone(), output.clone()),
 
+            mul: AluMultiplyModule::new(src0.clone(), src1.clone(), output.clone()),
+            mulh: AluMultiplyHighModule::new(src0.clone(), src1.clone(), output.clone()),
+            mulhsu: AluMultiplyHighSignedUnsignedModule::new(src0.clone(), src1.clone(), output.clone()),
+            mulhu: AluMultiplyHighUnsignedModule::new(src0.clone(), src1.clone(), output.clone()),
+            div: AluDivideModule::new(src0.clone(), src1.clone(), output.clone()),
+            divu: AluDivideUnsignedModule::new(src0.clone(), src1.clone(), output.clone()),
+            rem: AluRemainderModule::new(src0.clone(), src1.clone(), output.clone()),
+            remu: AluRemainderUnsignedModule::new(src0.clone(), src1.clone(), output.clone()),
+
+            min: AluMinimumModule::new(src0.clone(), src1.clone(), output.clone()),
+            max: AluMaximumModule::new(src0.clone(), src1.clone(), output.clone()),
+            minu: AluMinimumUnsignedModule::new(src0.clone(), src1.clone(), output.clone()),
+            maxu: AluMaximumUnsignedModule::new(src0.clone(), src1.clone(), output.clone()),
+
             src0,
             src1,
             output,
@@ -63,8 +95,21 @@ impl ArithmaticLogicUnit
             0b100 => self.xor.tick(),
             0b101 => self.shiftright.tick(self.sub_flag),
             0b110 => self.or.tick(),
-            0b111 => self.and.tick(),
-            default => panic!("Bad ALU Mode: {:03b}", default)
+            0b111 => self.and.tick(self.sub_flag),
+            0b1000 => self.mul.tick(),
+            0b1001 => self.mulh.tick(),
+            0b1010 => self.mulhsu.tick(),
+            0b1011 => self.mulhu.tick(),
+            0b1100 => self.div.tick(),
+            0b1101 => self.divu.tick(),
+            0b1110 => self.rem.tick(),
+            0b1111 => self.remu.tick(),
+            0b10000 => self.min.tick(),
+            0b10001 => self.max.tick(),
+            0b10010 => self.minu.tick(),
+            0b10011 => self.maxu.tick(),
+            // Unused modes drive zero
+            _ => self.output.borrow_mut().enable_value(0)
         }
     }
 }
\ No newline at end of file
@@ -86,13 +86,20 @@ impl AluAndModule
         }
     }
 
-    /// Tick the module
-    pub fn tick(&self)
+    /// Tick the module (the flag inverts src1, clearing its set bits from src0)
+    pub fn tick(&self, flag: bool)
     {
         let s0 = self.src0.borrow().read_value();
         let s1 = self.src1.borrow().read_value();
 
-        self.output.borrow_mut().enable_value(s0 & s1);
+        if flag
+        {
+            self.output.borrow_mut().enable_value(s0 & !s1);
+        }
+        else
+        {
+            self.output.borrow_mut().enable_value(s0 & s1);
+        }
     }
 }
 
@@ -287,4 +294,394 @@ impl AluShiftRightModule
             self.output.borrow_mut().enable_value(s0 >> (s1 & 0b11111));
         }
     }
+}
+
+/// Multiply Module
+pub struct AluMultiplyModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluMultiplyModule
+{
+    /// Generate a new AluMultiplyModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        self.output.borrow_mut().enable_value(s0.wrapping_mul(s1));
+    }
+}
+
+/// Multiply High (Signed x Signed) Module
+pub struct AluMultiplyHighModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluMultiplyHighModule
+{
+    /// Generate a new AluMultiplyHighModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        let product = (s0 as i32 as i64) * (s1 as i32 as i64);
+
+        self.output.borrow_mut().enable_value((product >> 32) as u32);
+    }
+}
+
+/// Multiply High (Signed x Unsigned) Module
+pub struct AluMultiplyHighSignedUnsignedModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluMultiplyHighSignedUnsignedModule
+{
+    /// Generate a new AluMultiplyHighSignedUnsignedModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        let product = (s0 as i32 as i64) * (s1 as i64);
+
+        self.output.borrow_mut().enable_value((product >> 32) as u32);
+    }
+}
+
+/// Multiply High (Unsigned x Unsigned) Module
+pub struct AluMultiplyHighUnsignedModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluMultiplyHighUnsignedModule
+{
+    /// Generate a new AluMultiplyHighUnsignedModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        let product = (s0 as u64) * (s1 as u64);
+
+        self.output.borrow_mut().enable_value((product >> 32) as u32);
+    }
+}
+
+/// Divide Module
+pub struct AluDivideModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluDivideModule
+{
+    /// Generate a new AluDivideModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        // Division by zero gives all ones, overflow (-2^31 / -1) gives -2^31
+        let result = if s1 == 0 {u32::MAX} else {(s0 as i32).wrapping_div(s1 as i32) as u32};
+
+        self.output.borrow_mut().enable_value(result);
+    }
+}
+
+/// Divide Unsigned Module
+pub struct AluDivideUnsignedModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluDivideUnsignedModule
+{
+    /// Generate a new AluDivideUnsignedModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        // Division by zero gives all ones
+        let result = s0.checked_div(s1).unwrap_or(u32::MAX);
+
+        self.output.borrow_mut().enable_value(result);
+    }
+}
+
+/// Remainder Module
+pub struct AluRemainderModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluRemainderModule
+{
+    /// Generate a new AluRemainderModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        // Division by zero gives the dividend, overflow (-2^31 / -1) gives 0
+        let result = if s1 == 0 {s0} else {(s0 as i32).wrapping_rem(s1 as i32) as u32};
+
+        self.output.borrow_mut().enable_value(result);
+    }
+}
+
+/// Remainder Unsigned Module
+pub struct AluRemainderUnsignedModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluRemainderUnsignedModule
+{
+    /// Generate a new AluRemainderUnsignedModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        // Division by zero gives the dividend
+        let result = s0.checked_rem(s1).unwrap_or(s0);
+
+        self.output.borrow_mut().enable_value(result);
+    }
+}
+
+/// Minimum Module
+pub struct AluMinimumModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluMinimumModule
+{
+    /// Generate a new AluMinimumModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        self.output.borrow_mut().enable_value((s0 as i32).min(s1 as i32) as u32);
+    }
+}
+
+/// Maximum Module
+pub struct AluMaximumModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluMaximumModule
+{
+    /// Generate a new AluMaximumModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        self.output.borrow_mut().enable_value((s0 as i32).max(s1 as i32) as u32);
+    }
+}
+
+/// Minimum Unsigned Module
+pub struct AluMinimumUnsignedModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluMinimumUnsignedModule
+{
+    /// Generate a new AluMinimumUnsignedModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        self.output.borrow_mut().enable_value(s0.min(s1));
+    }
+}
+
+/// Maximum Unsigned Module
+pub struct AluMaximumUnsignedModule
+{
+    src0: Rc<RefCell<Bus>>,
+    src1: Rc<RefCell<Bus>>,
+    output: Rc<RefCell<Bus>>
+}
+
+impl AluMaximumUnsignedModule
+{
+    /// Generate a new AluMaximumUnsignedModule
+    pub fn new(src0: Rc<RefCell<Bus>>, src1: Rc<RefCell<Bus>>, output: Rc<RefCell<Bus>>) -> Self
+    {
+        Self
+        {
+            src0,
+            src1,
+            output
+        }
+    }
+
+    /// Tick the module
+    pub fn tick(&self)
+    {
+        let s0 = self.src0.borrow().read_value();
+        let s1 = self.src1.borrow().read_value();
+
+        self.output.borrow_mut().enable_value(s0.max(s1));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,1190 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::REGISTER_NAMES;
+use super::EncodeError;
+use super::encoder;
+
+/// Error generated while assembling a program, holding the line it occurred on
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssemblerError
+{
+    pub line: usize,
+    pub message: String
+}
+
+impl AssemblerError
+{
+    /// Generate a new AssemblerError
+    pub fn new(line: usize, message: String) -> Self
+    {
+        Self
+        {
+            line,
+            message
+        }
+    }
+}
+
+impl fmt::Display for AssemblerError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AssemblerError {}
+
+/// Parse a register name (either xN or its ABI name)
+pub fn parse_register(name: &str) -> Option<u8>
+{
+    let name = name.trim();
+
+    if let Some(num) = name.strip_prefix('x')
+    {
+        return num.parse::<u8>().ok().filter(|v| *v < 32);
+    }
+
+    if name == "fp"
+    {
+        return Some(8);
+    }
+
+    REGISTER_NAMES.iter().position(|n| *n == name).map(|i| i as u8)
+}
+
+/// Parse an integer literal (decimal, 0x hex, 0b binary or a 'c' character)
+fn parse_literal(text: &str) -> Option<i64>
+{
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16).ok()
+    }
+    else if let Some(bin) = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B"))
+    {
+        i64::from_str_radix(bin, 2).ok()
+    }
+    else if let Some(inner) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\''))
+    {
+        // A character literal holds exactly one character
+        let mut chars = inner.chars();
+
+        match (chars.next(), chars.next())
+        {
+            (Some(c), None) => Some(c as i64),
+            _ => None
+        }
+    }
+    else
+    {
+        text.parse().ok()
+    }
+}
+
+/// Split the upper 20 bits for lui/auipc and the sign extended lower 12 bits off of a value
+fn split_hi_lo(value: i64) -> (i64, i64)
+{
+    let hi = ((value + 0x800) >> 12) & 0xFFFFF;
+    let lo = ((value & 0xFFF) ^ 0x800) - 0x800;
+
+    (hi, lo)
+}
+
+/// Split the memory ordering suffix (.aq, .rl or .aqrl) off of an atomic mnemonic, giving the aq and rl bits
+fn split_ordering(mnemonic: &str) -> (&str, bool, bool)
+{
+    if let Some(base) = mnemonic.strip_suffix(".aqrl")
+    {
+        (base, true, true)
+    }
+    else if let Some(base) = mnemonic.strip_suffix(".aq")
+    {
+        (base, true, false)
+    }
+    else if let Some(base) = mnemonic.strip_suffix(".rl")
+    {
+        (base, false, true)
+    }
+    else
+    {
+        (mnemonic, false, false)
+    }
+}
+
+/// Scan a line of source once, tracking character and string literals, to cut any comment off of it and find the colons
+/// and commas outside of the literals (given as offsets into the line)
+fn scan_line(line: &str) -> (&str, Vec<usize>, Vec<usize>)
+{
+    let mut colons = Vec::new();
+    let mut commas = Vec::new();
+    let mut quote = None;
+
+    for (index, c) in line.char_indices()
+    {
+        match (quote, c)
+        {
+            (Some(open), _) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '#' | ';') => return (&line[..index], colons, commas),
+            (None, '/') if line[index + 1..].starts_with('/') => return (&line[..index], colons, commas),
+            (None, ':') => colons.push(index),
+            (None, ',') => commas.push(index),
+            _ => {}
+        }
+    }
+
+    (line, colons, commas)
+}
+
+/// Encoder taking three register numbers
+type RegisterEncoder = fn(u8, u8, u8) -> Result<u32, EncodeError>;
+
+/// Encoder taking two register numbers and an immediate
+type ImmediateEncoder = fn(u8, u8, i32) -> Result<u32, EncodeError>;
+
+/// Encoder taking a register number, a CSR and either a register number or an immediate
+type CsrEncoder = fn(u8, u16, u8) -> Result<u32, EncodeError>;
+
+/// Encoder taking three register numbers and the aq and rl ordering bits
+type AtomicEncoder = fn(u8, u8, u8, bool, bool) -> Result<u32, EncodeError>;
+
+/// Single statement of the source, with the address it was placed at
+#[derive(Debug, Clone)]
+struct Statement
+{
+    line: usize,
+    address: u32,
+    size: u32,
+    mnemonic: String,
+    operands: Vec<String>
+}
+
+/// RV32IMA Assembler
+pub struct Assembler
+{
+    base: u32,
+    labels: HashMap<String, u32>,
+    statements: Vec<Statement>
+}
+
+impl Assembler
+{
+    /// Generate a new Assembler placing the program at the base address
+    pub fn new(base: u32) -> Self
+    {
+        Self
+        {
+            base,
+            labels: HashMap::new(),
+            statements: Vec::new()
+        }
+    }
+
+    /// Assemble source text into blocks of contiguous bytes, each with the address it belongs at. The bytes skipped by .org,
+    /// .align and .space are left out, so they keep what memory holds (zero after power on)
+    pub fn assemble(&mut self, source: &str) -> Result<Vec<(u32, Vec<u8>)>, AssemblerError>
+    {
+        self.labels.clear();
+        self.statements.clear();
+
+        self.layout(source)?;
+
+        let mut blocks: Vec<(u32, Vec<u8>)> = Vec::new();
+
+        for statement in &self.statements
+        {
+            let bytes = self.encode(statement)?;
+
+            if bytes.is_empty()
+            {
+                continue;
+            }
+
+            match blocks.last_mut()
+            {
+                Some((address, data)) if address.wrapping_add(data.len() as u32) == statement.address => data.extend(bytes),
+                _ => blocks.push((statement.address, bytes))
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    /// First pass, collect every label and find the address and size of each statement
+    fn layout(&mut self, source: &str) -> Result<(), AssemblerError>
+    {
+        let mut address = self.base;
+
+        for (i, raw_line) in source.lines().enumerate()
+        {
+            let line = i + 1;
+
+            let (code, colons, commas) = scan_line(raw_line);
+            let mut start = 0;
+
+            // Labels
+            for index in colons
+            {
+                let label = code[start..index].trim();
+
+                if label.is_empty() || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+                    || label.starts_with(|c: char| c.is_ascii_digit())
+                {
+                    return Err(AssemblerError::new(line, format!("Invalid label '{}'", label)));
+                }
+
+                if self.labels.insert(label.to_string(), address).is_some()
+                {
+                    return Err(AssemblerError::new(line, format!("Label '{}' is defined more than once", label)));
+                }
+
+                start = index + 1;
+            }
+
+            let text = code[start..].trim_start();
+            start = code.len() - text.len();
+
+            if text.trim_end().is_empty()
+            {
+                continue;
+            }
+
+            // Operands run from the first whitespace after the mnemonic to the end of the code, split at the commas
+            let (mnemonic, start) = match text.find(char::is_whitespace)
+            {
+                Some(index) => (&text[..index], start + index),
+                None => (text, code.len())
+            };
+
+            let mnemonic = mnemonic.to_ascii_lowercase();
+            let mut operands = Vec::new();
+            if !code[start..].trim().is_empty()
+            {
+                let mut from = start;
+                for index in commas.into_iter().filter(|&index| index > start)
+                {
+                    operands.push(code[from..index].trim().to_string());
+                    from = index + 1;
+                }
+
+                operands.push(code[from..].trim().to_string());
+            }
+
+            let mut statement = Statement
+            {
+                line,
+                address,
+                size: 4,
+                mnemonic,
+                operands
+            };
+
+            match statement.mnemonic.as_str()
+            {
+                ".org" =>
+                {
+                    let target = self.evaluate_operand(&statement, 0)? as u32;
+
+                    if target < address
+                    {
+                        return Err(AssemblerError::new(line, format!(".org 0x{:08X} moves backwards from 0x{:08X}", target, address)));
+                    }
+
+                    statement.size = target - address;
+                },
+                ".align" =>
+                {
+                    let power = self.evaluate_operand(&statement, 0)?;
+
+                    if !(0..=12).contains(&power)
+                    {
+                        return Err(AssemblerError::new(line, format!("Alignment 2^{} is out of range", power)));
+                    }
+
+                    let alignment = 1u32 << power;
+                    statement.size = (alignment - (address % alignment)) % alignment;
+                },
+                ".space" | ".zero" => statement.size = self.immediate(&statement, 0, 0, u32::MAX as i64)? as u32,
+                ".word" => statement.size = 4 * statement.operands.len() as u32,
+                ".half" => statement.size = 2 * statement.operands.len() as u32,
+                ".byte" => statement.size = statement.operands.len() as u32,
+                ".text" | ".data" | ".globl" | ".global" => statement.size = 0,
+                "la" | "call" | "tail" => statement.size = 8,
+                "li" =>
+                {
+                    // Forward references are always given the full two instruction sequence
+                    statement.size = match statement.operands.get(1).map(|op| self.evaluate(line, op).map(|v| v as i32 as i64))
+                    {
+                        Some(Ok(value)) if (-2048..2048).contains(&value) || split_hi_lo(value).1 == 0 => 4,
+                        _ => 8
+                    };
+                },
+                _ => {}
+            }
+
+            address = address.checked_add(statement.size)
+                        .ok_or_else(|| AssemblerError::new(line, "Program extends past the end of the address space".to_string()))?;
+
+            self.statements.push(statement);
+        }
+
+        Ok(())
+    }
+
+    /// Evaluate an expression made of literals, labels, %hi(...) and %lo(...) joined with + and -
+    fn evaluate(&self, line: usize, text: &str) -> Result<i64, AssemblerError>
+    {
+        let mut rest = text.trim();
+        let mut total: i64 = 0;
+
+        if rest.is_empty()
+        {
+            return Err(AssemblerError::new(line, "Expected an expression".to_string()));
+        }
+
+        loop
+        {
+            let mut sign = 1;
+
+            while let Some(remaining) = rest.strip_prefix('-').or_else(|| rest.strip_prefix('+'))
+            {
+                if rest.starts_with('-')
+                {
+                    sign = -sign;
+                }
+
+                rest = remaining.trim_start();
+            }
+
+            let length = if rest.starts_with('%')
+            {
+                let mut depth = 0;
+                let mut end = None;
+
+                for (i, c) in rest.char_indices()
+                {
+                    if c == '('
+                    {
+                        depth += 1;
+                    }
+                    else if c == ')'
+                    {
+                        depth -= 1;
+
+                        if depth == 0
+                        {
+                            end = Some(i + 1);
+                            break;
+                        }
+                    }
+                }
+
+                end.ok_or_else(|| AssemblerError::new(line, format!("Unbalanced parentheses in '{}'", text)))?
+            }
+            else if rest.starts_with('\'')
+            {
+                // Up to and including the closing quote, which may be several bytes on for a character outside of ASCII
+                match rest.char_indices().skip(1).find(|(_, c)| *c == '\'')
+                {
+                    Some((i, _)) => i + 1,
+                    None => return Err(AssemblerError::new(line, format!("Unterminated character literal in '{}'", text)))
+                }
+            }
+            else
+            {
+                rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len())
+            };
+
+            let term = &rest[..length];
+            total = total.wrapping_add(sign * self.evaluate_term(line, term)?);
+            rest = rest[length..].trim_start();
+
+            if rest.is_empty()
+            {
+                return Ok(total);
+            }
+
+            if !rest.starts_with('+') && !rest.starts_with('-')
+            {
+                return Err(AssemblerError::new(line, format!("Unexpected '{}' in expression '{}'", rest, text)));
+            }
+        }
+    }
+
+    /// Evaluate a single term of an expression
+    fn evaluate_term(&self, line: usize, term: &str) -> Result<i64, AssemblerError>
+    {
+        if let Some(inner) = term.strip_prefix("%hi(").and_then(|t| t.strip_suffix(')'))
+        {
+            return Ok(split_hi_lo(self.evaluate(line, inner)?).0);
+        }
+
+        if let Some(inner) = term.strip_prefix("%lo(").and_then(|t| t.strip_suffix(')'))
+        {
+            return Ok(split_hi_lo(self.evaluate(line, inner)?).1);
+        }
+
+        if term.starts_with('\'')
+        {
+            return parse_literal(term).ok_or_else(|| AssemblerError::new(line, format!("Character literal {} does not hold exactly one character", term)));
+        }
+
+        if term.starts_with(|c: char| c.is_ascii_digit())
+        {
+            return parse_literal(term).ok_or_else(|| AssemblerError::new(line, format!("Invalid number '{}'", term)));
+        }
+
+        match self.labels.get(term)
+        {
+            Some(address) => Ok(*address as i64),
+            None => Err(AssemblerError::new(line, format!("Unknown label '{}'", term)))
+        }
+    }
+
+    /// Evaluate an operand of a statement
+    fn evaluate_operand(&self, statement: &Statement, index: usize) -> Result<i64, AssemblerError>
+    {
+        match statement.operands.get(index)
+        {
+            Some(operand) => self.evaluate(statement.line, operand),
+            None => Err(AssemblerError::new(statement.line, format!("'{}' is missing an operand", statement.mnemonic)))
+        }
+    }
+
+    /// Evaluate an operand which must fit in some signed or unsigned range
+    fn immediate(&self, statement: &Statement, index: usize, min: i64, max: i64) -> Result<i64, AssemblerError>
+    {
+        let value = self.evaluate_operand(statement, index)?;
+
+        if value < min || value > max
+        {
+            return Err(AssemblerError::new(statement.line, format!("Immediate {} is out of range ({} to {})", value, min, max)));
+        }
+
+        Ok(value)
+    }
+
+    /// Evaluate a branch or jump target into an offset from the statement, checking its range
+    fn offset(&self, statement: &Statement, index: usize, bits: u32) -> Result<i64, AssemblerError>
+    {
+        let offset = self.evaluate_operand(statement, index)? - statement.address as i64;
+        let limit = 1i64 << (bits - 1);
+
+        if offset < -limit || offset >= limit
+        {
+            return Err(AssemblerError::new(statement.line, format!("Target is out of range (offset {})", offset)));
+        }
+
+        if offset & 1 != 0
+        {
+            return Err(AssemblerError::new(statement.line, format!("Target offset {} is not a multiple of 2", offset)));
+        }
+
+        Ok(offset)
+    }
+
+    /// Parse a register operand of a statement
+    fn register(&self, statement: &Statement, index: usize) -> Result<u8, AssemblerError>
+    {
+        match statement.operands.get(index)
+        {
+            Some(operand) => parse_register(operand)
+                                .ok_or_else(|| AssemblerError::new(statement.line, format!("Invalid register '{}'", operand))),
+            None => Err(AssemblerError::new(statement.line, format!("'{}' is missing an operand", statement.mnemonic)))
+        }
+    }
+
+    /// Parse a memory operand of a statement (offset(register)) into the register and offset
+    fn memory_operand(&self, statement: &Statement, index: usize) -> Result<(u8, i64), AssemblerError>
+    {
+        let operand = match statement.operands.get(index)
+        {
+            Some(operand) => operand,
+            None => return Err(AssemblerError::new(statement.line, format!("'{}' is missing an operand", statement.mnemonic)))
+        };
+
+        let open = operand.rfind('(');
+        let (offset_text, register_text) = match open
+        {
+            Some(open) if operand.ends_with(')') => (operand[..open].trim(), &operand[open + 1..operand.len() - 1]),
+            _ => return Err(AssemblerError::new(statement.line, format!("Expected a memory operand 'offset(register)', found '{}'", operand)))
+        };
+
+        let register = parse_register(register_text)
+                        .ok_or_else(|| AssemblerError::new(statement.line, format!("Invalid register '{}'", register_text)))?;
+
+        let offset = if offset_text.is_empty() {0} else {self.evaluate(statement.line, offset_text)?};
+
+        if !(-2048..2048).contains(&offset)
+        {
+            return Err(AssemblerError::new(statement.line, format!("Offset {} is out of range (-2048 to 2047)", offset)));
+        }
+
+        Ok((register, offset))
+    }
+
+    /// Parse the address operand of an atomic statement ((register) or 0(register)) into the register
+    fn atomic_address(&self, statement: &Statement, index: usize) -> Result<u8, AssemblerError>
+    {
+        let (register, offset) = self.memory_operand(statement, index)?;
+
+        if offset != 0
+        {
+            return Err(AssemblerError::new(statement.line, format!("'{}' does not take an address offset", statement.mnemonic)));
+        }
+
+        Ok(register)
+    }
+
+    /// Parse the predecessor or successor set of a fence (some of the letters i, o, r and w) into its bits
+    fn fence_set(&self, statement: &Statement, index: usize) -> Result<u8, AssemblerError>
+    {
+        let operand = statement.operands[index].trim();
+        let mut set = 0;
+
+        for c in operand.chars()
+        {
+            let bit = match c
+            {
+                'i' => 0b1000,
+                'o' => 0b0100,
+                'r' => 0b0010,
+                'w' => 0b0001,
+                _ => 0
+            };
+
+            if bit == 0 || set & bit != 0
+            {
+                return Err(AssemblerError::new(statement.line, format!("Invalid fence set '{}'", operand)));
+            }
+
+            set |= bit;
+        }
+
+        if set == 0
+        {
+            return Err(AssemblerError::new(statement.line, format!("Invalid fence set '{}'", operand)));
+        }
+
+        Ok(set)
+    }
+
+    /// Check the number of operands given to a statement
+    fn expect_operands(&self, statement: &Statement, count: usize) -> Result<(), AssemblerError>
+    {
+        if statement.operands.len() != count
+        {
+            return Err(AssemblerError::new(statement.line, format!("'{}' expects {} operands, found {}", statement.mnemonic, count, statement.operands.len())));
+        }
+
+        Ok(())
+    }
+
+    /// Second pass, encode a statement into its bytes
+    fn encode(&self, statement: &Statement) -> Result<Vec<u8>, AssemblerError>
+    {
+        let mut bytes = Vec::new();
+
+        match statement.mnemonic.as_str()
+        {
+            ".org" | ".align" | ".space" | ".zero" | ".text" | ".data" | ".globl" | ".global" => {},
+            ".word" =>
+            {
+                for i in 0..statement.operands.len()
+                {
+                    let value = self.immediate(statement, i, i32::MIN as i64, u32::MAX as i64)?;
+                    bytes.extend_from_slice(&(value as u32).to_le_bytes());
+                }
+            },
+            ".half" =>
+            {
+                for i in 0..statement.operands.len()
+                {
+                    let value = self.immediate(statement, i, i16::MIN as i64, u16::MAX as i64)?;
+                    bytes.extend_from_slice(&(value as u16).to_le_bytes());
+                }
+            },
+            ".byte" =>
+            {
+                for i in 0..statement.operands.len()
+                {
+                    bytes.push(self.immediate(statement, i, i8::MIN as i64, u8::MAX as i64)? as u8);
+                }
+            },
+            _ =>
+            {
+                for inst in self.encode_instruction(statement)?
+                {
+                    bytes.extend_from_slice(&inst.to_le_bytes());
+                }
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Encode an instruction or pseudo-instruction into one or more instruction words
+    fn encode_instruction(&self, statement: &Statement) -> Result<Vec<u32>, AssemblerError>
+    {
+        let mnemonic = statement.mnemonic.as_str();
+        let operand_count = statement.operands.len();
+        let (atomic, aq, rl) = split_ordering(mnemonic);
+
+        let words = match mnemonic
+        {
+            // Register arithmatic
+            "add" | "sub" | "sll" | "slt" | "sltu" | "xor" | "srl" | "sra" | "or" | "and" |
+            "mul" | "mulh" | "mulhsu" | "mulhu" | "div" | "divu" | "rem" | "remu" =>
+            {
+                self.expect_operands(statement, 3)?;
+
+                let encode: RegisterEncoder = match mnemonic
+                {
+                    "add" => encoder::add,
+                    "sub" => encoder::sub,
+                    "sll" => encoder::sll,
+                    "slt" => encoder::slt,
+                    "sltu" => encoder::sltu,
+                    "xor" => encoder::xor,
+                    "srl" => encoder::srl,
+                    "sra" => encoder::sra,
+                    "or" => encoder::or,
+                    "and" => encoder::and,
+                    "mul" => encoder::mul,
+                    "mulh" => encoder::mulh,
+                    "mulhsu" => encoder::mulhsu,
+                    "mulhu" => encoder::mulhu,
+                    "div" => encoder::div,
+                    "divu" => encoder::divu,
+                    "rem" => encoder::rem,
+                    _ => encoder::remu
+                };
+
+                encode(self.register(statement, 0)?, self.register(statement, 1)?, self.register(statement, 2)?).map(|w| vec![w])
+            },
+            // Immediate arithmatic
+            "addi" | "slti" | "sltiu" | "xori" | "ori" | "andi" =>
+            {
+                self.expect_operands(statement, 3)?;
+
+                let encode: ImmediateEncoder = match mnemonic
+                {
+                    "addi" => encoder::addi,
+                    "slti" => encoder::slti,
+                    "sltiu" => encoder::sltiu,
+                    "xori" => encoder::xori,
+                    "ori" => encoder::ori,
+                    _ => encoder::andi
+                };
+
+                encode(self.register(statement, 0)?, self.register(statement, 1)?, self.immediate(statement, 2, -2048, 2047)? as i32).map(|w| vec![w])
+            },
+            // Immediate shifts
+            "slli" | "srli" | "srai" =>
+            {
+                self.expect_operands(statement, 3)?;
+
+                let encode: RegisterEncoder = match mnemonic
+                {
+                    "slli" => encoder::slli,
+                    "srli" => encoder::srli,
+                    _ => encoder::srai
+                };
+
+                encode(self.register(statement, 0)?, self.register(statement, 1)?, self.immediate(statement, 2, 0, 31)? as u8).map(|w| vec![w])
+            },
+            // Loads
+            "lb" | "lh" | "lw" | "lbu" | "lhu" =>
+            {
+                self.expect_operands(statement, 2)?;
+
+                let encode: ImmediateEncoder = match mnemonic
+                {
+                    "lb" => encoder::lb,
+                    "lh" => encoder::lh,
+                    "lw" => encoder::lw,
+                    "lbu" => encoder::lbu,
+                    _ => encoder::lhu
+                };
+
+                let (rs1, offset) = self.memory_operand(statement, 1)?;
+
+                encode(self.register(statement, 0)?, rs1, offset as i32).map(|w| vec![w])
+            },
+            // Stores
+            "sb" | "sh" | "sw" =>
+            {
+                self.expect_operands(statement, 2)?;
+
+                let encode: ImmediateEncoder = match mnemonic
+                {
+                    "sb" => encoder::sb,
+                    "sh" => encoder::sh,
+                    _ => encoder::sw
+                };
+
+                let (rs1, offset) = self.memory_operand(statement, 1)?;
+
+                encode(self.register(statement, 0)?, rs1, offset as i32).map(|w| vec![w])
+            },
+            // Branches
+            "beq" | "bne" | "blt" | "bge" | "bltu" | "bgeu" | "bgt" | "ble" | "bgtu" | "bleu" =>
+            {
+                self.expect_operands(statement, 3)?;
+
+                let (encode, swap): (ImmediateEncoder, bool) = match mnemonic
+                {
+                    "beq" => (encoder::beq, false),
+                    "bne" => (encoder::bne, false),
+                    "blt" => (encoder::blt, false),
+                    "bge" => (encoder::bge, false),
+                    "bltu" => (encoder::bltu, false),
+                    "bgeu" => (encoder::bgeu, false),
+                    "bgt" => (encoder::blt, true),
+                    "ble" => (encoder::bge, true),
+                    "bgtu" => (encoder::bltu, true),
+                    _ => (encoder::bgeu, true)
+                };
+
+                let (rs1, rs2) = if swap
+                {
+                    (self.register(statement, 1)?, self.register(statement, 0)?)
+                }
+                else
+                {
+                    (self.register(statement, 0)?, self.register(statement, 1)?)
+                };
+
+                encode(rs1, rs2, self.offset(statement, 2, 13)? as i32).map(|w| vec![w])
+            },
+            // Branches against zero
+            "beqz" | "bnez" | "blez" | "bgez" | "bltz" | "bgtz" =>
+            {
+                self.expect_operands(statement, 2)?;
+
+                let reg = self.register(statement, 0)?;
+                let offset = self.offset(statement, 1, 13)? as i32;
+
+                match mnemonic
+                {
+                    "beqz" => encoder::beq(reg, 0, offset),
+                    "bnez" => encoder::bne(reg, 0, offset),
+                    "blez" => encoder::bge(0, reg, offset),
+                    "bgez" => encoder::bge(reg, 0, offset),
+                    "bltz" => encoder::blt(reg, 0, offset),
+                    _ => encoder::blt(0, reg, offset)
+                }.map(|w| vec![w])
+            },
+            "lui" | "auipc" =>
+            {
+                self.expect_operands(statement, 2)?;
+
+                let encode = if mnemonic == "lui" {encoder::lui} else {encoder::auipc};
+                let imm = self.immediate(statement, 1, -0x80000, 0xFFFFF)? as u32 & 0xFFFFF;
+
+                encode(self.register(statement, 0)?, imm).map(|w| vec![w])
+            },
+            "jal" =>
+            {
+                let (rd, target) = match operand_count
+                {
+                    1 => (1, 0),
+                    _ =>
+                    {
+                        self.expect_operands(statement, 2)?;
+                        (self.register(statement, 0)?, 1)
+                    }
+                };
+
+                encoder::jal(rd, self.offset(statement, target, 21)? as i32).map(|w| vec![w])
+            },
+            "jalr" =>
+            {
+                let (rd, rs1, offset) = match operand_count
+                {
+                    1 => (1, self.register(statement, 0)?, 0),
+                    2 if parse_register(&statement.operands[1]).is_some() => (self.register(statement, 0)?, self.register(statement, 1)?, 0),
+                    2 =>
+                    {
+                        let (rs1, offset) = self.memory_operand(statement, 1)?;
+                        (self.register(statement, 0)?, rs1, offset)
+                    },
+                    _ =>
+                    {
+                        self.expect_operands(statement, 3)?;
+                        (self.register(statement, 0)?, self.register(statement, 1)?, self.immediate(statement, 2, -2048, 2047)?)
+                    }
+                };
+
+                encoder::jalr(rd, rs1, offset as i32).map(|w| vec![w])
+            },
+            "fence" =>
+            {
+                // A fence without operands orders everything
+                let (pred, succ) = match operand_count
+                {
+                    0 => (0b1111, 0b1111),
+                    _ =>
+                    {
+                        self.expect_operands(statement, 2)?;
+                        (self.fence_set(statement, 0)?, self.fence_set(statement, 1)?)
+                    }
+                };
+
+                encoder::fence(pred, succ).map(|w| vec![w])
+            },
+            "fence.tso" =>
+            {
+                self.expect_operands(statement, 0)?;
+                encoder::fence_tso().map(|w| vec![w])
+            },
+            "fence.i" =>
+            {
+                self.expect_operands(statement, 0)?;
+                encoder::fence_i().map(|w| vec![w])
+            },
+            "ecall" =>
+            {
+                self.expect_operands(statement, 0)?;
+                encoder::ecall().map(|w| vec![w])
+            },
+            "ebreak" =>
+            {
+                self.expect_operands(statement, 0)?;
+                encoder::ebreak().map(|w| vec![w])
+            },
+            "sret" =>
+            {
+                self.expect_operands(statement, 0)?;
+                encoder::sret().map(|w| vec![w])
+            },
+            "mret" =>
+            {
+                self.expect_operands(statement, 0)?;
+                encoder::mret().map(|w| vec![w])
+            },
+            "wfi" =>
+            {
+                self.expect_operands(statement, 0)?;
+                encoder::wfi().map(|w| vec![w])
+            },
+            "sfence.vma" =>
+            {
+                // Without operands every address space and address is flushed
+                let (rs1, rs2) = match operand_count
+                {
+                    0 => (0, 0),
+                    1 => (self.register(statement, 0)?, 0),
+                    _ =>
+                    {
+                        self.expect_operands(statement, 2)?;
+                        (self.register(statement, 0)?, self.register(statement, 1)?)
+                    }
+                };
+
+                encoder::sfence_vma(rs1, rs2).map(|w| vec![w])
+            },
+            "csrrw" | "csrrs" | "csrrc" | "csrrwi" | "csrrsi" | "csrrci" =>
+            {
+                self.expect_operands(statement, 3)?;
+
+                let (encode, immediate): (CsrEncoder, bool) = match mnemonic
+                {
+                    "csrrw" => (encoder::csrrw, false),
+                    "csrrs" => (encoder::csrrs, false),
+                    "csrrc" => (encoder::csrrc, false),
+                    "csrrwi" => (encoder::csrrwi, true),
+                    "csrrsi" => (encoder::csrrsi, true),
+                    _ => (encoder::csrrci, true)
+                };
+
+                let csr = self.immediate(statement, 1, 0, 0xFFF)? as u16;
+                let source = if immediate {self.immediate(statement, 2, 0, 31)? as u8} else {self.register(statement, 2)?};
+
+                encode(self.register(statement, 0)?, csr, source).map(|w| vec![w])
+            },
+            // Pseudo-instructions
+            "csrr" =>
+            {
+                self.expect_operands(statement, 2)?;
+                encoder::csrrs(self.register(statement, 0)?, self.immediate(statement, 1, 0, 0xFFF)? as u16, 0).map(|w| vec![w])
+            },
+            "rdcycle" | "rdtime" | "rdinstret" | "rdcycleh" | "rdtimeh" | "rdinstreth" =>
+            {
+                self.expect_operands(statement, 1)?;
+
+                let csr = match mnemonic
+                {
+                    "rdcycle" => 0xC00,
+                    "rdtime" => 0xC01,
+                    "rdinstret" => 0xC02,
+                    "rdcycleh" => 0xC80,
+                    "rdtimeh" => 0xC81,
+                    _ => 0xC82
+                };
+
+                encoder::csrrs(self.register(statement, 0)?, csr, 0).map(|w| vec![w])
+            },
+            "csrw" | "csrs" | "csrc" | "csrwi" | "csrsi" | "csrci" =>
+            {
+                self.expect_operands(statement, 2)?;
+
+                let (encode, immediate): (CsrEncoder, bool) = match mnemonic
+                {
+                    "csrw" => (encoder::csrrw, false),
+                    "csrs" => (encoder::csrrs, false),
+                    "csrc" => (encoder::csrrc, false),
+                    "csrwi" => (encoder::csrrwi, true),
+                    "csrsi" => (encoder::csrrsi, true),
+                    _ => (encoder::csrrci, true)
+                };
+
+                let csr = self.immediate(statement, 0, 0, 0xFFF)? as u16;
+                let source = if immediate {self.immediate(statement, 1, 0, 31)? as u8} else {self.register(statement, 1)?};
+
+                encode(0, csr, source).map(|w| vec![w])
+            },
+            "nop" =>
+            {
+                self.expect_operands(statement, 0)?;
+                encoder::addi(0, 0, 0).map(|w| vec![w])
+            },
+            "mv" =>
+            {
+                self.expect_operands(statement, 2)?;
+                encoder::addi(self.register(statement, 0)?, self.register(statement, 1)?, 0).map(|w| vec![w])
+            },
+            "not" =>
+            {
+                self.expect_operands(statement, 2)?;
+                encoder::xori(self.register(statement, 0)?, self.register(statement, 1)?, -1).map(|w| vec![w])
+            },
+            "neg" =>
+            {
+                self.expect_operands(statement, 2)?;
+                encoder::sub(self.register(statement, 0)?, 0, self.register(statement, 1)?).map(|w| vec![w])
+            },
+            "seqz" =>
+            {
+                self.expect_operands(statement, 2)?;
+                encoder::sltiu(self.register(statement, 0)?, self.register(statement, 1)?, 1).map(|w| vec![w])
+            },
+            "snez" =>
+            {
+                self.expect_operands(statement, 2)?;
+                encoder::sltu(self.register(statement, 0)?, 0, self.register(statement, 1)?).map(|w| vec![w])
+            },
+            "sltz" =>
+            {
+                self.expect_operands(statement, 2)?;
+                encoder::slt(self.register(statement, 0)?, self.register(statement, 1)?, 0).map(|w| vec![w])
+            },
+            "sgtz" =>
+            {
+                self.expect_operands(statement, 2)?;
+                encoder::slt(self.register(statement, 0)?, 0, self.register(statement, 1)?).map(|w| vec![w])
+            },
+            "j" =>
+            {
+                self.expect_operands(statement, 1)?;
+                encoder::jal(0, self.offset(statement, 0, 21)? as i32).map(|w| vec![w])
+            },
+            "jr" =>
+            {
+                self.expect_operands(statement, 1)?;
+                encoder::jalr(0, self.register(statement, 0)?, 0).map(|w| vec![w])
+            },
+            "ret" =>
+            {
+                self.expect_operands(statement, 0)?;
+                encoder::jalr(0, 1, 0).map(|w| vec![w])
+            },
+            "li" =>
+            {
+                self.expect_operands(statement, 2)?;
+
+                let rd = self.register(statement, 0)?;
+                let value = self.immediate(statement, 1, i32::MIN as i64, u32::MAX as i64)? as i32 as i64;
+                let (hi, lo) = split_hi_lo(value);
+
+                if statement.size == 8
+                {
+                    encoder::lui(rd, hi as u32).and_then(|first| encoder::addi(rd, rd, lo as i32).map(|second| vec![first, second]))
+                }
+                else if (-2048..2048).contains(&value)
+                {
+                    encoder::addi(rd, 0, value as i32).map(|w| vec![w])
+                }
+                else
+                {
+                    encoder::lui(rd, hi as u32).map(|w| vec![w])
+                }
+            },
+            "la" | "call" | "tail" =>
+            {
+                let rd = match mnemonic
+                {
+                    "la" =>
+                    {
+                        self.expect_operands(statement, 2)?;
+                        self.register(statement, 0)?
+                    },
+                    "call" =>
+                    {
+                        self.expect_operands(statement, 1)?;
+                        1
+                    },
+                    _ =>
+                    {
+                        self.expect_operands(statement, 1)?;
+                        6
+                    }
+                };
+
+                let target_index = statement.operands.len() - 1;
+                let offset = (self.evaluate_operand(statement, target_index)? - statement.address as i64) as i32 as i64;
+                let (hi, lo) = split_hi_lo(offset);
+
+                let second = match mnemonic
+                {
+                    "la" => encoder::addi(rd, rd, lo as i32),
+                    "call" => encoder::jalr(1, rd, lo as i32),
+                    _ => encoder::jalr(0, rd, lo as i32)
+                };
+
+                encoder::auipc(rd, hi as u32).and_then(|first| second.map(|second| vec![first, second]))
+            },
+            // Atomics
+            _ if atomic == "lr.w" =>
+            {
+                self.expect_operands(statement, 2)?;
+                encoder::lr_w(self.register(statement, 0)?, self.atomic_address(statement, 1)?, aq, rl).map(|w| vec![w])
+            },
+            _ if matches!(atomic, "sc.w" | "amoswap.w" | "amoadd.w" | "amoxor.w" | "amoand.w" | "amoor.w" |
+                                  "amomin.w" | "amomax.w" | "amominu.w" | "amomaxu.w") =>
+            {
+                self.expect_operands(statement, 3)?;
+
+                let encode: AtomicEncoder = match atomic
+                {
+                    "sc.w" => encoder::sc_w,
+                    "amoswap.w" => encoder::amoswap_w,
+                    "amoadd.w" => encoder::amoadd_w,
+                    "amoxor.w" => encoder::amoxor_w,
+                    "amoand.w" => encoder::amoand_w,
+                    "amoor.w" => encoder::amoor_w,
+                    "amomin.w" => encoder::amomin_w,
+                    "amomax.w" => encoder::amomax_w,
+                    "amominu.w" => encoder::amominu_w,
+                    _ => encoder::amomaxu_w
+                };
+
+                encode(self.register(statement, 0)?, self.register(statement, 1)?, self.atomic_address(statement, 2)?, aq, rl).map(|w| vec![w])
+            },
+            _ => return Err(AssemblerError::new(statement.line, format!("Unknown instruction '{}'", mnemonic)))
+        };
+
+        words.map_err(|error| AssemblerError::new(statement.line, error.to_string()))
+    }
+}
+
+/// Assemble RV32IMA source text placed at the base address into blocks of bytes ready to be written to memory at their addresses
+pub fn assemble(source: &str, base: u32) -> Result<Vec<(u32, Vec<u8>)>, AssemblerError>
+{
+    Assembler::new(base).assemble(source)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Assemble source placed at address 0 into a single block, giving its instruction words
+    fn words(source: &str) -> Vec<u32>
+    {
+        let blocks = assemble(source, 0).unwrap();
+        assert_eq!(blocks.len(), 1, "{}", source);
+
+        blocks[0].1.chunks(4).map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]])).collect()
+    }
+
+    #[test]
+    fn li_is_sized_for_its_value_during_layout()
+    {
+        assert_eq!(words("li a0, -2048"), vec![encoder::addi(10, 0, -2048).unwrap()]);
+        assert_eq!(words("li a0, 0x12345000"), vec![encoder::lui(10, 0x12345).unwrap()]);
+
+        // A set bit 11 makes the addi subtract, so lui carries one into its upper bits
+        assert_eq!(words("li a0, 0x12345FFF"), vec![encoder::lui(10, 0x12346).unwrap(), encoder::addi(10, 10, -1).unwrap()]);
+        assert_eq!(words("li a0, 2048"), vec![encoder::lui(10, 1).unwrap(), encoder::addi(10, 10, -2048).unwrap()]);
+
+        // Labels after an li are placed by the size it was given, and forward references always take two instructions
+        assert_eq!(words("li a0, 5\nhere: .word here"), vec![encoder::addi(10, 0, 5).unwrap(), 4]);
+        assert_eq!(words("li a0, end\nend: .word end"), vec![encoder::lui(10, 0).unwrap(), encoder::addi(10, 10, 8).unwrap(), 8]);
+    }
+
+    #[test]
+    fn labels_resolve_forwards_and_backwards()
+    {
+        let source = "beq a0, zero, end\nloop: addi a0, a0, -1\nbne a0, zero, loop\nend: jal zero, loop";
+
+        assert_eq!(words(source), vec![encoder::beq(10, 0, 12).unwrap(), encoder::addi(10, 10, -1).unwrap(),
+                                       encoder::bne(10, 0, -4).unwrap(), encoder::jal(0, -8).unwrap()]);
+    }
+
+    #[test]
+    fn errors_name_their_line()
+    {
+        assert_eq!(assemble("beq a0, a1, far\n.space 4096\nfar:", 0).unwrap_err(),
+                   AssemblerError::new(1, "Target is out of range (offset 4100)".to_string()));
+        assert_eq!(assemble("addi a0, a0, 1\nfrob a0", 0).unwrap_err(), AssemblerError::new(2, "Unknown instruction 'frob'".to_string()));
+        assert_eq!(assemble("j nowhere", 0).unwrap_err(), AssemblerError::new(1, "Unknown label 'nowhere'".to_string()));
+        assert_eq!(assemble(".org 8\n.org 4", 0).unwrap_err(), AssemblerError::new(2, ".org 0x00000004 moves backwards from 0x00000008".to_string()));
+    }
+
+    #[test]
+    fn statements_are_placed_in_blocks_at_their_addresses()
+    {
+        let blocks = assemble("addi a0, zero, 1\n.org 0x40000000\n.word 0x12345678\n.space 0x10000000\n.byte 1, 2", 0x100).unwrap();
+
+        assert_eq!(blocks, vec![(0x100, vec![0x13, 0x05, 0x10, 0x00]),
+                                (0x40000000, vec![0x78, 0x56, 0x34, 0x12]),
+                                (0x50000004, vec![1, 2])]);
+    }
+
+    #[test]
+    fn labels_after_a_gap_take_the_address_past_it()
+    {
+        // The alignment padding runs into the gap, so it is not written either
+        let blocks = assemble("jal zero, far\n.align 3\n.org 0x1000\nfar: .word far", 0).unwrap();
+
+        assert_eq!(blocks, vec![(0, encoder::jal(0, 0x1000).unwrap().to_le_bytes().to_vec()),
+                                (0x1000, vec![0x00, 0x10, 0x00, 0x00])]);
+    }
+
+    #[test]
+    fn lines_are_split_outside_of_literals()
+    {
+        assert_eq!(scan_line("start: li a0, ',' # x, y: z"), ("start: li a0, ',' ", vec![5], vec![12]));
+        assert_eq!(scan_line(".byte \"a;b\", ':' // c"), (".byte \"a;b\", ':' ", vec![], vec![11]));
+        assert_eq!(scan_line("addi a0, a0, 4 / 2"), ("addi a0, a0, 4 / 2", vec![], vec![7, 11]));
+    }
+
+    #[test]
+    fn character_literals_hold_one_character()
+    {
+        assert_eq!(assemble(".byte 'A', 'é' - 0x80", 0).unwrap(), vec![(0, vec![0x41, 0x69])]);
+
+        for source in [".byte ''", ".byte 'ab'", ".byte 'é", ".byte 'a' + 'b"]
+        {
+            assert!(assemble(source, 0).is_err(), "{}", source);
+        }
+
+        // Comment markers and separators inside a literal are part of it
+        assert_eq!(assemble(".byte '#', ';', ',', '/', ':' // punctuation", 0).unwrap(), vec![(0, vec![0x23, 0x3B, 0x2C, 0x2F, 0x3A])]);
+        assert_eq!(assemble("start: li a0, '/' ; slash\n.half ',' # comma", 0).unwrap(),
+                   vec![(0, vec![0x13, 0x05, 0xF0, 0x02, 0x2C, 0x00])]);
+    }
+}
\ No newline at end of file
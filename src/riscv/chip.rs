@@ -8,15 +8,29 @@ use super::Register32;
 
 #[allow(unused_imports)]
 use super::{MemoryAccess, MemoryAccess16, MemoryAccess32};
-use super::MotherboardMemory;
+use super::{MotherboardMemory, MemoryProgrammer, MemoryRegion, MemoryMapError, RegionKind, DEFAULT_MEMORY_MAP};
+use super::Device;
 
 use super::{HardwareZeroRegister, Register};
 
+use super::{Instruction, Operation};
+
 use super::ArithmaticLogicUnit;
 
 use super::sign_extend;
+use super::{is_compressed, expand_compressed};
+use super::{disassemble, disassemble_memory};
+
+use super::{CsrHandler, CsrAddresses, MIP_MSIP, MIP_MTIP, MIP_MEIP, SATP_MODE};
+use super::{MSTATUS_SIE, MSTATUS_MIE, MSTATUS_SPIE, MSTATUS_MPIE, MSTATUS_SPP, MSTATUS_MPP, MSTATUS_MPRV, MSTATUS_SUM, MSTATUS_MXR, MSTATUS_TW};
+use super::Clint;
+use super::{Plic, InterruptLine};
+use super::{Fault, Privilege, TrapCause, INTERRUPT_PRIORITY, TRAP_LOOP_LIMIT};
+use super::{AccessType, PageTableWalker, PAGE_SIZE};
+use super::CounterEvent;
 
-use super::{CsrHandler, CsrAddresses};
+use super::LoaderError;
+use super::AssemblerError;
 
 /// Chip Mode (Keeps track of where in executing an instruction the processor pauses at)
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -29,9 +43,65 @@ pub enum ChipMode
     ExecuteJump,
     ExecuteBranch,
     BranchCheck,
+    LoadReserved,
+    StoreConditional,
+    AtomicLoad,
+    AtomicStore,
+    WriteCsr,
+    MemoryStall,
+    WaitForInterrupt,
+}
+
+/// How a load or store which is not aligned to its width is handled (misaligned atomics always trap)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MisalignedPolicy
+{
+    /// Raise the load or store address misaligned exception
+    Trap,
+    /// Perform the access as if it were aligned
+    Emulate,
+    /// Split the access into aligned word bus cycles, taking a clock for each extra cycle
+    Split
+}
+
+/// Generate the 32 general purpose registers at their power on values (x0 is hardwired to zero)
+fn register_file() -> [Box<dyn Register32>; 32]
+{
+    [Box::new(HardwareZeroRegister::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new()),
+     Box::new(Register::new())]
 }
 
-/// RISCV 32I CPU Chip
+/// RISCV 32IMAC CPU Chip
 pub struct ChipCPU
 {
     src0_bus: Rc<RefCell<Bus>>,
@@ -42,23 +112,39 @@ pub struct ChipCPU
     data: Rc<RefCell<Bus>>,
 
     clock: usize,
+    idle_clocks: usize,
+    retired_instructions: usize,
+    traps_without_retiring: u32,
+    fault: Option<Fault>,
+
+    reset_vector: u32,
 
     inst: Register,
+    decoded: Instruction,
     pub program_counter: Register,
     output: Register,
     output2: Register,
     immediate: Register,
+    loaded: Register,
 
+    reservation: Option<u32>,
+
+    privilege: Privilege,
     mode: ChipMode,
+    stall_cycles: u32,
+    events: u32, // Counter events which happened in this clock
     memory_mode: u8, // 0b00: Byte, 0b10: Half Word, 0b10: Word, oring 0b100 makes the result unsigned
 
     registers: [Box<dyn Register32>; 32],
 
-    memory: Box<dyn MemoryAccess32>,
+    memory: Box<MotherboardMemory>,
+    clint: Rc<RefCell<Clint>>,
+    plic: Rc<RefCell<Plic>>,
 
     alu: ArithmaticLogicUnit,
 
     pub debug_display: bool,
+    pub misaligned_policy: MisalignedPolicy,
 
     csr_handle: CsrHandler
 }
@@ -75,40 +161,12 @@ impl ChipCPU
         let ram_addr_bus = Rc::new(RefCell::new(Bus::new()));
         let data = Rc::new(RefCell::new(Bus::new()));
 
+        let clint = Rc::new(RefCell::new(Clint::new(1)));
+        let plic = Rc::new(RefCell::new(Plic::new()));
+
         Self
         {
-            registers: [Box::new(HardwareZeroRegister::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),
-                        Box::new(Register::new()),],
+            registers: register_file(),
             
             alu: ArithmaticLogicUnit::new(src0_bus.clone(), src1_bus.clone(), alu_out_bus.clone()),
 
@@ -120,18 +178,34 @@ impl ChipCPU
             data,
 
             clock: 0,
+            idle_clocks: 0,
+            retired_instructions: 0,
+            traps_without_retiring: 0,
+            fault: None,
+
+            reset_vector: 0,
 
             inst: Register::new(),
+            decoded: Instruction::new(0),
             program_counter: Register::new(),
             output: Register::new(),
             output2: Register::new(),
             immediate: Register::new(),
+            loaded: Register::new(),
+
+            reservation: None,
 
+            privilege: Privilege::Machine,
             mode: ChipMode::LoadInstruction,
+            stall_cycles: 0,
+            events: 0,
 
-            memory: Box::new(MotherboardMemory::new()),
+            memory: Box::new(MotherboardMemory::from_map(&DEFAULT_MEMORY_MAP, clint.clone(), plic.clone()).expect("the default memory map is valid")),
+            clint,
+            plic,
 
             debug_display: false,
+            misaligned_policy: MisalignedPolicy::Trap,
 
             memory_mode: 0,
 
@@ -145,19 +219,64 @@ impl ChipCPU
         self.registers[reg].as_ref().get_value()
     }
 
+    /// Read the value of the instruction register (should not be used baring for debug output)
+    pub fn read_instruction_value(&self) -> u32
+    {
+        self.inst.get_value()
+    }
+
+    /// Get the current step of the instruction sequence
+    pub fn get_mode(&self) -> ChipMode
+    {
+        self.mode
+    }
+
+    /// Get the privilege level the hart is running at
+    pub fn get_privilege(&self) -> Privilege
+    {
+        self.privilege
+    }
+
+    /// Get the number of clock cycles run so far
+    pub fn get_clock(&self) -> usize
+    {
+        self.clock
+    }
+
+    /// Get the number of clock cycles spent waiting for an interrupt
+    pub fn get_idle_clocks(&self) -> usize
+    {
+        self.idle_clocks
+    }
+
+    /// Get the number of instructions retired, not counting those which raised an exception (the count carries on over a warm reset)
+    pub fn get_retired_instructions(&self) -> usize
+    {
+        self.retired_instructions
+    }
+
+    /// Get the fault which left the hart unable to make progress, if there has been one
+    pub fn get_fault(&self) -> Option<Fault>
+    {
+        self.fault
+    }
+
     /// Set the memory to read
     pub fn memory_read(&mut self)
     {
+        let addr = self.ram_addr_bus.borrow().read_value();
+        let unsigned = self.memory_mode & 0b100 != 0;
+
+        // Widths other than byte and half word read a word (encodings of wider accesses are illegal and never get here)
         self.data.borrow_mut().enable_value(
-            match self.memory_mode
+            match self.memory_mode & 0b11
             {
-                0b000 => sign_extend(self.memory.read_byte(self.ram_addr_bus.borrow().read_value()) as u32, 7),
-                0b001 => sign_extend(self.memory.read_u16(self.ram_addr_bus.borrow().read_value()) as u32, 15),
-                0b010 => self.memory.read_u32(self.ram_addr_bus.borrow().read_value()),
-                0b100 => self.memory.read_byte(self.ram_addr_bus.borrow().read_value()) as u32,
-                0b101 => self.memory.read_u16(self.ram_addr_bus.borrow().read_value()) as u32,
-                default => panic!("Unknown memory read mode {:03b}", default)
-            });  
+                0b00 if unsigned => self.memory.read_byte(addr) as u32,
+                0b00 => sign_extend(self.memory.read_byte(addr) as u32, 7),
+                0b01 if unsigned => self.memory.read_u16(addr) as u32,
+                0b01 => sign_extend(self.memory.read_u16(addr) as u32, 15),
+                _ => self.memory.read_u32(addr)
+            });
     }
 
     /// Set the memory to write
@@ -166,12 +285,17 @@ impl ChipCPU
         let addr = self.ram_addr_bus.borrow().read_value();
         let val = self.data.borrow().read_value();
 
+        // A store to the reserved word breaks the reservation
+        if self.reservation == Some(addr & !0b11)
+        {
+            self.reservation = None;
+        }
+
         match self.memory_mode & 0b11
         {
-            0b000 => self.memory.write_byte(addr, (val & 0xFF) as u8),
-            0b001 => self.memory.write_u16(addr, (val & 0xFFFF) as u16),
-            0b010 => self.memory.write_u32(addr, val),
-            default => panic!("Unknown memory write mode {:03b}", default)
+            0b00 => self.memory.write_byte(addr, (val & 0xFF) as u8),
+            0b01 => self.memory.write_u16(addr, (val & 0xFFFF) as u16),
+            _ => self.memory.write_u32(addr, val)
         }
     }
 
@@ -191,6 +315,497 @@ impl ChipCPU
         self.csr_handle.write_csr(addr, val);
     }
 
+    /// Set the address the program counter is set to by a reset
+    pub fn set_reset_vector(&mut self, addr: u32)
+    {
+        self.reset_vector = addr;
+    }
+
+    /// Get the address the program counter is set to by a reset
+    pub fn get_reset_vector(&self) -> u32
+    {
+        self.reset_vector
+    }
+
+    /// Warm reset, returning the hart, its CSRs and the interrupt controllers to their power on state and starting again at
+    /// the reset vector, while the memory keeps its contents (the clock count carries on, but mcycle starts again from 0)
+    pub fn reset(&mut self)
+    {
+        for bus in [&self.src0_bus, &self.src1_bus, &self.alu_out_bus, &self.ram_addr_bus, &self.data]
+        {
+            *bus.borrow_mut() = Bus::new();
+        }
+
+        self.alu = ArithmaticLogicUnit::new(self.src0_bus.clone(), self.src1_bus.clone(), self.alu_out_bus.clone());
+        self.registers = register_file();
+
+        self.inst = Register::new();
+        self.decoded = Instruction::new(0);
+        self.program_counter.value = self.reset_vector;
+        self.output = Register::new();
+        self.output2 = Register::new();
+        self.immediate = Register::new();
+        self.loaded = Register::new();
+
+        self.reservation = None;
+
+        self.privilege = Privilege::Machine;
+        self.mode = ChipMode::LoadInstruction;
+        self.stall_cycles = 0;
+        self.events = 0;
+        self.memory_mode = 0;
+
+        self.traps_without_retiring = 0;
+        self.fault = None;
+
+        self.csr_handle = CsrHandler::new();
+        self.clint.borrow_mut().reset();
+        self.memory.reset_devices();
+        self.plic.borrow_mut().reset();
+    }
+
+    /// Set the number of clock cycles per tick of the CLINT timer
+    pub fn set_timer_ratio(&mut self, cycles_per_tick: u32)
+    {
+        self.clint.borrow_mut().set_tick_ratio(cycles_per_tick);
+    }
+
+    /// Replace the memory map with new memory chips placed at the given bases (anything already loaded is lost)
+    pub fn set_memory_map(&mut self, map: &[(RegionKind, u32)]) -> Result<(), MemoryMapError>
+    {
+        *self.memory = MotherboardMemory::from_map(map, self.clint.clone(), self.plic.clone())?;
+
+        Ok(())
+    }
+
+    /// Map a peripheral into size bytes of the address space from base, to be clocked with the hart
+    pub fn attach_device(&mut self, name: &str, base: u32, size: u32, device: Rc<RefCell<dyn Device>>) -> Result<(), MemoryMapError>
+    {
+        self.memory.add_region(MemoryRegion::device(name, base, size, device))
+    }
+
+    /// Get an interrupt line which drives the given source of the PLIC
+    pub fn interrupt_line(&self, source: u32) -> InterruptLine
+    {
+        InterruptLine::new(self.plic.clone(), source)
+    }
+
+    /// Drive the interrupt pending bits of mip from the interrupt lines of the devices
+    fn update_interrupt_lines(&mut self)
+    {
+        let clint = self.clint.borrow();
+        let mut lines = 0;
+
+        if clint.software_interrupt()
+        {
+            lines |= MIP_MSIP;
+        }
+
+        if clint.timer_interrupt()
+        {
+            lines |= MIP_MTIP;
+        }
+
+        if self.plic.borrow().external_interrupt()
+        {
+            lines |= MIP_MEIP;
+        }
+
+        self.csr_handle.set_interrupt_lines(lines);
+    }
+
+    /// Check if an interrupt is pending and enabled in mie, which ends a wait whether or not it is taken
+    fn interrupt_waiting(&mut self) -> bool
+    {
+        self.csr_handle.read_csr(CsrAddresses::Mip as u32) & self.csr_handle.read_csr(CsrAddresses::Mie as u32) != 0
+    }
+
+    /// Skip the clocks the hart would spend waiting for an interrupt, up to the given clock of the next external event, the
+    /// CLINT timer interrupt or the next change of a peripheral, giving false if nothing can end the wait
+    pub fn fast_forward(&mut self, until: Option<usize>) -> bool
+    {
+        if self.mode != ChipMode::WaitForInterrupt || self.interrupt_waiting()
+        {
+            return true;
+        }
+
+        // Stop a clock short of the timer interrupt, so the wait ends through a normal clock
+        let timer = if self.csr_handle.read_csr(CsrAddresses::Mie as u32) & MIP_MTIP != 0
+        {
+            self.clint.borrow().clocks_until_timer().map(|clocks| self.clock.saturating_add(clocks.saturating_sub(1) as usize))
+        }
+        else
+        {
+            None
+        };
+
+        // Peripherals may raise an interrupt when they change, so they also get a normal clock for it
+        let device = self.memory.clocks_until_event().map(|clocks| self.clock.saturating_add(clocks.saturating_sub(1) as usize));
+
+        let target = match until.into_iter().chain(timer).chain(device).min()
+        {
+            Some(target) => target,
+            None => return false
+        };
+
+        let clocks = target.saturating_sub(self.clock);
+
+        if clocks > 0
+        {
+            self.clint.borrow_mut().advance(clocks as u64);
+            self.memory.advance(clocks as u64);
+            self.update_interrupt_lines();
+            self.csr_handle.set_time(self.clint.borrow().get_mtime());
+            self.csr_handle.count_events(CounterEvent::Cycle.bit(), ChipMode::WaitForInterrupt as u32, clocks as u64);
+
+            self.idle_clocks += clocks;
+            self.clock += clocks;
+        }
+
+        true
+    }
+
+    /// Find the highest priority interrupt which is pending, enabled in mie and globally enabled for the privilege it is taken at
+    fn pending_interrupt(&mut self) -> Option<TrapCause>
+    {
+        let mstatus = self.csr_handle.read_csr(CsrAddresses::Mstatus as u32);
+        let mideleg = self.csr_handle.read_csr(CsrAddresses::Mideleg as u32);
+        let pending = self.csr_handle.read_csr(CsrAddresses::Mip as u32) & self.csr_handle.read_csr(CsrAddresses::Mie as u32);
+
+        // Interrupts taken at a higher privilege are always enabled, and delegated interrupts are never taken in machine mode
+        let machine_enabled = self.privilege < Privilege::Machine || mstatus & MSTATUS_MIE != 0;
+        let supervisor_enabled = self.privilege < Privilege::Supervisor || (self.privilege == Privilege::Supervisor && mstatus & MSTATUS_SIE != 0);
+
+        INTERRUPT_PRIORITY.iter().copied().find(|cause|
+        {
+            let bit = 1 << cause.code();
+
+            pending & bit != 0 && if mideleg & bit != 0 {supervisor_enabled} else {machine_enabled}
+        })
+    }
+
+    /// Enter the trap handler, recording the cause, the trapping program counter and the trap value
+    fn enter_trap(&mut self, cause: TrapCause, value: u32)
+    {
+        self.events |= if cause.is_interrupt() {CounterEvent::Interrupt.bit()} else {CounterEvent::Exception.bit()};
+
+        let delegation = if cause.is_interrupt() {CsrAddresses::Mideleg} else {CsrAddresses::Medeleg};
+        let delegated = self.privilege < Privilege::Machine && self.csr_handle.read_csr(delegation as u32) & (1 << cause.code()) != 0;
+
+        let mstatus = self.csr_handle.read_csr(CsrAddresses::Mstatus as u32);
+
+        let vector = if delegated
+        {
+            // Stack the interrupt enable into SPIE and disable supervisor interrupts, SPP records if the trap came from supervisor mode
+            let previous_enable = if mstatus & MSTATUS_SIE != 0 {MSTATUS_SPIE} else {0};
+            let previous_privilege = if self.privilege == Privilege::Supervisor {MSTATUS_SPP} else {0};
+
+            self.csr_handle.write_csr(CsrAddresses::Mstatus as u32, (mstatus & !(MSTATUS_SIE | MSTATUS_SPIE | MSTATUS_SPP)) | previous_enable | previous_privilege);
+            self.csr_handle.write_csr(CsrAddresses::Sepc as u32, self.program_counter.value);
+            self.csr_handle.write_csr(CsrAddresses::Scause as u32, cause.mcause());
+            self.csr_handle.write_csr(CsrAddresses::Stval as u32, value);
+
+            self.privilege = Privilege::Supervisor;
+            CsrAddresses::Stvec
+        }
+        else
+        {
+            // Stack the interrupt enable into MPIE and disable interrupts, MPP records the privilege the trap came from
+            let previous_enable = if mstatus & MSTATUS_MIE != 0 {MSTATUS_MPIE} else {0};
+            let previous_privilege = self.privilege.bits() << 11;
+
+            self.csr_handle.write_csr(CsrAddresses::Mstatus as u32, (mstatus & !(MSTATUS_MIE | MSTATUS_MPIE | MSTATUS_MPP)) | previous_enable | previous_privilege);
+            self.csr_handle.write_csr(CsrAddresses::Mepc as u32, self.program_counter.value);
+            self.csr_handle.write_csr(CsrAddresses::Mcause as u32, cause.mcause());
+            self.csr_handle.write_csr(CsrAddresses::Mtval as u32, value);
+
+            self.privilege = Privilege::Machine;
+            CsrAddresses::Mtvec
+        };
+
+        let tvec = self.csr_handle.read_csr(vector as u32);
+
+        // Vectored mode (mode 1) sends interrupts to base + 4 * cause, everything else goes to the base
+        let base = tvec & !0b11;
+
+        let target = if tvec & 0b11 == 1 && cause.is_interrupt()
+        {
+            base.wrapping_add(4 * cause.code())
+        }
+        else
+        {
+            base
+        };
+
+        // An access which traps takes no extra bus cycles
+        self.stall_cycles = 0;
+
+        // With no handler to go to, or one which keeps trapping, the hart can never make progress again
+        self.traps_without_retiring += 1;
+
+        if self.fault.is_none()
+        {
+            if !self.csr_handle.is_trap_vector_written(vector)
+            {
+                self.fault = Some(Fault::NoTrapHandler(cause, self.program_counter.value));
+            }
+            else if self.traps_without_retiring >= TRAP_LOOP_LIMIT
+            {
+                self.fault = Some(Fault::TrapLoop(cause, self.program_counter.value));
+            }
+        }
+
+        self.data.borrow_mut().enable_value(target);
+        self.program_counter.set_from_bus(&self.data);
+    }
+
+    /// Return from a machine (mret) or supervisor (sret) mode trap handler, restoring the interrupt enable and privilege
+    fn return_from_trap(&mut self, level: Privilege)
+    {
+        let mstatus = self.csr_handle.read_csr(CsrAddresses::Mstatus as u32);
+
+        let (mstatus, privilege, epc) = if level == Privilege::Machine
+        {
+            let enable = if mstatus & MSTATUS_MPIE != 0 {MSTATUS_MIE} else {0};
+
+            // MPP is left as user mode, the least privileged mode
+            ((mstatus & !(MSTATUS_MIE | MSTATUS_MPP)) | enable | MSTATUS_MPIE, Privilege::from_bits((mstatus & MSTATUS_MPP) >> 11), CsrAddresses::Mepc)
+        }
+        else
+        {
+            let enable = if mstatus & MSTATUS_SPIE != 0 {MSTATUS_SIE} else {0};
+            let privilege = if mstatus & MSTATUS_SPP != 0 {Privilege::Supervisor} else {Privilege::User};
+
+            ((mstatus & !(MSTATUS_SIE | MSTATUS_SPP)) | enable | MSTATUS_SPIE, privilege, CsrAddresses::Sepc)
+        };
+
+        // Returning below machine mode stops loads and stores using the MPP privilege
+        let mstatus = if privilege < Privilege::Machine {mstatus & !MSTATUS_MPRV} else {mstatus};
+
+        self.csr_handle.write_csr(CsrAddresses::Mstatus as u32, mstatus);
+        self.privilege = privilege;
+
+        self.ram_addr_bus.borrow_mut().enable_value(epc as u32);
+        self.csr_read();
+
+        // The low bit of the epc is always clear, as instructions are at least 2 byte aligned
+        let target = self.data.borrow().read_value() & !1;
+        self.data.borrow_mut().enable_value(target);
+        self.program_counter.set_from_bus(&self.data);
+    }
+
+    /// Get the privilege a memory access is made at, loads and stores in machine mode use MPP when MPRV is set
+    fn access_privilege(&mut self, access: AccessType) -> Privilege
+    {
+        let mstatus = self.csr_handle.read_csr(CsrAddresses::Mstatus as u32);
+
+        if self.privilege == Privilege::Machine && access != AccessType::Fetch && mstatus & MSTATUS_MPRV != 0
+        {
+            Privilege::from_bits((mstatus & MSTATUS_MPP) >> 11)
+        }
+        else
+        {
+            self.privilege
+        }
+    }
+
+    /// Check if an access is translated through the Sv32 page tables (satp is in Sv32 mode below machine mode)
+    fn paging_enabled(&mut self, access: AccessType) -> bool
+    {
+        self.access_privilege(access) < Privilege::Machine && self.csr_handle.read_csr(CsrAddresses::Satp as u32) & SATP_MODE != 0
+    }
+
+    /// Translate a virtual address to a physical address and check the access against PMP and the memory map, raising the page fault or access fault if either fails
+    fn translate(&mut self, addr: u32, size: u32, access: AccessType) -> Option<u32>
+    {
+        let privilege = self.access_privilege(access);
+
+        let physical = if self.paging_enabled(access)
+        {
+            let mstatus = self.csr_handle.read_csr(CsrAddresses::Mstatus as u32);
+            let satp = self.csr_handle.read_csr(CsrAddresses::Satp as u32);
+
+            let walker = PageTableWalker
+            {
+                satp,
+                privilege,
+                sum: mstatus & MSTATUS_SUM != 0,
+                mxr: mstatus & MSTATUS_MXR != 0,
+                pmp: self.csr_handle.get_pmp()
+            };
+
+            match walker.translate(self.memory.as_mut(), addr, access)
+            {
+                Ok(physical) => physical,
+                Err(cause) =>
+                {
+                    // The trap value of a page fault is the virtual address
+                    self.enter_trap(cause, addr);
+
+                    return None;
+                }
+            }
+        }
+        else
+        {
+            addr
+        };
+
+        // Addresses which are not decoded to any device fault the same way as those PMP does not permit
+        if !self.csr_handle.get_pmp().check(physical, size, access, privilege) || !self.memory.is_mapped(physical, size)
+        {
+            // The trap value of an access fault is also the virtual address
+            self.enter_trap(access.access_fault(), addr);
+
+            return None;
+        }
+
+        Some(physical)
+    }
+
+    /// Translate the virtual address on the ALU output bus for an access of the current memory mode, replacing it with the physical address, giving false if it raised a fault
+    fn translate_alu_output(&mut self, access: AccessType) -> bool
+    {
+        let addr = self.alu_out_bus.borrow().read_value();
+        let width = 1 << (self.memory_mode & 0b11);
+
+        match self.translate(addr, width, access)
+        {
+            Some(physical) =>
+            {
+                self.alu_out_bus.borrow_mut().enable_value(physical);
+
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Apply the misaligned access policy to an access of the current memory mode, giving false if it raised the exception instead
+    fn check_data_alignment(&mut self, addr: u32, cause: TrapCause, atomic: bool) -> bool
+    {
+        let width = 1 << (self.memory_mode & 0b11);
+
+        if addr.is_multiple_of(width)
+        {
+            return true;
+        }
+
+        // Pages either side of a boundary need not be next to each other in physical memory, so an access across one always traps
+        let access = if cause == TrapCause::LoadAddressMisaligned {AccessType::Load} else {AccessType::Store};
+        let crosses_page = addr % PAGE_SIZE + width > PAGE_SIZE && self.paging_enabled(access);
+        let splittable = !atomic && !crosses_page;
+
+        match self.misaligned_policy
+        {
+            MisalignedPolicy::Emulate if splittable => true,
+            MisalignedPolicy::Split if splittable =>
+            {
+                // One extra bus cycle for each further aligned word the access touches
+                self.stall_cycles = (addr % 4 + width - 1) / 4;
+
+                true
+            },
+            _ =>
+            {
+                self.enter_trap(cause, addr);
+
+                false
+            }
+        }
+    }
+
+    /// Fetch the instruction at the program counter into the instruction register, giving false if it raised a page fault or access fault
+    fn fetch_instruction(&mut self) -> bool
+    {
+        let physical = match self.translate(self.program_counter.value, 2, AccessType::Fetch)
+        {
+            Some(physical) => physical,
+            None => return false
+        };
+
+        // Read the first 16 bit parcel of the instruction from memory
+        self.ram_addr_bus.borrow_mut().enable_value(physical);
+        self.memory_mode = 0b101;
+        self.memory_read();
+
+        let parcel = self.data.borrow().read_value() as u16;
+
+        let length = if is_compressed(parcel)
+        {
+            // Expand to the 32 bit equivalent, reserved encodings are kept as the parcel which decodes as illegal
+            self.data.borrow_mut().enable_value(expand_compressed(parcel).unwrap_or(parcel as u32));
+
+            2
+        }
+        else
+        {
+            // Read the second parcel separately, as the instruction may straddle a word or page boundary
+            let physical = match self.translate(self.program_counter.value.wrapping_add(2), 2, AccessType::Fetch)
+            {
+                Some(physical) => physical,
+                None => return false
+            };
+
+            self.ram_addr_bus.borrow_mut().enable_value(physical);
+            self.memory_read();
+
+            let upper = self.data.borrow().read_value();
+            self.data.borrow_mut().enable_value((upper << 16) | parcel as u32);
+
+            4
+        };
+
+        self.inst.set_from_bus(&self.data);
+
+        // Add the instruction length to the program counter
+        self.program_counter.enable_on_bus(&self.src0_bus);
+        self.src1_bus.borrow_mut().enable_value(length);
+        self.alu.mode = 0;
+        self.alu.sub_flag = false;
+        self.alu.tick();
+        self.output2.set_from_bus(&self.alu_out_bus);
+
+        true
+    }
+
+    /// Check if a CSR instruction writes the CSR, CSRRS and CSRRC (and their immediate forms) do not when rs1 is x0 or the immediate is zero
+    fn csr_writes(instruction: &Instruction) -> bool
+    {
+        matches!(instruction.operation, Operation::Csrrw | Operation::Csrrwi) || instruction.rs1 != 0
+    }
+
+    /// Get the counter events of the decoded instruction retiring in a clock spent in the given mode
+    fn retired_events(&self, mode: ChipMode) -> u32
+    {
+        let kind = match self.decoded.operation
+        {
+            Operation::Lb | Operation::Lh | Operation::Lw | Operation::Lbu | Operation::Lhu => CounterEvent::Load.bit(),
+            Operation::Sb | Operation::Sh | Operation::Sw => CounterEvent::Store.bit(),
+            Operation::LrW | Operation::ScW | Operation::AmoswapW | Operation::AmoaddW | Operation::AmoxorW |
+            Operation::AmoandW | Operation::AmoorW | Operation::AmominW | Operation::AmomaxW |
+            Operation::AmominuW | Operation::AmomaxuW => CounterEvent::Atomic.bit(),
+            Operation::Jal | Operation::Jalr => CounterEvent::Jump.bit(),
+            // A taken branch finishes in ExecuteBranch, one which is not taken in BranchCheck
+            Operation::Beq | Operation::Bne | Operation::Blt | Operation::Bge | Operation::Bltu | Operation::Bgeu =>
+            {
+                if mode == ChipMode::ExecuteBranch {CounterEvent::BranchTaken.bit()} else {CounterEvent::BranchNotTaken.bit()}
+            },
+            _ => 0
+        };
+
+        CounterEvent::InstructionRetired.bit() | kind
+    }
+
+    /// Set the ALU to compute an arithmatic operation
+    fn set_alu_operation(&mut self, operation: Operation)
+    {
+        let (mode, sub_flag) = operation.alu_mode().unwrap_or((0, false));
+
+        self.alu.mode = mode;
+        self.alu.sub_flag = sub_flag;
+    }
+
     /// Clock to next instruction
     pub fn clock_to_instruction(&mut self)
     {
@@ -207,30 +822,33 @@ impl ChipCPU
     {
         self.alu.tick();
 
+        self.clint.borrow_mut().tick();
+        self.memory.tick();
+        self.update_interrupt_lines();
+        self.csr_handle.set_time(self.clint.borrow().get_mtime());
+
+        let mode = self.mode;
+
         match self.mode
         {
             ChipMode::LoadInstruction =>
             {
-                // Read instruction from memory
-                self.program_counter.enable_on_bus(&self.ram_addr_bus);
-                self.memory_mode = 0b010;
-                self.memory_read();
-                self.inst.set_from_bus(&self.data);
+                // Take a pending interrupt before fetching the next instruction
+                if let Some(cause) = self.pending_interrupt()
+                {
+                    self.enter_trap(cause, 0);
+                }
 
-                // Add 4 to the program counter
-                self.program_counter.enable_on_bus(&self.src0_bus);
-                self.src1_bus.borrow_mut().enable_value(4);
-                self.alu.mode = 0;
-                self.alu.sub_flag = false;
-                self.alu.tick();
-                self.output2.set_from_bus(&self.alu_out_bus);
+                // An instruction page fault goes to its handler, which is fetched instead
+                let fetched = self.fetch_instruction() || self.fetch_instruction();
 
                 if self.debug_display
                 {
                     println!("{:?}", self);
                 }
 
-                self.mode = ChipMode::ExecuteInstruction;
+                // If the handler could not be fetched either, try again on the next clock
+                self.mode = if fetched {ChipMode::ExecuteInstruction} else {ChipMode::LoadInstruction};
             },
             ChipMode::ExecuteInstruction =>
             {
@@ -238,151 +856,328 @@ impl ChipCPU
 
                 // Decode the instruction (this would be done with discrete logic, this is mostly to generate the proper immediates)
                 let instruction = super::Instruction::new(self.inst.get_value());
+                self.decoded = instruction;
 
                 self.immediate.value = instruction.immediate;
-                // Arithmatic Immediate commands
-                if instruction.opcode == 0b0010011
-                {
-                    self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
-                    self.immediate.enable_on_bus(&self.src1_bus);
-                    self.alu.mode = instruction.funct3 as usize;
-                    self.alu.sub_flag = (instruction.funct7 & 0b0100000) > 0 && instruction.funct3 == 0b101;
 
-                    self.alu.tick();
-                }
-                // Arithmatic Register Commands
-                else if instruction.opcode == 0b0110011
+                match instruction.operation
                 {
-                    self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
-                    self.registers[instruction.rs2 as usize].enable_on_bus(&self.src1_bus);
-                    self.alu.mode = instruction.funct3 as usize;
-                    self.alu.sub_flag = (instruction.funct7 & 0b0100000) > 0;
+                    // Arithmatic Immediate commands
+                    Operation::Addi | Operation::Slti | Operation::Sltiu | Operation::Xori | Operation::Ori |
+                    Operation::Andi | Operation::Slli | Operation::Srli | Operation::Srai =>
+                    {
+                        self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
+                        self.immediate.enable_on_bus(&self.src1_bus);
+                        self.set_alu_operation(instruction.operation);
+
+                        self.alu.tick();
+                    },
+                    // Arithmatic Register Commands
+                    Operation::Add | Operation::Sub | Operation::Sll | Operation::Slt | Operation::Sltu |
+                    Operation::Xor | Operation::Srl | Operation::Sra | Operation::Or | Operation::And |
+                    Operation::Mul | Operation::Mulh | Operation::Mulhsu | Operation::Mulhu |
+                    Operation::Div | Operation::Divu | Operation::Rem | Operation::Remu =>
+                    {
+                        self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
+                        self.registers[instruction.rs2 as usize].enable_on_bus(&self.src1_bus);
+                        self.set_alu_operation(instruction.operation);
 
-                    self.alu.tick();
-                }
-                // LUI
-                else if instruction.opcode == 0b0110111
-                {
-                    self.immediate.enable_on_bus(&self.src0_bus);
-                    self.registers[0].enable_on_bus(&self.src1_bus);
+                        self.alu.tick();
+                    },
+                    Operation::Lui =>
+                    {
+                        self.immediate.enable_on_bus(&self.src0_bus);
+                        self.registers[0].enable_on_bus(&self.src1_bus);
 
-                    self.alu.mode = 0;
-                    self.alu.sub_flag = false;
+                        self.alu.mode = 0;
+                        self.alu.sub_flag = false;
 
-                    self.alu.tick();
-                }
-                // AUIPC
-                else if instruction.opcode == 0b0010111
-                {
-                    self.immediate.enable_on_bus(&self.src0_bus);
-                    self.program_counter.enable_on_bus(&self.src1_bus);
+                        self.alu.tick();
+                    },
+                    Operation::Auipc =>
+                    {
+                        self.immediate.enable_on_bus(&self.src0_bus);
+                        self.program_counter.enable_on_bus(&self.src1_bus);
 
-                    self.alu.mode = 0;
-                    self.alu.sub_flag = false;
+                        self.alu.mode = 0;
+                        self.alu.sub_flag = false;
 
-                    self.alu.tick();
-                }
-                // Load
-                else if instruction.opcode == 0b0000011
-                {
-                    self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
-                    self.immediate.enable_on_bus(&self.src1_bus);
-                    self.alu.mode = 0;
-                    self.alu.sub_flag = false;
+                        self.alu.tick();
+                    },
+                    // Load
+                    Operation::Lb | Operation::Lh | Operation::Lw | Operation::Lbu | Operation::Lhu =>
+                    {
+                        self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
+                        self.immediate.enable_on_bus(&self.src1_bus);
+                        self.alu.mode = 0;
+                        self.alu.sub_flag = false;
 
-                    self.alu.tick();
+                        self.alu.tick();
+
+                        self.memory_mode = instruction.operation.memory_mode().unwrap_or(0b010);
+
+                        let addr = self.alu_out_bus.borrow().read_value();
+
+                        // The physical address replaces the virtual address on the ALU output
+                        next_mode = if self.check_data_alignment(addr, TrapCause::LoadAddressMisaligned, false) &&
+                                       self.translate_alu_output(AccessType::Load)
+                        {
+                            ChipMode::LoadData
+                        }
+                        else
+                        {
+                            ChipMode::LoadInstruction
+                        };
+                    },
+                    // Store
+                    Operation::Sb | Operation::Sh | Operation::Sw =>
+                    {
+                        self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
+                        self.immediate.enable_on_bus(&self.src1_bus);
+                        self.alu.mode = 0;
+                        self.alu.sub_flag = false;
 
-                    next_mode = ChipMode::LoadData;
-                }
-                // Store
-                else if instruction.opcode == 0b0100011
-                {
-                    self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
-                    self.immediate.enable_on_bus(&self.src1_bus);
-                    self.alu.mode = 0;
-                    self.alu.sub_flag = false;
+                        self.alu.tick();
 
-                    self.alu.tick();
+                        self.output.set_from_bus(&self.alu_out_bus);
+                        self.memory_mode = instruction.operation.memory_mode().unwrap_or(0b010);
 
-                    self.output.set_from_bus(&self.alu_out_bus);
-                    self.output.enable_on_bus(&self.ram_addr_bus);
+                        if self.check_data_alignment(self.output.value, TrapCause::StoreAddressMisaligned, false) &&
+                           self.translate_alu_output(AccessType::Store)
+                        {
+                            self.output.set_from_bus(&self.alu_out_bus);
+                            self.output.enable_on_bus(&self.ram_addr_bus);
 
-                    self.registers[instruction.rs2 as usize].enable_on_bus(&self.data);
-                    self.memory_write();
+                            self.registers[instruction.rs2 as usize].enable_on_bus(&self.data);
+                            self.memory_write();
 
-                    next_mode = ChipMode::LoadInstruction;
-                }
-                // System Instruction
-                else if instruction.opcode == 0b1110011
-                {
-                    // Is 'interrupt'
-                    if instruction.funct3 == 0b000
+                            // Move value from OUT2 to PC
+                            self.output2.enable_on_bus(&self.data);
+                            self.program_counter.set_from_bus(&self.data);
+                        }
+
+                        next_mode = if self.stall_cycles > 0 {ChipMode::MemoryStall} else {ChipMode::LoadInstruction};
+                    },
+                    // Atomic
+                    Operation::LrW | Operation::ScW | Operation::AmoswapW | Operation::AmoaddW | Operation::AmoxorW |
+                    Operation::AmoandW | Operation::AmoorW | Operation::AmominW | Operation::AmomaxW |
+                    Operation::AmominuW | Operation::AmomaxuW =>
                     {
-                        // Make both act like 'ecall'
-                        self.ram_addr_bus.borrow_mut().enable_value(CsrAddresses::Mtvec as u32);
-                        self.csr_read();
+                        // The address is rs1 without an offset
+                        self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
+                        self.registers[0].enable_on_bus(&self.src1_bus);
+                        self.alu.mode = 0;
+                        self.alu.sub_flag = false;
 
+                        self.alu.tick();
+
+                        // Misaligned atomics always trap, as loads for lr.w and as stores otherwise
+                        let (cause, access) = if instruction.operation == Operation::LrW
+                        {
+                            (TrapCause::LoadAddressMisaligned, AccessType::Load)
+                        }
+                        else
+                        {
+                            (TrapCause::StoreAddressMisaligned, AccessType::Store)
+                        };
+
+                        let addr = self.alu_out_bus.borrow().read_value();
+
+                        self.memory_mode = 0b010;
+
+                        next_mode = match instruction.operation
+                        {
+                            _ if !self.check_data_alignment(addr, cause, true) || !self.translate_alu_output(access) => ChipMode::LoadInstruction,
+                            Operation::LrW => ChipMode::LoadReserved,
+                            Operation::ScW => ChipMode::StoreConditional,
+                            _ => ChipMode::AtomicLoad
+                        };
+                    },
+                    // Memory ordering
+                    Operation::Fence | Operation::FenceTso | Operation::FenceI =>
+                    {
+                        // Every access completes within its instruction, and instructions are fetched and decoded from
+                        // memory each time, so there is nothing to wait on or flush
+                        self.output2.enable_on_bus(&self.data);
                         self.program_counter.set_from_bus(&self.data);
 
                         next_mode = ChipMode::LoadInstruction;
-                    }
-                }
-                // JAL
-                else if instruction.opcode == 0b1101111
-                {
-                    // Add to the program counter
-                    self.program_counter.enable_on_bus(&self.src0_bus);
-                    self.immediate.enable_on_bus(&self.src1_bus);
-                    self.alu.mode = 0;
-                    self.alu.sub_flag = false;
-                    self.alu.tick();
+                    },
+                    // System Instruction
+                    Operation::Ecall =>
+                    {
+                        self.enter_trap(TrapCause::environment_call(self.privilege), 0);
 
-                    next_mode = ChipMode::ExecuteJump;
-                }
-                // JALR
-                else if instruction.opcode == 0b1100111
-                {
-                    // Add to the program counter
-                    self.immediate.enable_on_bus(&self.src0_bus);
-                    self.registers[instruction.rs1 as usize].enable_on_bus(&self.src1_bus);
-                    self.alu.mode = 0;
-                    self.alu.sub_flag = false;
-                    self.alu.tick();
+                        next_mode = ChipMode::LoadInstruction;
+                    },
+                    Operation::Ebreak =>
+                    {
+                        // The trap value of a breakpoint is its address
+                        self.enter_trap(TrapCause::Breakpoint, self.program_counter.value);
 
-                    next_mode = ChipMode::ExecuteJump;
-                }
-                // Branch
-                else if instruction.opcode == 0b1100011 
-                {
-                    self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
-                    self.registers[instruction.rs2 as usize].enable_on_bus(&self.src1_bus);
-                    
-                    // Eq
-                    if instruction.funct3 & 0b110 == 0b000
+                        next_mode = ChipMode::LoadInstruction;
+                    },
+                    Operation::Sret | Operation::Mret =>
                     {
-                        self.alu.mode = 0;
-                        self.alu.sub_flag = false;
-                    }
-                    // Lt
-                    else if instruction.funct3 & 0b110 == 0b100
+                        let level = if instruction.operation == Operation::Mret {Privilege::Machine} else {Privilege::Supervisor};
+
+                        // Returning from a trap needs at least the privilege of the handler
+                        if self.privilege < level
+                        {
+                            self.enter_trap(TrapCause::IllegalInstruction, self.inst.get_value());
+                        }
+                        else
+                        {
+                            self.return_from_trap(level);
+                        }
+
+                        next_mode = ChipMode::LoadInstruction;
+                    },
+                    Operation::Wfi =>
                     {
-                        self.alu.mode = 0b010;
-                        self.alu.sub_flag = false;
-                    }
-                    // Ltu
-                    else if instruction.funct3 & 0b110 == 0b110
+                        // The wait has no time limit, so it is illegal in user mode, and in supervisor mode with mstatus.TW set
+                        let timeout = self.csr_handle.read_csr(CsrAddresses::Mstatus as u32) & MSTATUS_TW != 0;
+
+                        if self.privilege == Privilege::User || (self.privilege == Privilege::Supervisor && timeout)
+                        {
+                            self.enter_trap(TrapCause::IllegalInstruction, self.inst.get_value());
+
+                            next_mode = ChipMode::LoadInstruction;
+                        }
+                        else
+                        {
+                            // The program counter moves on to the next instruction, which an interrupt returns to
+                            next_mode = ChipMode::WaitForInterrupt;
+                        }
+                    },
+                    Operation::SfenceVma =>
+                    {
+                        // Every access walks the page tables, so there are no cached translations to flush
+                        if self.privilege < Privilege::Supervisor
+                        {
+                            self.enter_trap(TrapCause::IllegalInstruction, self.inst.get_value());
+                        }
+                        else
+                        {
+                            self.output2.enable_on_bus(&self.data);
+                            self.program_counter.set_from_bus(&self.data);
+                        }
+
+                        next_mode = ChipMode::LoadInstruction;
+                    },
+                    // Zicsr, accesses to undefined CSRs, CSRs needing a higher privilege and writes to read only CSRs are illegal
+                    Operation::Csrrw | Operation::Csrrs | Operation::Csrrc |
+                    Operation::Csrrwi | Operation::Csrrsi | Operation::Csrrci
+                        if !self.csr_handle.check_access(instruction.immediate & 0xFFF, self.privilege, Self::csr_writes(&instruction)) =>
+                    {
+                        self.enter_trap(TrapCause::IllegalInstruction, self.inst.get_value());
+
+                        next_mode = ChipMode::LoadInstruction;
+                    },
+                    Operation::Csrrw | Operation::Csrrs | Operation::Csrrc |
+                    Operation::Csrrwi | Operation::Csrrsi | Operation::Csrrci =>
+                    {
+                        let write_only = matches!(instruction.operation, Operation::Csrrw | Operation::Csrrwi);
+
+                        // CSRRW does not read the CSR when the old value would be discarded
+                        if write_only && instruction.rd == 0
+                        {
+                            self.loaded.value = 0;
+                        }
+                        else
+                        {
+                            self.ram_addr_bus.borrow_mut().enable_value(instruction.immediate & 0xFFF);
+                            self.csr_read();
+                            self.loaded.set_from_bus(&self.data);
+                        }
+
+                        // Compute the new value from the old value and either rs1 or the zero extended immediate in the rs1 field
+                        if write_only
+                        {
+                            self.registers[0].enable_on_bus(&self.src0_bus);
+                        }
+                        else
+                        {
+                            self.loaded.enable_on_bus(&self.src0_bus);
+                        }
+
+                        if matches!(instruction.operation, Operation::Csrrw | Operation::Csrrs | Operation::Csrrc)
+                        {
+                            self.registers[instruction.rs1 as usize].enable_on_bus(&self.src1_bus);
+                        }
+                        else
+                        {
+                            self.src1_bus.borrow_mut().enable_value(instruction.rs1 as u32);
+                        }
+
+                        self.set_alu_operation(instruction.operation);
+                        self.alu.tick();
+
+                        next_mode = ChipMode::WriteCsr;
+                    },
+                    Operation::Jal =>
                     {
-                        self.alu.mode = 0b011;
+                        // Add to the program counter
+                        self.program_counter.enable_on_bus(&self.src0_bus);
+                        self.immediate.enable_on_bus(&self.src1_bus);
+                        self.alu.mode = 0;
                         self.alu.sub_flag = false;
-                    }
+                        self.alu.tick();
 
-                    self.alu.tick();
+                        next_mode = ChipMode::ExecuteJump;
+                    },
+                    Operation::Jalr =>
+                    {
+                        // Add to the program counter
+                        self.immediate.enable_on_bus(&self.src0_bus);
+                        self.registers[instruction.rs1 as usize].enable_on_bus(&self.src1_bus);
+                        self.alu.mode = 0;
+                        self.alu.sub_flag = false;
+                        self.alu.tick();
 
-                    self.output.set_from_bus(&self.data);
+                        next_mode = ChipMode::ExecuteJump;
+                    },
+                    // Branch
+                    Operation::Beq | Operation::Bne | Operation::Blt | Operation::Bge | Operation::Bltu | Operation::Bgeu =>
+                    {
+                        self.registers[instruction.rs1 as usize].enable_on_bus(&self.src0_bus);
+                        self.registers[instruction.rs2 as usize].enable_on_bus(&self.src1_bus);
+
+                        match instruction.operation
+                        {
+                            // Eq (the difference is zero)
+                            Operation::Beq | Operation::Bne =>
+                            {
+                                self.alu.mode = 0;
+                                self.alu.sub_flag = true;
+                            },
+                            // Lt
+                            Operation::Blt | Operation::Bge =>
+                            {
+                                self.alu.mode = 0b010;
+                                self.alu.sub_flag = false;
+                            },
+                            // Ltu
+                            _ =>
+                            {
+                                self.alu.mode = 0b011;
+                                self.alu.sub_flag = false;
+                            }
+                        }
+
+                        self.alu.tick();
+
+                        next_mode = ChipMode::BranchCheck;
+                    },
+                    Operation::Illegal =>
+                    {
+                        // The trap value is the instruction (or the 16 bit parcel of a compressed instruction)
+                        self.enter_trap(TrapCause::IllegalInstruction, self.inst.get_value());
 
-                    next_mode = ChipMode::BranchCheck;
+                        next_mode = ChipMode::LoadInstruction;
+                    }
                 }
-                
+
                 if next_mode != ChipMode::BranchCheck && next_mode != ChipMode::ExecuteJump && next_mode != ChipMode::LoadInstruction
                 {
                     // If the instruction is not a jump, then move the next address into the program counter
@@ -403,28 +1198,22 @@ impl ChipCPU
             },
             ChipMode::LoadData =>
             {
-                // Decode the instruction (this would be done with discrete logic, this is mostly to generate the proper immediates)
-                let instruction = super::Instruction::new(self.inst.get_value());
-
                 self.output.enable_on_bus(&self.ram_addr_bus);
-                self.memory_mode = instruction.funct3;
+                self.memory_mode = self.decoded.operation.memory_mode().unwrap_or(0b010);
                 self.memory_read();
-                self.registers[instruction.rd as usize].set_from_bus(&self.data);
+                self.registers[self.decoded.rd as usize].set_from_bus(&self.data);
 
                 if self.debug_display
                 {
                     println!("{:?}", self);
                 }
 
-                self.mode = ChipMode::LoadInstruction;  
+                self.mode = if self.stall_cycles > 0 {ChipMode::MemoryStall} else {ChipMode::LoadInstruction};
             },
             ChipMode::StoreResult =>
             {
-                // Decode the instruction (this would be done with discrete logic, this is mostly to generate the proper immediates)
-                let instruction = super::Instruction::new(self.inst.get_value());
-
                 self.output.enable_on_bus(&self.data);
-                self.registers[instruction.rd as usize].set_from_bus(&self.data);
+                self.registers[self.decoded.rd as usize].set_from_bus(&self.data);
 
                 if self.debug_display
                 {
@@ -435,14 +1224,18 @@ impl ChipCPU
             },
             ChipMode::ExecuteJump =>
             {
-                // Decode the instruction (this would be done with discrete logic, this is mostly to generate the proper immediates)
-                let instruction = super::Instruction::new(self.inst.get_value());
+                // The low bit of a jalr target is cleared. Jump and branch offsets are even too, so with compressed
+                // instructions (IALIGN=16) a target is always aligned and the misaligned exception cannot be raised
+                if self.decoded.operation == Operation::Jalr
+                {
+                    self.output.value &= !1;
+                }
 
                 self.output.enable_on_bus(&self.ram_addr_bus);
                 self.program_counter.set_from_bus(&self.ram_addr_bus);
 
                 self.output2.enable_on_bus(&self.data);
-                self.registers[instruction.rd as usize].set_from_bus(&self.data);
+                self.registers[self.decoded.rd as usize].set_from_bus(&self.data);
 
                 if self.debug_display
                 {
@@ -455,22 +1248,15 @@ impl ChipCPU
             {
                 let mut next_mode = ChipMode::ExecuteBranch;
 
-                // Decode the instruction (this would be done with discrete logic, this is mostly to generate the proper immediates)
-                let instruction = super::Instruction::new(self.inst.get_value());
-
-                let mut branch_cond =
-                match instruction.funct3 & 0b110
+                let branch_cond =
+                match self.decoded.operation
                 {
-                    0b000 => self.output.value == 0,
-                    0b100 | 0b110 => self.output.value == 1,
-                    default => panic!("Unknown Funct3 for branch instruction {:03b}", default)
+                    Operation::Beq => self.output.value == 0,
+                    Operation::Bne => self.output.value != 0,
+                    Operation::Blt | Operation::Bltu => self.output.value == 1,
+                    _ => self.output.value != 1
                 };
 
-                if instruction.funct3 & 0b001 > 0
-                {
-                    branch_cond = !branch_cond;
-                }
-
                 if branch_cond
                 {
                     // Add to the program counter
@@ -497,6 +1283,144 @@ impl ChipCPU
 
                 self.mode = next_mode;
             },
+            ChipMode::LoadReserved =>
+            {
+                self.output.enable_on_bus(&self.ram_addr_bus);
+                self.memory_mode = 0b010;
+                self.memory_read();
+                self.registers[self.decoded.rd as usize].set_from_bus(&self.data);
+
+                self.reservation = Some(self.output.value & !0b11);
+
+                if self.debug_display
+                {
+                    println!("{:?}", self);
+                }
+
+                self.mode = ChipMode::LoadInstruction;
+            },
+            ChipMode::StoreConditional =>
+            {
+                let success = self.reservation == Some(self.output.value & !0b11);
+
+                if success
+                {
+                    self.output.enable_on_bus(&self.ram_addr_bus);
+                    self.registers[self.decoded.rs2 as usize].enable_on_bus(&self.data);
+                    self.memory_mode = 0b010;
+                    self.memory_write();
+                }
+
+                // rd is 0 on success and 1 on failure, the reservation is released either way
+                self.data.borrow_mut().enable_value(if success {0} else {1});
+                self.registers[self.decoded.rd as usize].set_from_bus(&self.data);
+
+                self.reservation = None;
+
+                if self.debug_display
+                {
+                    println!("{:?}", self);
+                }
+
+                self.mode = ChipMode::LoadInstruction;
+            },
+            ChipMode::AtomicLoad =>
+            {
+                self.output.enable_on_bus(&self.ram_addr_bus);
+                self.memory_mode = 0b010;
+                self.memory_read();
+                self.loaded.set_from_bus(&self.data);
+
+                // Swap stores rs2 unchanged, so the loaded value is replaced by zero
+                if self.decoded.operation == Operation::AmoswapW
+                {
+                    self.registers[0].enable_on_bus(&self.src0_bus);
+                }
+                else
+                {
+                    self.loaded.enable_on_bus(&self.src0_bus);
+                }
+
+                self.registers[self.decoded.rs2 as usize].enable_on_bus(&self.src1_bus);
+                self.set_alu_operation(self.decoded.operation);
+                self.alu.tick();
+
+                self.output2.set_from_bus(&self.alu_out_bus);
+
+                if self.debug_display
+                {
+                    println!("{:?}", self);
+                }
+
+                self.mode = ChipMode::AtomicStore;
+            },
+            ChipMode::AtomicStore =>
+            {
+                self.output.enable_on_bus(&self.ram_addr_bus);
+                self.output2.enable_on_bus(&self.data);
+                self.memory_mode = 0b010;
+                self.memory_write();
+
+                self.loaded.enable_on_bus(&self.data);
+                self.registers[self.decoded.rd as usize].set_from_bus(&self.data);
+
+                if self.debug_display
+                {
+                    println!("{:?}", self);
+                }
+
+                self.mode = ChipMode::LoadInstruction;
+            },
+            ChipMode::WriteCsr =>
+            {
+                if Self::csr_writes(&self.decoded)
+                {
+                    self.ram_addr_bus.borrow_mut().enable_value(self.decoded.immediate & 0xFFF);
+                    self.output.enable_on_bus(&self.data);
+                    self.csr_write();
+                }
+
+                // The old value of the CSR goes to rd
+                self.loaded.enable_on_bus(&self.data);
+                self.registers[self.decoded.rd as usize].set_from_bus(&self.data);
+
+                if self.debug_display
+                {
+                    println!("{:?}", self);
+                }
+
+                self.mode = ChipMode::LoadInstruction;
+            },
+            ChipMode::MemoryStall =>
+            {
+                // Each extra bus cycle of a split access takes a clock
+                self.stall_cycles -= 1;
+
+                if self.debug_display
+                {
+                    println!("{:?}", self);
+                }
+
+                if self.stall_cycles == 0
+                {
+                    self.mode = ChipMode::LoadInstruction;
+                }
+            },
+            ChipMode::WaitForInterrupt =>
+            {
+                // The hart is idle until an interrupt is pending, which is taken before the next fetch if it is enabled
+                self.idle_clocks += 1;
+
+                if self.debug_display
+                {
+                    println!("{:?}", self);
+                }
+
+                if self.interrupt_waiting()
+                {
+                    self.mode = ChipMode::LoadInstruction;
+                }
+            },
             ChipMode::ExecuteBranch =>
             {
                 self.output.enable_on_bus(&self.data);
@@ -511,16 +1435,84 @@ impl ChipCPU
             },
         }
 
+        // An instruction retires in the clock its last step returns to fetching, unless it raised an exception
+        if mode != ChipMode::LoadInstruction && self.mode == ChipMode::LoadInstruction && self.events & CounterEvent::Exception.bit() == 0
+        {
+            self.events |= self.retired_events(mode);
+            self.retired_instructions += 1;
+            self.traps_without_retiring = 0;
+        }
+
+        self.csr_handle.count_events(self.events | CounterEvent::Cycle.bit(), mode as u32, 1);
+        self.events = 0;
+
         self.clock += 1;
     }
 
-    /// Write data into a region of memory
+    /// Write data into a region of memory, as a programmer would before power on (so read only memory is filled too)
     pub fn write_to_memory(&mut self, addr: u32, data: Vec<u8>)
     {
         for (i, val) in data.iter().enumerate()
         {
-            self.memory.write_byte(addr.wrapping_add(i as u32), *val);
+            self.memory.program_byte(addr.wrapping_add(i as u32), *val);
+        }
+    }
+
+    /// Disassemble a number of instructions from memory starting at an address
+    pub fn disassemble_range(&self, start: u32, count: u32) -> String
+    {
+        disassemble_memory(self.memory.as_ref(), start, count)
+    }
+
+    /// Load an RV32 ELF executable into memory and set the program counter to its entry point
+    pub fn load_elf(&mut self, data: &[u8]) -> Result<(), LoaderError>
+    {
+        let entry = super::load_elf(&mut MemoryProgrammer::new(self.memory.as_mut()), data)?;
+
+        self.program_counter.value = entry;
+
+        Ok(())
+    }
+
+    /// Assemble RV32IMA source text into memory at the base address and set the program counter to it
+    pub fn load_assembly(&mut self, base: u32, source: &str) -> Result<(), AssemblerError>
+    {
+        for (address, data) in super::assemble(source, base)?
+        {
+            self.write_to_memory(address, data);
         }
+
+        self.program_counter.value = base;
+
+        Ok(())
+    }
+
+    /// Load a raw binary image into memory at the base address
+    pub fn load_binary(&mut self, base: u32, data: &[u8]) -> Result<(), LoaderError>
+    {
+        super::load_binary(&mut MemoryProgrammer::new(self.memory.as_mut()), base, data)
+    }
+
+    /// Load an Intel HEX image into memory offset by the base address, setting the program counter if the image has a start address
+    pub fn load_ihex(&mut self, base: u32, text: &str) -> Result<(), LoaderError>
+    {
+        if let Some(start) = super::load_ihex(&mut MemoryProgrammer::new(self.memory.as_mut()), base, text)?
+        {
+            self.program_counter.value = start;
+        }
+
+        Ok(())
+    }
+
+    /// Load a Motorola S-record image into memory offset by the base address, setting the program counter if the image has a start address
+    pub fn load_srec(&mut self, base: u32, text: &str) -> Result<(), LoaderError>
+    {
+        if let Some(start) = super::load_srec(&mut MemoryProgrammer::new(self.memory.as_mut()), base, text)?
+        {
+            self.program_counter.value = start;
+        }
+
+        Ok(())
     }
 }
 
@@ -535,7 +1527,8 @@ impl fmt::Debug for ChipCPU
         writeln!(f, " Registers:")?;
 
         //    PC: 0x00000000   OUT: 0x00000000
-        writeln!(f, "   PC: 0x{:08X}   OUT: 0x{:08X}   OUT2: 0x{:08X}", self.program_counter.get_value(), self.output.get_value(), self.output2.get_value())?;
+        writeln!(f, "   PC: 0x{:08X}   OUT: 0x{:08X}   OUT2: 0x{:08X}   LOADED: 0x{:08X}", self.program_counter.get_value(), self.output.get_value(),
+                                                                                    self.output2.get_value(), self.loaded.get_value())?;
 
         //    R00: 0x00000000   R01: 0x00000000   R02: 0x00000000   R03: 0x00000000
         // ...
@@ -551,8 +1544,8 @@ impl fmt::Debug for ChipCPU
             writeln!(f, " ")?
         }
 
-        //    INST: 0x00000000   0b00000000000000000000000000000000
-        writeln!(f, "   INST: 0x{0:08X}   0b{0:032b}", self.inst.get_value())?;
+        //    INST: 0x00000000   0b00000000000000000000000000000000   addi zero, zero, 0
+        writeln!(f, "   INST: 0x{0:08X}   0b{0:032b}   {1}", self.inst.get_value(), disassemble(self.inst.get_value()))?;
 
         //  Buses:
         writeln!(f, " Buses:")?;
@@ -570,11 +1563,24 @@ impl fmt::Debug for ChipCPU
         //    Clk: 0000000000
         writeln!(f, "   Clk: {}", self.clock)?;
 
-        //    Mode: LoadInstruction
-        writeln!(f, "   Mode: {:?}", self.mode)?;
+        //    Mtime: 0   Mtimecmp: 0
+        writeln!(f, "   Mtime: {}   Mtimecmp: {}", self.clint.borrow().get_mtime(), self.clint.borrow().get_mtimecmp())?;
+
+        //    Plic Pending: 00000000   Claimed: 00000000
+        writeln!(f, "   Plic Pending: {:08X}   Claimed: {:08X}", self.plic.borrow().get_pending(), self.plic.borrow().get_claimed())?;
+
+        //    Mode: LoadInstruction   Privilege: Machine
+        writeln!(f, "   Mode: {:?}   Privilege: {:?}", self.mode, self.privilege)?;
+
+        //    Reservation: 0x00000000
+        match self.reservation
+        {
+            Some(addr) => writeln!(f, "   Reservation: 0x{:08X}", addr)?,
+            None => writeln!(f, "   Reservation: None")?
+        }
 
         //    Alu Mode: 0b000 (T)
-        writeln!(f, "   Alu Mode: 0b{:03b}-{:01b} ({})", self.alu.mode, if self.alu.sub_flag {1} else {0},
+        writeln!(f, "   Alu Mode: 0b{:04b}-{:01b} ({})", self.alu.mode, if self.alu.sub_flag {1} else {0},
             match self.alu.mode
             {
                 0b000 => if self.alu.sub_flag {"-"} else {"+"},
@@ -584,7 +1590,19 @@ impl fmt::Debug for ChipCPU
                 0b100 => "^",
                 0b101 => if self.alu.sub_flag {">>u"} else {">>"},
                 0b110 => "|",
-                0b111 => "&",
+                0b111 => if self.alu.sub_flag {"&~"} else {"&"},
+                0b1000 => "*",
+                0b1001 => "*h",
+                0b1010 => "*hsu",
+                0b1011 => "*hu",
+                0b1100 => "/",
+                0b1101 => "/u",
+                0b1110 => "%",
+                0b1111 => "%u",
+                0b10000 => "min",
+                0b10001 => "max",
+                0b10010 => "minu",
+                0b10011 => "maxu",
                 _ => "?"
             })?;
 
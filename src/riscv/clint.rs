@@ -0,0 +1,173 @@
+use super::{Device, AccessWidth};
+
+/// Base address of the CLINT in the memory map
+pub const CLINT_BASE: u32 = 0x0200_0000;
+
+/// Size of the CLINT address range
+pub const CLINT_SIZE: u32 = 0x0001_0000;
+
+/// Offset of the msip register
+const MSIP_OFFSET: u32 = 0x0000;
+
+/// Offset of the 64 bit mtimecmp register
+const MTIMECMP_OFFSET: u32 = 0x4000;
+
+/// Offset of the 64 bit mtime register
+const MTIME_OFFSET: u32 = 0xBFF8;
+
+/// Core Local Interruptor (machine timer and software interrupts for a single hart)
+pub struct Clint
+{
+    msip: bool,
+    mtimecmp: u64,
+    mtime: u64,
+
+    cycles_per_tick: u32,
+    divider: u32
+}
+
+impl Clint
+{
+    /// Generate a new Clint, with mtime counting once every cycles_per_tick clock cycles
+    pub fn new(cycles_per_tick: u32) -> Self
+    {
+        Self
+        {
+            msip: false,
+            mtimecmp: u64::MAX,
+            mtime: 0,
+
+            cycles_per_tick: cycles_per_tick.max(1),
+            divider: 0
+        }
+    }
+
+    /// Set the number of clock cycles per mtime tick
+    pub fn set_tick_ratio(&mut self, cycles_per_tick: u32)
+    {
+        self.cycles_per_tick = cycles_per_tick.max(1);
+        self.divider = 0;
+    }
+
+    /// Return the registers to their power on values, keeping the tick ratio
+    pub fn reset(&mut self)
+    {
+        self.msip = false;
+        self.mtimecmp = u64::MAX;
+        self.mtime = 0;
+        self.divider = 0;
+    }
+
+    /// Advance by one clock cycle
+    pub fn tick(&mut self)
+    {
+        self.divider += 1;
+
+        if self.divider >= self.cycles_per_tick
+        {
+            self.divider = 0;
+            self.mtime = self.mtime.wrapping_add(1);
+        }
+    }
+
+    /// Advance by a number of clock cycles at once
+    pub fn advance(&mut self, clocks: u64)
+    {
+        let total = self.divider as u64 + clocks;
+        let ratio = self.cycles_per_tick as u64;
+
+        self.mtime = self.mtime.wrapping_add(total / ratio);
+        self.divider = (total % ratio) as u32;
+    }
+
+    /// Get the number of clock cycles until the timer interrupt becomes pending (None if it is too far off to count)
+    pub fn clocks_until_timer(&self) -> Option<u64>
+    {
+        if self.timer_interrupt()
+        {
+            return Some(0);
+        }
+
+        // The first tick of mtime comes once the divider finishes its count, then one every cycles_per_tick
+        let ratio = self.cycles_per_tick as u64;
+
+        (self.mtimecmp - self.mtime - 1).checked_mul(ratio).and_then(|clocks| clocks.checked_add(ratio - self.divider as u64))
+    }
+
+    /// Get the current value of mtime
+    pub fn get_mtime(&self) -> u64
+    {
+        self.mtime
+    }
+
+    /// Get the current value of mtimecmp
+    pub fn get_mtimecmp(&self) -> u64
+    {
+        self.mtimecmp
+    }
+
+    /// Check if the timer interrupt is pending (mtime has reached mtimecmp)
+    pub fn timer_interrupt(&self) -> bool
+    {
+        self.mtime >= self.mtimecmp
+    }
+
+    /// Check if the software interrupt is pending
+    pub fn software_interrupt(&self) -> bool
+    {
+        self.msip
+    }
+}
+
+impl Clint
+{
+    /// Read one byte of the registers
+    fn read_register_byte(&self, offset: u32) -> u8
+    {
+        match offset
+        {
+            MSIP_OFFSET => self.msip as u8,
+            MTIMECMP_OFFSET..=0x4007 => (self.mtimecmp >> (8 * (offset - MTIMECMP_OFFSET))) as u8,
+            MTIME_OFFSET..=0xBFFF => (self.mtime >> (8 * (offset - MTIME_OFFSET))) as u8,
+            _ => 0
+        }
+    }
+
+    /// Write one byte of the registers
+    fn write_register_byte(&mut self, offset: u32, data: u8)
+    {
+        // Replace one byte of a 64 bit register
+        let replace = |value: u64, shift: u32| (value & !(0xFF << shift)) | ((data as u64) << shift);
+
+        match offset
+        {
+            // Only the low bit of msip is implemented
+            MSIP_OFFSET => self.msip = data & 1 != 0,
+            MTIMECMP_OFFSET..=0x4007 => self.mtimecmp = replace(self.mtimecmp, 8 * (offset - MTIMECMP_OFFSET)),
+            MTIME_OFFSET..=0xBFFF => self.mtime = replace(self.mtime, 8 * (offset - MTIME_OFFSET)),
+            _ => {}
+        }
+    }
+}
+
+// The hart clocks and resets the CLINT itself, as mtime also drives the time CSR, so as a device it only decodes the registers
+impl Device for Clint
+{
+    fn read(&mut self, offset: u32, width: AccessWidth) -> u32
+    {
+        self.peek(offset, width)
+    }
+
+    fn peek(&self, offset: u32, width: AccessWidth) -> u32
+    {
+        (0..width.bytes()).fold(0, |value, i| value | ((self.read_register_byte(offset.wrapping_add(i)) as u32) << (8 * i)))
+    }
+
+    fn write(&mut self, offset: u32, width: AccessWidth, data: u32)
+    {
+        for i in 0..width.bytes()
+        {
+            self.write_register_byte(offset.wrapping_add(i), (data >> (8 * i)) as u8);
+        }
+    }
+}
\ No newline at end of file
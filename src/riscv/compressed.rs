@@ -0,0 +1,178 @@
+use super::{extract_bit_range, sign_extend};
+use super::encoder;
+
+/// Check if the low 16 bit parcel of an instruction is a compressed instruction (the low two bits are not 0b11)
+pub fn is_compressed(parcel: u16) -> bool
+{
+    parcel & 0b11 != 0b11
+}
+
+/// Gather scattered bits of a parcel into an immediate, each entry being (parcel bit, immediate bit)
+fn gather_bits(parcel: u32, layout: &[(usize, usize)]) -> u32
+{
+    layout.iter().fold(0, |imm, (from, to)| imm | (extract_bit_range(parcel, *from, 1) << to))
+}
+
+/// Expand a compressed instruction into its 32 bit equivalent, giving None for reserved and unsupported encodings
+pub fn expand_compressed(parcel: u16) -> Option<u32>
+{
+    let parcel = parcel as u32;
+
+    let funct3 = extract_bit_range(parcel, 13, 3);
+
+    // Full register fields (bits 11:7 and 6:2) and the x8-x15 register fields (bits 9:7 and 4:2)
+    let rd = extract_bit_range(parcel, 7, 5) as u8;
+    let rs2 = extract_bit_range(parcel, 2, 5) as u8;
+    let rd_short = extract_bit_range(parcel, 7, 3) as u8 + 8;
+    let rs2_short = extract_bit_range(parcel, 2, 3) as u8 + 8;
+
+    // Six bit immediate split between bit 12 and bits 6:2
+    let imm6 = sign_extend((extract_bit_range(parcel, 12, 1) << 5) | extract_bit_range(parcel, 2, 5), 5) as i32;
+    let shamt = extract_bit_range(parcel, 2, 5) as u8;
+    let shamt_high = extract_bit_range(parcel, 12, 1) != 0;
+
+    // Word offsets of c.lw and c.sw
+    let word_offset = gather_bits(parcel, &[(5, 6), (6, 2), (10, 3), (11, 4), (12, 5)]) as i32;
+
+    let jump_offset = sign_extend(gather_bits(parcel, &[(2, 5), (3, 1), (4, 2), (5, 3), (6, 7), (7, 6), (8, 10), (9, 8), (10, 9), (11, 4), (12, 11)]), 11) as i32;
+    let branch_offset = sign_extend(gather_bits(parcel, &[(2, 5), (3, 1), (4, 2), (5, 6), (6, 7), (10, 3), (11, 4), (12, 8)]), 8) as i32;
+
+    let expanded = match (extract_bit_range(parcel, 0, 2), funct3)
+    {
+        // c.addi4spn (a zero immediate is reserved, which includes the all zero illegal instruction)
+        (0b00, 0b000) =>
+        {
+            let imm = gather_bits(parcel, &[(5, 3), (6, 2), (7, 6), (8, 7), (9, 8), (10, 9), (11, 4), (12, 5)]);
+
+            if imm == 0
+            {
+                return None;
+            }
+
+            encoder::addi(rs2_short, 2, imm as i32)
+        },
+        // c.lw
+        (0b00, 0b010) => encoder::lw(rs2_short, rd_short, word_offset),
+        // c.sw
+        (0b00, 0b110) => encoder::sw(rs2_short, rd_short, word_offset),
+        // c.addi and c.nop
+        (0b01, 0b000) => encoder::addi(rd, rd, imm6),
+        // c.jal
+        (0b01, 0b001) => encoder::jal(1, jump_offset),
+        // c.li
+        (0b01, 0b010) => encoder::addi(rd, 0, imm6),
+        // c.addi16sp
+        (0b01, 0b011) if rd == 2 =>
+        {
+            let imm = sign_extend(gather_bits(parcel, &[(2, 5), (3, 7), (4, 8), (5, 6), (6, 4), (12, 9)]), 9) as i32;
+
+            if imm == 0
+            {
+                return None;
+            }
+
+            encoder::addi(2, 2, imm)
+        },
+        // c.lui
+        (0b01, 0b011) =>
+        {
+            if imm6 == 0
+            {
+                return None;
+            }
+
+            encoder::lui(rd, imm6 as u32 & 0xFFFFF)
+        },
+        // c.srli, c.srai, c.andi and the register arithmatic of the x8-x15 registers
+        (0b01, 0b100) => match extract_bit_range(parcel, 10, 2)
+        {
+            0b00 if !shamt_high => encoder::srli(rd_short, rd_short, shamt),
+            0b01 if !shamt_high => encoder::srai(rd_short, rd_short, shamt),
+            0b10 => encoder::andi(rd_short, rd_short, imm6),
+            0b11 if !shamt_high => match extract_bit_range(parcel, 5, 2)
+            {
+                0b00 => encoder::sub(rd_short, rd_short, rs2_short),
+                0b01 => encoder::xor(rd_short, rd_short, rs2_short),
+                0b10 => encoder::or(rd_short, rd_short, rs2_short),
+                _ => encoder::and(rd_short, rd_short, rs2_short)
+            },
+            _ => return None
+        },
+        // c.j
+        (0b01, 0b101) => encoder::jal(0, jump_offset),
+        // c.beqz
+        (0b01, 0b110) => encoder::beq(rd_short, 0, branch_offset),
+        // c.bnez
+        (0b01, 0b111) => encoder::bne(rd_short, 0, branch_offset),
+        // c.slli
+        (0b10, 0b000) if !shamt_high => encoder::slli(rd, rd, shamt),
+        // c.lwsp (loading into x0 is reserved)
+        (0b10, 0b010) if rd != 0 => encoder::lw(rd, 2, gather_bits(parcel, &[(2, 6), (3, 7), (4, 2), (5, 3), (6, 4), (12, 5)]) as i32),
+        // c.jr, c.mv, c.ebreak, c.jalr and c.add
+        (0b10, 0b100) => match (shamt_high, rd, rs2)
+        {
+            (false, 0, 0) => return None,
+            (false, _, 0) => encoder::jalr(0, rd, 0),
+            (false, _, _) => encoder::add(rd, 0, rs2),
+            (true, 0, 0) => encoder::ebreak(),
+            (true, _, 0) => encoder::jalr(1, rd, 0),
+            (true, _, _) => encoder::add(rd, rd, rs2)
+        },
+        // c.swsp
+        (0b10, 0b110) => encoder::sw(rs2, 2, gather_bits(parcel, &[(7, 6), (8, 7), (9, 2), (10, 3), (11, 4), (12, 5)]) as i32),
+        // Floating point loads and stores, and reserved encodings
+        _ => return None
+    };
+
+    expanded.ok()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn parcels_are_told_apart_by_their_low_bits()
+    {
+        assert!(is_compressed(0x0001));
+        assert!(is_compressed(0x8082));
+        assert!(!is_compressed(0x0003));
+        assert!(!is_compressed(0x0513));
+    }
+
+    #[test]
+    fn expands_to_the_equivalent_instruction()
+    {
+        let cases =
+        [
+            (0x0001, 0x00000013), // c.nop: addi zero, zero, 0
+            (0x0505, 0x00150513), // c.addi a0, 1
+            (0x557D, 0xFFF00513), // c.li a0, -1
+            (0x41C8, 0x0045A503), // c.lw a0, 4(a1)
+            (0xC588, 0x00A5A423), // c.sw a0, 8(a1)
+            (0x4512, 0x00412503), // c.lwsp a0, 4(sp)
+            (0xA001, 0x0000006F), // c.j 0
+            (0xC101, 0x00050063), // c.beqz a0, 0
+            (0x852E, 0x00B00533), // c.mv a0, a1: add a0, zero, a1
+            (0x952E, 0x00B50533), // c.add a0, a1
+            (0x8082, 0x00008067), // c.jr ra: jalr zero, 0(ra)
+            (0x9002, 0x00100073)  // c.ebreak
+        ];
+
+        for (parcel, word) in cases
+        {
+            assert_eq!(expand_compressed(parcel), Some(word), "0x{:04X}", parcel);
+        }
+    }
+
+    #[test]
+    fn reserved_encodings_do_not_expand()
+    {
+        // The all zero parcel, c.lwsp into zero, c.jr zero, c.addi16sp by 0 and c.fld
+        for parcel in [0x0000, 0x4002, 0x8002, 0x6101, 0x2000]
+        {
+            assert_eq!(expand_compressed(parcel), None, "0x{:04X}", parcel);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,140 @@
+/// Number of counters (cycle, time, instret and the 29 hardware performance monitor counters)
+pub const COUNTERS: usize = 32;
+
+/// Index of the time counter, which mirrors the CLINT mtime and has no machine mode counter
+pub const TIME_COUNTER: usize = 1;
+
+/// Base of the events counting the cycles spent in a step of the instruction sequence, the ChipMode number is added to it
+pub const MODE_CYCLE_EVENT: u32 = 0x100;
+
+/// Event a counter can count, the hardware performance monitor counters select one by writing its number to mhpmevent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CounterEvent
+{
+    /// A clock cycle
+    Cycle = 1,
+    /// An instruction completed without raising an exception
+    InstructionRetired = 2,
+    /// A load instruction retired
+    Load = 3,
+    /// A store instruction retired
+    Store = 4,
+    /// An atomic instruction (including lr.w and sc.w) retired
+    Atomic = 5,
+    /// A conditional branch retired having been taken
+    BranchTaken = 6,
+    /// A conditional branch retired without being taken
+    BranchNotTaken = 7,
+    /// A jal or jalr retired
+    Jump = 8,
+    /// An exception was taken
+    Exception = 9,
+    /// An interrupt was taken
+    Interrupt = 10
+}
+
+impl CounterEvent
+{
+    /// Get the bit of the event in a set of events
+    pub fn bit(&self) -> u32
+    {
+        1 << (*self as u32)
+    }
+}
+
+/// Counters of the Zicntr and Zihpm extensions (every counter is 64 bits, accessed as two 32 bit halves)
+pub struct Counters
+{
+    values: [u64; COUNTERS],
+    events: [u32; COUNTERS],
+
+    // Counters written by software in this clock, which do not also count
+    written: u32
+}
+
+impl Counters
+{
+    /// Generate a new Counters, with every counter at zero and counting nothing
+    pub fn new() -> Self
+    {
+        Self
+        {
+            values: [0; COUNTERS],
+            events: [0; COUNTERS],
+
+            written: 0
+        }
+    }
+
+    /// Read one half of a counter
+    pub fn read_counter(&self, counter: usize, high: bool) -> u32
+    {
+        if high {(self.values[counter] >> 32) as u32} else {self.values[counter] as u32}
+    }
+
+    /// Write one half of a counter, the write replaces any count made in the same clock
+    pub fn write_counter(&mut self, counter: usize, high: bool, value: u32)
+    {
+        let current = self.values[counter];
+
+        self.values[counter] = if high
+        {
+            (current & 0xFFFF_FFFF) | ((value as u64) << 32)
+        }
+        else
+        {
+            (current & !0xFFFF_FFFF) | value as u64
+        };
+
+        self.written |= 1 << counter;
+    }
+
+    /// Read the event a hardware performance monitor counter counts
+    pub fn read_event(&self, counter: usize) -> u32
+    {
+        self.events[counter]
+    }
+
+    /// Select the event a hardware performance monitor counter counts (unknown events are never counted)
+    pub fn write_event(&mut self, counter: usize, event: u32)
+    {
+        self.events[counter] = event;
+    }
+
+    /// Set the time counter from the CLINT mtime
+    pub fn set_time(&mut self, mtime: u64)
+    {
+        self.values[TIME_COUNTER] = mtime;
+    }
+
+    /// Count the events which happened in each of a number of clocks spent in the given ChipMode, except in the counters inhibited by mcountinhibit
+    pub fn count(&mut self, events: u32, mode: u32, inhibit: u32, clocks: u64)
+    {
+        for counter in 0..COUNTERS
+        {
+            if counter == TIME_COUNTER || (inhibit | self.written) & (1 << counter) != 0
+            {
+                continue;
+            }
+
+            let counted = match counter
+            {
+                0 => events & CounterEvent::Cycle.bit() != 0,
+                2 => events & CounterEvent::InstructionRetired.bit() != 0,
+                _ => match self.events[counter]
+                {
+                    0 => false,
+                    event if event >= MODE_CYCLE_EVENT => event - MODE_CYCLE_EVENT == mode,
+                    event => event < 32 && events & (1 << event) != 0
+                }
+            };
+
+            if counted
+            {
+                self.values[counter] = self.values[counter].wrapping_add(clocks);
+            }
+        }
+
+        self.written = 0;
+    }
+}
\ No newline at end of file
@@ -1,35 +1,432 @@
+use std::collections::HashMap;
+
+use super::{Counters, COUNTERS, TIME_COUNTER};
+use super::{Pmp, PMP_ENTRIES, Privilege};
+
 /// Address of a CSR (For clearer addressing)
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CsrAddresses
 {
-    Mtvec = 0x305
+    Sstatus = 0x100,
+    Sie = 0x104,
+    Stvec = 0x105,
+    Scounteren = 0x106,
+    Sscratch = 0x140,
+    Sepc = 0x141,
+    Scause = 0x142,
+    Stval = 0x143,
+    Sip = 0x144,
+    Satp = 0x180,
+    Mvendorid = 0xF11,
+    Marchid = 0xF12,
+    Mimpid = 0xF13,
+    Mhartid = 0xF14,
+    Mstatus = 0x300,
+    Misa = 0x301,
+    Medeleg = 0x302,
+    Mideleg = 0x303,
+    Mie = 0x304,
+    Mtvec = 0x305,
+    Mcounteren = 0x306,
+    Mcountinhibit = 0x320,
+    Mscratch = 0x340,
+    Mepc = 0x341,
+    Mcause = 0x342,
+    Mtval = 0x343,
+    Mip = 0x344,
+    Pmpcfg0 = 0x3A0,
+    Pmpaddr0 = 0x3B0,
+    Mcycle = 0xB00,
+    Mcycleh = 0xB80,
+    Cycle = 0xC00,
+    Cycleh = 0xC80
+}
+
+/// Supervisor interrupt enable bit of mstatus
+pub const MSTATUS_SIE: u32 = 1 << 1;
+
+/// Machine interrupt enable bit of mstatus
+pub const MSTATUS_MIE: u32 = 1 << 3;
+
+/// Supervisor previous interrupt enable bit of mstatus
+pub const MSTATUS_SPIE: u32 = 1 << 5;
+
+/// Machine previous interrupt enable bit of mstatus
+pub const MSTATUS_MPIE: u32 = 1 << 7;
+
+/// Supervisor previous privilege bit of mstatus (set for supervisor mode, clear for user mode)
+pub const MSTATUS_SPP: u32 = 1 << 8;
+
+/// Machine previous privilege field of mstatus
+pub const MSTATUS_MPP: u32 = 0b11 << 11;
+
+/// Modify privilege bit of mstatus (machine mode loads and stores use the privilege in MPP)
+pub const MSTATUS_MPRV: u32 = 1 << 17;
+
+/// Permit supervisor user memory access bit of mstatus
+pub const MSTATUS_SUM: u32 = 1 << 18;
+
+/// Make executable readable bit of mstatus
+pub const MSTATUS_MXR: u32 = 1 << 19;
+
+/// Timeout wait bit of mstatus (WFI below machine mode is illegal)
+pub const MSTATUS_TW: u32 = 1 << 21;
+
+/// Bits of mstatus visible through sstatus
+const SSTATUS_MASK: u32 = MSTATUS_SIE | MSTATUS_SPIE | MSTATUS_SPP | MSTATUS_SUM | MSTATUS_MXR;
+
+/// Bits of mstatus which are implemented (the rest are hardwired to zero)
+const MSTATUS_MASK: u32 = SSTATUS_MASK | MSTATUS_MIE | MSTATUS_MPIE | MSTATUS_MPP | MSTATUS_MPRV | MSTATUS_TW;
+
+/// Supervisor software interrupt pending bit of mip
+pub const MIP_SSIP: u32 = 1 << 1;
+
+/// Supervisor timer interrupt pending bit of mip
+pub const MIP_STIP: u32 = 1 << 5;
+
+/// Supervisor external interrupt pending bit of mip
+pub const MIP_SEIP: u32 = 1 << 9;
+
+/// Machine software interrupt pending bit of mip
+pub const MIP_MSIP: u32 = 1 << 3;
+
+/// Machine timer interrupt pending bit of mip
+pub const MIP_MTIP: u32 = 1 << 7;
+
+/// Machine external interrupt pending bit of mip
+pub const MIP_MEIP: u32 = 1 << 11;
+
+/// Supervisor interrupt pending bits of mip (and enable bits of mie)
+const SUPERVISOR_INTERRUPTS: u32 = MIP_SSIP | MIP_STIP | MIP_SEIP;
+
+/// Machine interrupt pending bits of mip, which are driven by the devices
+const MACHINE_INTERRUPTS: u32 = MIP_MSIP | MIP_MTIP | MIP_MEIP;
+
+/// Exceptions which can be delegated (all of them except an environment call from machine mode)
+const DELEGABLE_EXCEPTIONS: u32 = 0xB3FF;
+
+/// Value of misa, RV32 with the A, C, I, M, S and U extensions
+pub const MISA: u32 = (0b01 << 30) | (1 << 0) | (1 << 2) | (1 << 8) | (1 << 12) | (1 << 18) | (1 << 20);
+
+/// Implementation version reported in mimpid
+const MIMPID: u32 = 1;
+
+/// Mode bit of satp (set for Sv32 translation, clear for bare addressing)
+pub const SATP_MODE: u32 = 1 << 31;
+
+/// Root page table page number field of satp
+pub const SATP_PPN: u32 = 0x003F_FFFF;
+
+/// Where the value of a CSR is held
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CsrStorage
+{
+    /// The CSR holds its own value
+    Value(u32),
+    /// The CSR is a window onto another CSR, restricted to the interrupts delegated in mideleg if set
+    View(u32, bool),
+    /// The CSR is a pmpcfg register of the PMP, packing the configuration of four entries
+    PmpConfig(usize),
+    /// The CSR is a pmpaddr register of the PMP
+    PmpAddress(usize),
+    /// The CSR is the low (or if set, high) half of a counter
+    Counter(usize, bool),
+    /// The CSR is the mhpmevent register selecting what a counter counts
+    EventSelector(usize)
 }
 
-/// CSR handling code
+/// A CSR in the registry
+#[derive(Debug, Clone, Copy)]
+struct CsrRegister
+{
+    storage: CsrStorage,
+    /// Bits software can see
+    read_mask: u32,
+    /// Bits software can change, the others are read only (or hardwired, for WARL fields)
+    write_mask: u32
+}
+
+impl CsrRegister
+{
+    /// A CSR holding its own value
+    fn value(reset: u32, write_mask: u32) -> Self
+    {
+        Self
+        {
+            storage: CsrStorage::Value(reset),
+            read_mask: u32::MAX,
+            write_mask
+        }
+    }
+
+    /// A CSR which always reads the same value
+    fn constant(value: u32) -> Self
+    {
+        Self::value(value, 0)
+    }
+
+    /// A CSR showing some of the bits of another CSR
+    fn view(of: CsrAddresses, read_mask: u32, write_mask: u32, delegated: bool) -> Self
+    {
+        Self
+        {
+            storage: CsrStorage::View(of as u32, delegated),
+            read_mask,
+            write_mask
+        }
+    }
+
+    /// A CSR held by the PMP (which applies its own locking) or the counters
+    fn held(storage: CsrStorage) -> Self
+    {
+        Self
+        {
+            storage,
+            read_mask: u32::MAX,
+            write_mask: u32::MAX
+        }
+    }
+}
+
+/// CSR handling code (a registry of the defined CSRs)
 pub struct CsrHandler
 {
-    data: [u32; 4096],
+    registers: HashMap<u32, CsrRegister>,
+    pmp: Pmp,
+    counters: Counters,
+    mtvec_written: bool,
+    stvec_written: bool
 }
 
 impl CsrHandler
 {
-    /// Generate a new CsrHandler object
+    /// Generate a new CsrHandler object, with every CSR at its reset value
     pub fn new() -> Self
     {
+        let definitions =
+        [
+            (CsrAddresses::Sstatus, CsrRegister::view(CsrAddresses::Mstatus, SSTATUS_MASK, SSTATUS_MASK, false)),
+            (CsrAddresses::Sie, CsrRegister::view(CsrAddresses::Mie, SUPERVISOR_INTERRUPTS, SUPERVISOR_INTERRUPTS, true)),
+            (CsrAddresses::Stvec, CsrRegister::value(0, !0b10)),
+            (CsrAddresses::Scounteren, CsrRegister::value(0, u32::MAX)),
+            (CsrAddresses::Sscratch, CsrRegister::value(0, u32::MAX)),
+            (CsrAddresses::Sepc, CsrRegister::value(0, !1)),
+            (CsrAddresses::Scause, CsrRegister::value(0, u32::MAX)),
+            (CsrAddresses::Stval, CsrRegister::value(0, u32::MAX)),
+            // Only the software interrupt can be raised or cleared through sip
+            (CsrAddresses::Sip, CsrRegister::view(CsrAddresses::Mip, SUPERVISOR_INTERRUPTS, MIP_SSIP, true)),
+            // Address space identifiers are not implemented
+            (CsrAddresses::Satp, CsrRegister::value(0, SATP_MODE | SATP_PPN)),
+            (CsrAddresses::Mvendorid, CsrRegister::constant(0)),
+            (CsrAddresses::Marchid, CsrRegister::constant(0)),
+            (CsrAddresses::Mimpid, CsrRegister::constant(MIMPID)),
+            (CsrAddresses::Mhartid, CsrRegister::constant(0)),
+            (CsrAddresses::Mstatus, CsrRegister::value(0, MSTATUS_MASK)),
+            // The extensions cannot be turned off
+            (CsrAddresses::Misa, CsrRegister::constant(MISA)),
+            (CsrAddresses::Medeleg, CsrRegister::value(0, DELEGABLE_EXCEPTIONS)),
+            (CsrAddresses::Mideleg, CsrRegister::value(0, SUPERVISOR_INTERRUPTS)),
+            (CsrAddresses::Mie, CsrRegister::value(0, SUPERVISOR_INTERRUPTS | MACHINE_INTERRUPTS)),
+            // Only the direct (0) and vectored (1) modes exist
+            (CsrAddresses::Mtvec, CsrRegister::value(0, !0b10)),
+            (CsrAddresses::Mcounteren, CsrRegister::value(0, u32::MAX)),
+            // time cannot be inhibited, as it is the CLINT mtime
+            (CsrAddresses::Mcountinhibit, CsrRegister::value(0, !(1 << TIME_COUNTER))),
+            (CsrAddresses::Mscratch, CsrRegister::value(0, u32::MAX)),
+            (CsrAddresses::Mepc, CsrRegister::value(0, !1)),
+            (CsrAddresses::Mcause, CsrRegister::value(0, u32::MAX)),
+            (CsrAddresses::Mtval, CsrRegister::value(0, u32::MAX)),
+            // The machine interrupts are driven by the devices
+            (CsrAddresses::Mip, CsrRegister::value(0, SUPERVISOR_INTERRUPTS))
+        ];
+
+        let mut registers: HashMap<u32, CsrRegister> = definitions.iter().map(|(addr, register)| (*addr as u32, *register)).collect();
+
+        for index in 0..PMP_ENTRIES / 4
+        {
+            registers.insert(CsrAddresses::Pmpcfg0 as u32 + index as u32, CsrRegister::held(CsrStorage::PmpConfig(index)));
+        }
+
+        for entry in 0..PMP_ENTRIES
+        {
+            registers.insert(CsrAddresses::Pmpaddr0 as u32 + entry as u32, CsrRegister::held(CsrStorage::PmpAddress(entry)));
+        }
+
+        // Every counter can be read at user level, and all but time have a writable machine mode counter
+        for counter in 0..COUNTERS
+        {
+            for (low, high) in [(CsrAddresses::Cycle, CsrAddresses::Cycleh), (CsrAddresses::Mcycle, CsrAddresses::Mcycleh)]
+            {
+                if counter == TIME_COUNTER && low == CsrAddresses::Mcycle
+                {
+                    continue;
+                }
+
+                registers.insert(low as u32 + counter as u32, CsrRegister::held(CsrStorage::Counter(counter, false)));
+                registers.insert(high as u32 + counter as u32, CsrRegister::held(CsrStorage::Counter(counter, true)));
+            }
+
+            // The hardware performance monitor counters (3 onwards) select their event
+            if counter >= 3
+            {
+                registers.insert(CsrAddresses::Mcountinhibit as u32 + counter as u32, CsrRegister::held(CsrStorage::EventSelector(counter)));
+            }
+        }
+
         Self
         {
-            data: [0; 4096]
+            registers,
+            pmp: Pmp::new(),
+            counters: Counters::new(),
+            mtvec_written: false,
+            stvec_written: false
+        }
+    }
+
+    /// Check if a trap handler has been installed by writing the trap vector (mtvec or stvec) since reset
+    pub fn is_trap_vector_written(&self, tvec: CsrAddresses) -> bool
+    {
+        match tvec
+        {
+            CsrAddresses::Mtvec => self.mtvec_written,
+            CsrAddresses::Stvec => self.stvec_written,
+            _ => false
+        }
+    }
+
+    /// Get the PMP configured through the pmpcfg and pmpaddr CSRs
+    pub fn get_pmp(&self) -> &Pmp
+    {
+        &self.pmp
+    }
+
+    /// Check if software at a privilege level may access a CSR (it must exist, not need a higher privilege, and not be read only for a write)
+    pub fn check_access(&mut self, addr: u32, privilege: Privilege, write: bool) -> bool
+    {
+        // Bits 11:10 are 0b11 for read only CSRs, and bits 9:8 give the lowest privilege which can access it
+        let read_only = (addr >> 10) & 0b11 == 0b11;
+        let required = Privilege::from_bits(addr >> 8);
+
+        if !self.registers.contains_key(&addr) || privilege < required || (write && read_only)
+        {
+            return false;
         }
+
+        // The user level counters are only available below machine mode when enabled in mcounteren, and in user mode also in scounteren
+        let counter = addr & 0x1F;
+        let user_counter = (addr & !0x1F) == CsrAddresses::Cycle as u32 || (addr & !0x1F) == CsrAddresses::Cycleh as u32;
+
+        let enabled = (privilege == Privilege::Machine || self.read_csr(CsrAddresses::Mcounteren as u32) & (1 << counter) != 0) &&
+                      (privilege != Privilege::User || self.read_csr(CsrAddresses::Scounteren as u32) & (1 << counter) != 0);
+
+        !user_counter || enabled
     }
 
-    /// Read a Csr
+    /// Read a Csr (undefined CSRs read as zero)
     pub fn read_csr(&mut self, addr: u32) -> u32
     {
-        self.data[(addr as usize)]
+        let register = match self.registers.get(&addr)
+        {
+            Some(register) => *register,
+            None => return 0
+        };
+
+        match register.storage
+        {
+            CsrStorage::Value(value) => value & register.read_mask,
+            CsrStorage::View(of, delegated) => self.read_csr(of) & register.read_mask & self.delegation_mask(delegated),
+            CsrStorage::PmpConfig(index) => self.pmp.read_config(index),
+            CsrStorage::PmpAddress(entry) => self.pmp.read_address(entry),
+            CsrStorage::Counter(counter, high) => self.counters.read_counter(counter, high),
+            CsrStorage::EventSelector(counter) => self.counters.read_event(counter)
+        }
     }
 
-    /// Write a Csr
+    /// Write a Csr, leaving the bits which are read only (writes to undefined CSRs are ignored)
     pub fn write_csr(&mut self, addr: u32, data: u32)
     {
-        self.data[(addr as usize)] = data;
+        let register = match self.registers.get(&addr)
+        {
+            Some(register) => *register,
+            None => return
+        };
+
+        if addr == CsrAddresses::Mtvec as u32
+        {
+            self.mtvec_written = true;
+        }
+        else if addr == CsrAddresses::Stvec as u32
+        {
+            self.stvec_written = true;
+        }
+
+        // Replace the bits of a value under a mask
+        let merge = |value: u32, mask: u32| (value & !mask) | (data & mask);
+
+        match register.storage
+        {
+            CsrStorage::Value(value) =>
+            {
+                let written = merge(value, register.write_mask);
+
+                // mstatus.MPP cannot hold the reserved privilege 0b10, so it keeps its previous value
+                let legal = if addr == CsrAddresses::Mstatus as u32 && written & MSTATUS_MPP == 0b10 << 11
+                {
+                    (written & !MSTATUS_MPP) | (value & MSTATUS_MPP)
+                }
+                else
+                {
+                    written
+                };
+
+                self.set_value(addr, legal);
+            },
+            CsrStorage::View(of, delegated) =>
+            {
+                let value = self.read_csr(of);
+                let mask = register.write_mask & self.delegation_mask(delegated);
+
+                self.set_value(of, merge(value, mask));
+            },
+            CsrStorage::PmpConfig(index) => self.pmp.write_config(index, data),
+            CsrStorage::PmpAddress(entry) => self.pmp.write_address(entry, data),
+            CsrStorage::Counter(counter, high) => self.counters.write_counter(counter, high, data),
+            CsrStorage::EventSelector(counter) => self.counters.write_event(counter, data)
+        }
+    }
+
+    /// Drive the machine interrupt pending bits of mip from the interrupt lines of the devices
+    pub fn set_interrupt_lines(&mut self, lines: u32)
+    {
+        let mip = self.read_csr(CsrAddresses::Mip as u32);
+        self.set_value(CsrAddresses::Mip as u32, (mip & !MACHINE_INTERRUPTS) | (lines & MACHINE_INTERRUPTS));
+    }
+
+    /// Set the time counter from the CLINT mtime
+    pub fn set_time(&mut self, mtime: u64)
+    {
+        self.counters.set_time(mtime);
+    }
+
+    /// Count the events which happened in each of a number of clocks spent in the given ChipMode
+    pub fn count_events(&mut self, events: u32, mode: u32, clocks: u64)
+    {
+        let inhibit = self.read_csr(CsrAddresses::Mcountinhibit as u32);
+        self.counters.count(events, mode, inhibit, clocks);
+    }
+
+    /// Get the mask of a view, restricted to the delegated interrupts if needed
+    fn delegation_mask(&mut self, delegated: bool) -> u32
+    {
+        if delegated {self.read_csr(CsrAddresses::Mideleg as u32)} else {u32::MAX}
+    }
+
+    /// Set the value held by a CSR, ignoring its write mask
+    fn set_value(&mut self, addr: u32, value: u32)
+    {
+        if let Some(register) = self.registers.get_mut(&addr)
+        {
+            register.storage = CsrStorage::Value(value);
+        }
     }
 }
\ No newline at end of file
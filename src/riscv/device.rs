@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+/// Width of a bus access
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccessWidth
+{
+    Byte,
+    Half,
+    Word
+}
+
+impl AccessWidth
+{
+    /// Get the number of bytes the access covers
+    pub fn bytes(&self) -> u32
+    {
+        match self
+        {
+            AccessWidth::Byte => 1,
+            AccessWidth::Half => 2,
+            AccessWidth::Word => 4
+        }
+    }
+
+    /// Get the mask of the bits of a value the access carries
+    pub fn mask(&self) -> u32
+    {
+        match self
+        {
+            AccessWidth::Byte => 0xFF,
+            AccessWidth::Half => 0xFFFF,
+            AccessWidth::Word => u32::MAX
+        }
+    }
+}
+
+/// Trait for a memory mapped peripheral, which sees each bus access whole and is clocked with the hart. Interrupts are raised
+/// through an InterruptLine given to the device when it is built
+pub trait Device
+{
+    /// Read a value of the given width at an offset into the device (which may have side effects, unlike a memory read)
+    fn read(&mut self, offset: u32, width: AccessWidth) -> u32;
+
+    /// Read a value of the given width at an offset into the device without any side effects, as a debugger inspecting it would
+    fn peek(&self, offset: u32, width: AccessWidth) -> u32;
+
+    /// Write a value of the given width at an offset into the device
+    fn write(&mut self, offset: u32, width: AccessWidth, data: u32);
+
+    /// Advance by one clock cycle
+    fn tick(&mut self)
+    {
+
+    }
+
+    /// Advance by a number of clock cycles at once (never past the clock given by clocks_until_event)
+    fn advance(&mut self, clocks: u64)
+    {
+        for _ in 0..clocks
+        {
+            self.tick();
+        }
+    }
+
+    /// Get the number of clock cycles until the device next changes on its own, such as raising an interrupt (None if it never will)
+    fn clocks_until_event(&self) -> Option<u64>
+    {
+        None
+    }
+
+    /// Return the device to its power on state
+    fn reset(&mut self)
+    {
+
+    }
+}
+
+/// A register in a RegisterBank
+#[derive(Debug, Clone, Copy)]
+pub struct BankRegister
+{
+    value: u32,
+    reset: u32,
+    /// Bits software can change by writing them
+    write_mask: u32,
+    /// Bits software clears by writing ones to them
+    clear_mask: u32,
+    /// Bits cleared by reading them
+    read_clear_mask: u32
+}
+
+impl BankRegister
+{
+    /// A register holding the bits of write_mask written to it
+    pub fn value(reset: u32, write_mask: u32) -> Self
+    {
+        Self
+        {
+            value: reset,
+            reset,
+            write_mask,
+            clear_mask: 0,
+            read_clear_mask: 0
+        }
+    }
+
+    /// A register which always reads the same value
+    pub fn constant(value: u32) -> Self
+    {
+        Self::value(value, 0)
+    }
+
+    /// A status register whose bits are set by the device and cleared by writing ones to them
+    pub fn write_one_to_clear(mask: u32) -> Self
+    {
+        Self
+        {
+            clear_mask: mask,
+            ..Self::value(0, 0)
+        }
+    }
+
+    /// A register set by the device whose bits are cleared once software reads them
+    pub fn clear_on_read(mask: u32) -> Self
+    {
+        Self
+        {
+            read_clear_mask: mask,
+            ..Self::value(0, 0)
+        }
+    }
+}
+
+/// A bank of 32 bit registers at word offsets, taking care of the access widths and software side effects for a Device. Offsets
+/// with no register read as zero and ignore writes
+pub struct RegisterBank
+{
+    registers: HashMap<u32, BankRegister>
+}
+
+impl RegisterBank
+{
+    /// Generate a new RegisterBank from its registers and their offsets
+    pub fn new(definitions: &[(u32, BankRegister)]) -> Self
+    {
+        Self
+        {
+            registers: definitions.iter().map(|(offset, register)| (*offset & !0b11, *register)).collect()
+        }
+    }
+
+    /// Get the value of a register, without any side effects (for the device)
+    pub fn get(&self, offset: u32) -> u32
+    {
+        self.registers.get(&offset).map_or(0, |register| register.value)
+    }
+
+    /// Set the value of a register, whatever software may write to it (for the device)
+    pub fn set(&mut self, offset: u32, value: u32)
+    {
+        if let Some(register) = self.registers.get_mut(&offset)
+        {
+            register.value = value;
+        }
+    }
+
+    /// Set bits of a register (for the device)
+    pub fn set_bits(&mut self, offset: u32, bits: u32)
+    {
+        self.set(offset, self.get(offset) | bits);
+    }
+
+    /// Return every register to its reset value
+    pub fn reset(&mut self)
+    {
+        for register in self.registers.values_mut()
+        {
+            register.value = register.reset;
+        }
+    }
+
+    /// Read from software, giving the bytes of the register at the offset (a narrow access picks out the bytes it covers)
+    pub fn read(&mut self, offset: u32, width: AccessWidth) -> u32
+    {
+        let value = self.peek(offset, width);
+        let lanes = width.mask() << (8 * (offset & 0b11));
+
+        if let Some(register) = self.registers.get_mut(&(offset & !0b11))
+        {
+            register.value &= !(register.read_clear_mask & lanes);
+        }
+
+        value
+    }
+
+    /// Read the bytes software would, without clearing any bits
+    pub fn peek(&self, offset: u32, width: AccessWidth) -> u32
+    {
+        let shift = 8 * (offset & 0b11);
+
+        (self.get(offset & !0b11) >> shift) & width.mask()
+    }
+
+    /// Write from software, changing only the bytes the access covers, giving the offset of the register written (if any)
+    pub fn write(&mut self, offset: u32, width: AccessWidth, data: u32) -> Option<u32>
+    {
+        let shift = 8 * (offset & 0b11);
+        let lanes = width.mask() << shift;
+        let bits = (data << shift) & lanes;
+
+        match self.registers.get_mut(&(offset & !0b11))
+        {
+            Some(register) =>
+            {
+                let writable = register.write_mask & lanes;
+
+                register.value = (register.value & !writable) | (bits & writable);
+                register.value &= !(bits & register.clear_mask);
+
+                Some(offset & !0b11)
+            },
+            None => None
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,258 @@
+use std::fmt;
+
+use super::{Instruction, Operation, extract_bit_range};
+use super::MemoryAccess;
+use super::{is_compressed, expand_compressed};
+
+/// ABI names of the integer registers
+pub const REGISTER_NAMES: [&str; 32] =
+[
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2",
+    "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7",
+    "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6"
+];
+
+/// Get the ABI name of a register
+pub fn register_name(reg: u8) -> &'static str
+{
+    REGISTER_NAMES[(reg & 0b11111) as usize]
+}
+
+/// Format a branch or jump target, either as an absolute address or as an offset
+fn format_target(offset: u32, pc: Option<u32>) -> String
+{
+    match pc
+    {
+        Some(pc) => format!("0x{:08X}", pc.wrapping_add(offset)),
+        None if (offset as i32) < 0 => format!("-0x{:X}", (offset as i32).unsigned_abs()),
+        None => format!("0x{:X}", offset)
+    }
+}
+
+/// Format the predecessor or successor set of a fence as the letters i, o, r and w
+fn format_fence_set(set: u32) -> String
+{
+    // An empty set is written as 0, like objdump does
+    if set == 0
+    {
+        return String::from("0");
+    }
+
+    "iorw".chars().enumerate().filter(|(i, _)| set & (0b1000 >> i) != 0).map(|(_, c)| c).collect()
+}
+
+/// Write the assembly for an instruction, with branch targets made absolute if the pc is known
+fn write_instruction(f: &mut dyn fmt::Write, inst: &Instruction, pc: Option<u32>) -> fmt::Result
+{
+    let mnemonic = inst.operation.mnemonic();
+    let rd = register_name(inst.rd);
+    let rs1 = register_name(inst.rs1);
+    let rs2 = register_name(inst.rs2);
+    let imm = inst.immediate as i32;
+    let csr = inst.immediate & 0xFFF;
+    let ordering = match inst.funct7 & 0b11
+    {
+        0b11 => ".aqrl",
+        0b10 => ".aq",
+        0b01 => ".rl",
+        _ => ""
+    };
+
+    match inst.operation
+    {
+        Operation::Add | Operation::Sub | Operation::Sll | Operation::Slt | Operation::Sltu |
+        Operation::Xor | Operation::Srl | Operation::Sra | Operation::Or | Operation::And |
+        Operation::Mul | Operation::Mulh | Operation::Mulhsu | Operation::Mulhu |
+        Operation::Div | Operation::Divu | Operation::Rem | Operation::Remu =>
+            write!(f, "{} {}, {}, {}", mnemonic, rd, rs1, rs2),
+        Operation::Slli | Operation::Srli | Operation::Srai =>
+            write!(f, "{} {}, {}, {}", mnemonic, rd, rs1, inst.rs2),
+        Operation::Addi | Operation::Slti | Operation::Sltiu | Operation::Xori | Operation::Ori | Operation::Andi =>
+            write!(f, "{} {}, {}, {}", mnemonic, rd, rs1, imm),
+        Operation::Lb | Operation::Lh | Operation::Lw | Operation::Lbu | Operation::Lhu | Operation::Jalr =>
+            write!(f, "{} {}, {}({})", mnemonic, rd, imm, rs1),
+        Operation::Sb | Operation::Sh | Operation::Sw =>
+            write!(f, "{} {}, {}({})", mnemonic, rs2, imm, rs1),
+        Operation::Beq | Operation::Bne | Operation::Blt | Operation::Bge | Operation::Bltu | Operation::Bgeu =>
+            write!(f, "{} {}, {}, {}", mnemonic, rs1, rs2, format_target(inst.immediate, pc)),
+        Operation::Lui | Operation::Auipc =>
+            write!(f, "{} {}, 0x{:X}", mnemonic, rd, inst.immediate >> 12),
+        Operation::Jal =>
+            write!(f, "{} {}, {}", mnemonic, rd, format_target(inst.immediate, pc)),
+        Operation::LrW =>
+            write!(f, "{}{} {}, ({})", mnemonic, ordering, rd, rs1),
+        Operation::ScW | Operation::AmoswapW | Operation::AmoaddW | Operation::AmoxorW | Operation::AmoandW |
+        Operation::AmoorW | Operation::AmominW | Operation::AmomaxW | Operation::AmominuW | Operation::AmomaxuW =>
+            write!(f, "{}{} {}, {}, ({})", mnemonic, ordering, rd, rs2, rs1),
+        Operation::Csrrw | Operation::Csrrs | Operation::Csrrc =>
+            write!(f, "{} {}, 0x{:03X}, {}", mnemonic, rd, csr, rs1),
+        Operation::Csrrwi | Operation::Csrrsi | Operation::Csrrci =>
+            write!(f, "{} {}, 0x{:03X}, {}", mnemonic, rd, csr, inst.rs1),
+        // fence w, 0 with no registers is the pause hint
+        Operation::Fence if inst.immediate & 0xFFF == 0x010 && inst.rd == 0 && inst.rs1 == 0 =>
+            write!(f, "pause"),
+        Operation::Fence =>
+            write!(f, "{} {}, {}", mnemonic, format_fence_set(extract_bit_range(inst.immediate, 4, 4)), format_fence_set(extract_bit_range(inst.immediate, 0, 4))),
+        Operation::SfenceVma =>
+            write!(f, "{} {}, {}", mnemonic, rs1, rs2),
+        Operation::FenceTso | Operation::FenceI |
+        Operation::Ecall | Operation::Ebreak | Operation::Sret | Operation::Mret | Operation::Wfi | Operation::Illegal =>
+            write!(f, "{}", mnemonic)
+    }
+}
+
+impl fmt::Display for Instruction
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write_instruction(f, self, None)
+    }
+}
+
+/// Disassemble an instruction word, giving branch and jump targets as offsets
+pub fn disassemble(word: u32) -> String
+{
+    disassemble_inner(word, None)
+}
+
+/// Disassemble an instruction word located at the pc, giving branch and jump targets as absolute addresses
+pub fn disassemble_at(word: u32, pc: u32) -> String
+{
+    disassemble_inner(word, Some(pc))
+}
+
+/// Disassemble an instruction word, with branch and jump targets made absolute if the pc is known
+fn disassemble_inner(word: u32, pc: Option<u32>) -> String
+{
+    let mut text = String::new();
+    let _ = write_instruction(&mut text, &Instruction::new(word), pc);
+
+    text
+}
+
+/// Disassemble a number of instructions starting at an address, one line per instruction (compressed instructions are shown
+/// expanded). Memory is only peeked at, so listing a peripheral's registers leaves them as they were
+pub fn disassemble_memory<M: MemoryAccess + ?Sized>(memory: &M, start: u32, count: u32) -> String
+{
+    let mut listing = String::new();
+    let mut addr = start;
+
+    let peek_u16 = |addr: u32| (memory.peek_byte(addr) as u16) | ((memory.peek_byte(addr.wrapping_add(1)) as u16) << 8);
+
+    for _ in 0..count
+    {
+        let parcel = peek_u16(addr);
+
+        if is_compressed(parcel)
+        {
+            let text = match expand_compressed(parcel)
+            {
+                Some(word) => disassemble_at(word, addr),
+                None => String::from("illegal")
+            };
+
+            listing.push_str(&format!("0x{:08X}:  {:04X}      {}\n", addr, parcel, text));
+            addr = addr.wrapping_add(2);
+        }
+        else
+        {
+            let word = (peek_u16(addr) as u32) | ((peek_u16(addr.wrapping_add(2)) as u32) << 16);
+
+            listing.push_str(&format!("0x{:08X}:  {:08X}  {}\n", addr, word, disassemble_at(word, addr)));
+            addr = addr.wrapping_add(4);
+        }
+    }
+
+    listing
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use super::super::{MemoryAccess16, MemoryAccess32, MotherboardMemory, MemoryRegion, Ram512k, Plic, PLIC_SIZE};
+
+    #[test]
+    fn lists_operands_in_assembler_syntax()
+    {
+        let cases =
+        [
+            (0x00B50533, "add a0, a0, a1"),
+            (0xFFF00513, "addi a0, zero, -1"),
+            (0x40155513, "srai a0, a0, 1"),
+            (0x0002A303, "lw t1, 0(t0)"),
+            (0x00A5A423, "sw a0, 8(a1)"),
+            (0x001002B7, "lui t0, 0x100"),
+            (0x34059573, "csrrw a0, 0x340, a1"),
+            (0x30047073, "csrrci zero, 0x300, 8"),
+            (0x0EC5A52F, "amoswap.w.aqrl a0, a2, (a1)"),
+            (0x1005A52F, "lr.w a0, (a1)"),
+            (0x0FF0000F, "fence iorw, iorw"),
+            (0x0100000F, "pause"),
+            (0x0000000F, "fence 0, 0"),
+            (0x12B50073, "sfence.vma a0, a1"),
+            (0x30200073, "mret"),
+            (0x10500073, "wfi"),
+            (0x00000000, "illegal")
+        ];
+
+        for (word, text) in cases
+        {
+            assert_eq!(disassemble(word), text, "0x{:08X}", word);
+        }
+    }
+
+    #[test]
+    fn fences_without_registers_have_no_operands()
+    {
+        assert_eq!(disassemble(0x0000100F), "fence.i");
+        assert_eq!(disassemble(0x8330000F), "fence.tso");
+    }
+
+    #[test]
+    fn targets_are_offsets_or_absolute_addresses()
+    {
+        // beq a0, zero, -4
+        assert_eq!(disassemble(0xFE050EE3), "beq a0, zero, -0x4");
+        assert_eq!(disassemble_at(0xFE050EE3, 0x100), "beq a0, zero, 0x000000FC");
+        assert_eq!(disassemble_at(0x0000006F, 0x40), "jal zero, 0x00000040");
+    }
+
+    #[test]
+    fn listing_expands_compressed_parcels()
+    {
+        let ram = Rc::new(RefCell::new(Ram512k::new()));
+        let mut memory = MotherboardMemory::new(vec![MemoryRegion::new("ram", 0, 0x80000, ram)]).unwrap();
+
+        memory.write_u16(0, 0x0505);
+        memory.write_u32(2, 0x00B50533);
+        memory.write_u16(6, 0x0000);
+        memory.write_u32(8, 0xFFFFFFFF);
+
+        // Reserved parcels and words are spelled the same
+        assert_eq!(disassemble_memory(&memory, 0, 4),
+                   "0x00000000:  0505      addi a0, a0, 1\n0x00000002:  00B50533  add a0, a0, a1\n0x00000006:  0000      illegal\n\
+                    0x00000008:  FFFFFFFF  illegal\n");
+    }
+
+    #[test]
+    fn listing_leaves_peripherals_as_they_were()
+    {
+        let plic = Rc::new(RefCell::new(Plic::new()));
+        let mut memory = MotherboardMemory::new(vec![MemoryRegion::device("plic", 0, PLIC_SIZE, plic.clone())]).unwrap();
+
+        memory.write_u32(0x4, 1);
+        memory.write_u32(0x2000, 0b10);
+        plic.borrow_mut().trigger_interrupt(1);
+
+        assert_eq!(disassemble_memory(&memory, 0x200004, 1), "0x00200004:  0001      addi zero, zero, 0\n");
+        assert_eq!(plic.borrow().get_claimed(), 0);
+
+        assert_eq!(memory.read_u32(0x200004), 1);
+        assert_eq!(plic.borrow().get_claimed(), 0b10);
+    }
+}
\ No newline at end of file
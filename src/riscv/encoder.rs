@@ -0,0 +1,477 @@
+use super::{EncodeError, encode_r, encode_i, encode_s, encode_b, encode_u, encode_j};
+use super::sign_extend;
+
+/// Encode a shift by an immediate amount (shamt in 0 to 31)
+fn encode_shift_immediate(rd: u8, funct3: u8, rs1: u8, shamt: u8, funct7: u8) -> Result<u32, EncodeError>
+{
+    if shamt > 31
+    {
+        return Err(EncodeError::ImmediateOutOfRange(shamt as i64, 0, 31));
+    }
+
+    encode_r(0b0010011, rd, funct3, rs1, shamt, funct7)
+}
+
+/// add rd, rs1, rs2
+pub fn add(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b000, rs1, rs2, 0b0000000)
+}
+
+/// sub rd, rs1, rs2
+pub fn sub(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b000, rs1, rs2, 0b0100000)
+}
+
+/// sll rd, rs1, rs2
+pub fn sll(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b001, rs1, rs2, 0b0000000)
+}
+
+/// slt rd, rs1, rs2
+pub fn slt(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b010, rs1, rs2, 0b0000000)
+}
+
+/// sltu rd, rs1, rs2
+pub fn sltu(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b011, rs1, rs2, 0b0000000)
+}
+
+/// xor rd, rs1, rs2
+pub fn xor(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b100, rs1, rs2, 0b0000000)
+}
+
+/// srl rd, rs1, rs2
+pub fn srl(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b101, rs1, rs2, 0b0000000)
+}
+
+/// sra rd, rs1, rs2
+pub fn sra(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b101, rs1, rs2, 0b0100000)
+}
+
+/// or rd, rs1, rs2
+pub fn or(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b110, rs1, rs2, 0b0000000)
+}
+
+/// and rd, rs1, rs2
+pub fn and(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b111, rs1, rs2, 0b0000000)
+}
+
+/// mul rd, rs1, rs2
+pub fn mul(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b000, rs1, rs2, 0b0000001)
+}
+
+/// mulh rd, rs1, rs2
+pub fn mulh(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b001, rs1, rs2, 0b0000001)
+}
+
+/// mulhsu rd, rs1, rs2
+pub fn mulhsu(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b010, rs1, rs2, 0b0000001)
+}
+
+/// mulhu rd, rs1, rs2
+pub fn mulhu(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b011, rs1, rs2, 0b0000001)
+}
+
+/// div rd, rs1, rs2
+pub fn div(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b100, rs1, rs2, 0b0000001)
+}
+
+/// divu rd, rs1, rs2
+pub fn divu(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b101, rs1, rs2, 0b0000001)
+}
+
+/// rem rd, rs1, rs2
+pub fn rem(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b110, rs1, rs2, 0b0000001)
+}
+
+/// remu rd, rs1, rs2
+pub fn remu(rd: u8, rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b0110011, rd, 0b111, rs1, rs2, 0b0000001)
+}
+
+/// Encode an atomic memory operation (the aq and rl bits set the memory ordering)
+fn encode_atomic(rd: u8, rs1: u8, rs2: u8, funct5: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_r(0b0101111, rd, 0b010, rs1, rs2, (funct5 << 2) | ((aq as u8) << 1) | rl as u8)
+}
+
+/// lr.w rd, (rs1)
+pub fn lr_w(rd: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, 0, 0b00010, aq, rl)
+}
+
+/// sc.w rd, rs2, (rs1)
+pub fn sc_w(rd: u8, rs2: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, rs2, 0b00011, aq, rl)
+}
+
+/// amoswap.w rd, rs2, (rs1)
+pub fn amoswap_w(rd: u8, rs2: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, rs2, 0b00001, aq, rl)
+}
+
+/// amoadd.w rd, rs2, (rs1)
+pub fn amoadd_w(rd: u8, rs2: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, rs2, 0b00000, aq, rl)
+}
+
+/// amoxor.w rd, rs2, (rs1)
+pub fn amoxor_w(rd: u8, rs2: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, rs2, 0b00100, aq, rl)
+}
+
+/// amoand.w rd, rs2, (rs1)
+pub fn amoand_w(rd: u8, rs2: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, rs2, 0b01100, aq, rl)
+}
+
+/// amoor.w rd, rs2, (rs1)
+pub fn amoor_w(rd: u8, rs2: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, rs2, 0b01000, aq, rl)
+}
+
+/// amomin.w rd, rs2, (rs1)
+pub fn amomin_w(rd: u8, rs2: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, rs2, 0b10000, aq, rl)
+}
+
+/// amomax.w rd, rs2, (rs1)
+pub fn amomax_w(rd: u8, rs2: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, rs2, 0b10100, aq, rl)
+}
+
+/// amominu.w rd, rs2, (rs1)
+pub fn amominu_w(rd: u8, rs2: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, rs2, 0b11000, aq, rl)
+}
+
+/// amomaxu.w rd, rs2, (rs1)
+pub fn amomaxu_w(rd: u8, rs2: u8, rs1: u8, aq: bool, rl: bool) -> Result<u32, EncodeError>
+{
+    encode_atomic(rd, rs1, rs2, 0b11100, aq, rl)
+}
+
+/// addi rd, rs1, imm
+pub fn addi(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0010011, rd, 0b000, rs1, imm)
+}
+
+/// slti rd, rs1, imm
+pub fn slti(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0010011, rd, 0b010, rs1, imm)
+}
+
+/// sltiu rd, rs1, imm
+pub fn sltiu(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0010011, rd, 0b011, rs1, imm)
+}
+
+/// xori rd, rs1, imm
+pub fn xori(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0010011, rd, 0b100, rs1, imm)
+}
+
+/// ori rd, rs1, imm
+pub fn ori(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0010011, rd, 0b110, rs1, imm)
+}
+
+/// andi rd, rs1, imm
+pub fn andi(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0010011, rd, 0b111, rs1, imm)
+}
+
+/// slli rd, rs1, shamt (shamt in 0 to 31)
+pub fn slli(rd: u8, rs1: u8, shamt: u8) -> Result<u32, EncodeError>
+{
+    encode_shift_immediate(rd, 0b001, rs1, shamt, 0b0000000)
+}
+
+/// srli rd, rs1, shamt (shamt in 0 to 31)
+pub fn srli(rd: u8, rs1: u8, shamt: u8) -> Result<u32, EncodeError>
+{
+    encode_shift_immediate(rd, 0b101, rs1, shamt, 0b0000000)
+}
+
+/// srai rd, rs1, shamt (shamt in 0 to 31)
+pub fn srai(rd: u8, rs1: u8, shamt: u8) -> Result<u32, EncodeError>
+{
+    encode_shift_immediate(rd, 0b101, rs1, shamt, 0b0100000)
+}
+
+/// lb rd, imm(rs1)
+pub fn lb(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0000011, rd, 0b000, rs1, imm)
+}
+
+/// lh rd, imm(rs1)
+pub fn lh(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0000011, rd, 0b001, rs1, imm)
+}
+
+/// lw rd, imm(rs1)
+pub fn lw(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0000011, rd, 0b010, rs1, imm)
+}
+
+/// lbu rd, imm(rs1)
+pub fn lbu(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0000011, rd, 0b100, rs1, imm)
+}
+
+/// lhu rd, imm(rs1)
+pub fn lhu(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b0000011, rd, 0b101, rs1, imm)
+}
+
+/// sb rs2, imm(rs1)
+pub fn sb(rs2: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_s(0b0100011, 0b000, rs1, rs2, imm)
+}
+
+/// sh rs2, imm(rs1)
+pub fn sh(rs2: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_s(0b0100011, 0b001, rs1, rs2, imm)
+}
+
+/// sw rs2, imm(rs1)
+pub fn sw(rs2: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_s(0b0100011, 0b010, rs1, rs2, imm)
+}
+
+/// beq rs1, rs2, offset
+pub fn beq(rs1: u8, rs2: u8, offset: i32) -> Result<u32, EncodeError>
+{
+    encode_b(0b1100011, 0b000, rs1, rs2, offset)
+}
+
+/// bne rs1, rs2, offset
+pub fn bne(rs1: u8, rs2: u8, offset: i32) -> Result<u32, EncodeError>
+{
+    encode_b(0b1100011, 0b001, rs1, rs2, offset)
+}
+
+/// blt rs1, rs2, offset
+pub fn blt(rs1: u8, rs2: u8, offset: i32) -> Result<u32, EncodeError>
+{
+    encode_b(0b1100011, 0b100, rs1, rs2, offset)
+}
+
+/// bge rs1, rs2, offset
+pub fn bge(rs1: u8, rs2: u8, offset: i32) -> Result<u32, EncodeError>
+{
+    encode_b(0b1100011, 0b101, rs1, rs2, offset)
+}
+
+/// bltu rs1, rs2, offset
+pub fn bltu(rs1: u8, rs2: u8, offset: i32) -> Result<u32, EncodeError>
+{
+    encode_b(0b1100011, 0b110, rs1, rs2, offset)
+}
+
+/// bgeu rs1, rs2, offset
+pub fn bgeu(rs1: u8, rs2: u8, offset: i32) -> Result<u32, EncodeError>
+{
+    encode_b(0b1100011, 0b111, rs1, rs2, offset)
+}
+
+/// lui rd, imm (imm is the upper 20 bits)
+pub fn lui(rd: u8, imm: u32) -> Result<u32, EncodeError>
+{
+    encode_u(0b0110111, rd, imm)
+}
+
+/// auipc rd, imm (imm is the upper 20 bits)
+pub fn auipc(rd: u8, imm: u32) -> Result<u32, EncodeError>
+{
+    encode_u(0b0010111, rd, imm)
+}
+
+/// jal rd, offset
+pub fn jal(rd: u8, offset: i32) -> Result<u32, EncodeError>
+{
+    encode_j(0b1101111, rd, offset)
+}
+
+/// jalr rd, imm(rs1)
+pub fn jalr(rd: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    encode_i(0b1100111, rd, 0b000, rs1, imm)
+}
+
+/// fence pred, succ (each a set of the device input, device output, memory read and memory write bits, i being 0b1000)
+pub fn fence(pred: u8, succ: u8) -> Result<u32, EncodeError>
+{
+    if pred > 0b1111
+    {
+        return Err(EncodeError::FieldOutOfRange("pred", pred));
+    }
+
+    if succ > 0b1111
+    {
+        return Err(EncodeError::FieldOutOfRange("succ", succ));
+    }
+
+    encode_i(0b0001111, 0, 0b000, 0, ((pred << 4) | succ) as i32)
+}
+
+/// fence.tso
+pub fn fence_tso() -> Result<u32, EncodeError>
+{
+    encode_i(0b0001111, 0, 0b000, 0, sign_extend(0x833, 11) as i32)
+}
+
+/// fence.i
+pub fn fence_i() -> Result<u32, EncodeError>
+{
+    encode_i(0b0001111, 0, 0b001, 0, 0)
+}
+
+/// ecall
+pub fn ecall() -> Result<u32, EncodeError>
+{
+    encode_i(0b1110011, 0, 0b000, 0, 0)
+}
+
+/// ebreak
+pub fn ebreak() -> Result<u32, EncodeError>
+{
+    encode_i(0b1110011, 0, 0b000, 0, 1)
+}
+
+/// sret
+pub fn sret() -> Result<u32, EncodeError>
+{
+    encode_i(0b1110011, 0, 0b000, 0, 0x102)
+}
+
+/// mret
+pub fn mret() -> Result<u32, EncodeError>
+{
+    encode_i(0b1110011, 0, 0b000, 0, 0x302)
+}
+
+/// wfi
+pub fn wfi() -> Result<u32, EncodeError>
+{
+    encode_i(0b1110011, 0, 0b000, 0, 0x105)
+}
+
+/// sfence.vma rs1, rs2
+pub fn sfence_vma(rs1: u8, rs2: u8) -> Result<u32, EncodeError>
+{
+    encode_r(0b1110011, 0, 0b000, rs1, rs2, 0b0001001)
+}
+
+/// Encode a CSR instruction (csr in 0 to 4095), the source is either rs1 or an immediate in 0 to 31
+fn encode_csr(rd: u8, funct3: u8, csr: u16, source: u8) -> Result<u32, EncodeError>
+{
+    if csr > 0xFFF
+    {
+        return Err(EncodeError::ImmediateOutOfRange(csr as i64, 0, 0xFFF));
+    }
+
+    encode_i(0b1110011, rd, funct3, source, sign_extend(csr as u32, 11) as i32)
+}
+
+/// Check the immediate of a CSR instruction is in 0 to 31
+fn check_csr_immediate(imm: u8) -> Result<u8, EncodeError>
+{
+    if imm > 31
+    {
+        return Err(EncodeError::ImmediateOutOfRange(imm as i64, 0, 31));
+    }
+
+    Ok(imm)
+}
+
+/// csrrw rd, csr, rs1
+pub fn csrrw(rd: u8, csr: u16, rs1: u8) -> Result<u32, EncodeError>
+{
+    encode_csr(rd, 0b001, csr, rs1)
+}
+
+/// csrrs rd, csr, rs1
+pub fn csrrs(rd: u8, csr: u16, rs1: u8) -> Result<u32, EncodeError>
+{
+    encode_csr(rd, 0b010, csr, rs1)
+}
+
+/// csrrc rd, csr, rs1
+pub fn csrrc(rd: u8, csr: u16, rs1: u8) -> Result<u32, EncodeError>
+{
+    encode_csr(rd, 0b011, csr, rs1)
+}
+
+/// csrrwi rd, csr, imm
+pub fn csrrwi(rd: u8, csr: u16, imm: u8) -> Result<u32, EncodeError>
+{
+    encode_csr(rd, 0b101, csr, check_csr_immediate(imm)?)
+}
+
+/// csrrsi rd, csr, imm
+pub fn csrrsi(rd: u8, csr: u16, imm: u8) -> Result<u32, EncodeError>
+{
+    encode_csr(rd, 0b110, csr, check_csr_immediate(imm)?)
+}
+
+/// csrrci rd, csr, imm
+pub fn csrrci(rd: u8, csr: u16, imm: u8) -> Result<u32, EncodeError>
+{
+    encode_csr(rd, 0b111, csr, check_csr_immediate(imm)?)
+}
\ No newline at end of file
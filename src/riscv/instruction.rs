@@ -1,5 +1,9 @@
+use std::fmt;
+
+use super::{InstructionFormat, Operation};
+
 /// Extract some bit range from a u32
-fn extract_bit_range(val: u32, bit_low: usize, size: usize) -> u32
+pub fn extract_bit_range(val: u32, bit_low: usize, size: usize) -> u32
 {
     (val >> bit_low) & ((1<<size) - 1)
 }
@@ -19,6 +23,136 @@ pub fn sign_extend(val: u32, bit: usize) -> u32
     }
 }
 
+/// Error generated when an instruction field does not fit in its encoding
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncodeError
+{
+    /// A register number is not in 0-31 (holds the register number)
+    RegisterOutOfRange(u8),
+    /// A fixed field (opcode, funct3 or funct7) is too wide (holds the field name and value)
+    FieldOutOfRange(&'static str, u8),
+    /// An immediate is out of range for its format (holds the value and the allowed range)
+    ImmediateOutOfRange(i64, i64, i64),
+    /// A branch or jump offset is not a multiple of 2
+    MisalignedOffset(i32)
+}
+
+impl fmt::Display for EncodeError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            EncodeError::RegisterOutOfRange(reg) => write!(f, "Register x{} is out of range (0 to 31)", reg),
+            EncodeError::FieldOutOfRange(name, val) => write!(f, "Field {} value 0b{:b} is too wide", name, val),
+            EncodeError::ImmediateOutOfRange(val, min, max) => write!(f, "Immediate {} is out of range ({} to {})", val, min, max),
+            EncodeError::MisalignedOffset(offset) => write!(f, "Offset {} is not a multiple of 2", offset)
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// Check that a register number fits in 5 bits
+fn check_register(reg: u8) -> Result<u32, EncodeError>
+{
+    if reg < 32 {Ok(reg as u32)} else {Err(EncodeError::RegisterOutOfRange(reg))}
+}
+
+/// Check that a fixed field fits in some number of bits
+fn check_field(name: &'static str, val: u8, size: usize) -> Result<u32, EncodeError>
+{
+    if (val as u32) < (1 << size) {Ok(val as u32)} else {Err(EncodeError::FieldOutOfRange(name, val))}
+}
+
+/// Check that an immediate is within a range
+fn check_immediate(val: i64, min: i64, max: i64) -> Result<u32, EncodeError>
+{
+    if val >= min && val <= max {Ok(val as u32)} else {Err(EncodeError::ImmediateOutOfRange(val, min, max))}
+}
+
+/// Check that a branch or jump offset is within a signed range and is a multiple of 2
+fn check_offset(val: i32, bits: usize) -> Result<u32, EncodeError>
+{
+    let imm = check_immediate(val as i64, -(1 << (bits - 1)), (1 << (bits - 1)) - 2)?;
+
+    if val & 1 != 0 {Err(EncodeError::MisalignedOffset(val))} else {Ok(imm)}
+}
+
+/// Encode an R format instruction
+pub fn encode_r(opcode: u8, rd: u8, funct3: u8, rs1: u8, rs2: u8, funct7: u8) -> Result<u32, EncodeError>
+{
+    Ok(check_field("opcode", opcode, 7)? |
+       (check_register(rd)? << 7) |
+       (check_field("funct3", funct3, 3)? << 12) |
+       (check_register(rs1)? << 15) |
+       (check_register(rs2)? << 20) |
+       (check_field("funct7", funct7, 7)? << 25))
+}
+
+/// Encode an I format instruction (immediate in -2048 to 2047)
+pub fn encode_i(opcode: u8, rd: u8, funct3: u8, rs1: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    let imm = check_immediate(imm as i64, -2048, 2047)?;
+
+    Ok(check_field("opcode", opcode, 7)? |
+       (check_register(rd)? << 7) |
+       (check_field("funct3", funct3, 3)? << 12) |
+       (check_register(rs1)? << 15) |
+       (extract_bit_range(imm, 0, 12) << 20))
+}
+
+/// Encode an S format instruction (immediate in -2048 to 2047)
+pub fn encode_s(opcode: u8, funct3: u8, rs1: u8, rs2: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    let imm = check_immediate(imm as i64, -2048, 2047)?;
+
+    Ok(check_field("opcode", opcode, 7)? |
+       (extract_bit_range(imm, 0, 5) << 7) |
+       (check_field("funct3", funct3, 3)? << 12) |
+       (check_register(rs1)? << 15) |
+       (check_register(rs2)? << 20) |
+       (extract_bit_range(imm, 5, 7) << 25))
+}
+
+/// Encode a B format instruction (offset in -4096 to 4094, a multiple of 2)
+pub fn encode_b(opcode: u8, funct3: u8, rs1: u8, rs2: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    let imm = check_offset(imm, 13)?;
+
+    Ok(check_field("opcode", opcode, 7)? |
+       (extract_bit_range(imm, 11, 1) << 7) |
+       (extract_bit_range(imm, 1, 4) << 8) |
+       (check_field("funct3", funct3, 3)? << 12) |
+       (check_register(rs1)? << 15) |
+       (check_register(rs2)? << 20) |
+       (extract_bit_range(imm, 5, 6) << 25) |
+       (extract_bit_range(imm, 12, 1) << 31))
+}
+
+/// Encode a U format instruction (imm is the upper 20 bits, 0 to 0xFFFFF)
+pub fn encode_u(opcode: u8, rd: u8, imm: u32) -> Result<u32, EncodeError>
+{
+    let imm = check_immediate(imm as i64, 0, 0xFFFFF)?;
+
+    Ok(check_field("opcode", opcode, 7)? |
+       (check_register(rd)? << 7) |
+       (imm << 12))
+}
+
+/// Encode a J format instruction (offset in -1048576 to 1048574, a multiple of 2)
+pub fn encode_j(opcode: u8, rd: u8, imm: i32) -> Result<u32, EncodeError>
+{
+    let imm = check_offset(imm, 21)?;
+
+    Ok(check_field("opcode", opcode, 7)? |
+       (check_register(rd)? << 7) |
+       (extract_bit_range(imm, 12, 8) << 12) |
+       (extract_bit_range(imm, 11, 1) << 20) |
+       (extract_bit_range(imm, 1, 10) << 21) |
+       (extract_bit_range(imm, 20, 1) << 31))
+}
+
 /// Instruction
 #[derive(Debug, Clone, Copy)]
 pub struct Instruction
@@ -29,7 +163,8 @@ pub struct Instruction
     pub rs2: u8,
     pub funct7: u8,
     pub funct3: u8,
-    pub immediate: u32
+    pub immediate: u32,
+    pub operation: Operation
 }
 
 impl Instruction
@@ -45,53 +180,37 @@ impl Instruction
         let funct7 = extract_bit_range(inst, 25, 7) as u8;
         let immediate =
 
-        // R Format
-        if opcode == 0b0110011
-        {
-            0
-        }
-        // I Format
-        else if opcode == 0b0010011 || opcode == 0b1100111 || opcode == 0b0000011 || opcode == 0b1110011
-        {
-            sign_extend(rs2 as u32 | ((funct7 as u32) << 5), 11)
-        }
-        // Store Format
-        else if opcode == 0b0100011
-        {
-            sign_extend(rd as u32 | ((funct7 as u32) << 5), 11)
-        }
-        // Branch Format
-        else if opcode == 0b1100011
+        match InstructionFormat::from_opcode(opcode)
         {
-            sign_extend(
-                (extract_bit_range(inst, 7, 1) << 11) |
-                (extract_bit_range(inst, 8, 4) << 1) |
-                (extract_bit_range(inst, 25, 6) << 5) |
-                (extract_bit_range(inst, 31, 1) << 12),
-                12
-            )
-        }
-        // U Format
-        else if opcode == 0b0110111 || opcode == 0b0010111
-        {
-           extract_bit_range(inst, 12, 20) << 12
-        }
-        // J Format
-        else if opcode == 0b1101111
-        {
-            sign_extend(
-                (extract_bit_range(inst, 12, 8) << 12) |
-                (extract_bit_range(inst, 20, 1) << 11) |
-                (extract_bit_range(inst, 21, 10) << 1) |
-                (extract_bit_range(inst, 31, 1) << 20),
-                20
-            )
-        }
-        else
-        {
-            panic!("Unknown opcode format {:07b}", opcode);
+            Some(InstructionFormat::I) => sign_extend(rs2 as u32 | ((funct7 as u32) << 5), 11),
+            Some(InstructionFormat::S) => sign_extend(rd as u32 | ((funct7 as u32) << 5), 11),
+            Some(InstructionFormat::B) =>
+            {
+                sign_extend(
+                    (extract_bit_range(inst, 7, 1) << 11) |
+                    (extract_bit_range(inst, 8, 4) << 1) |
+                    (extract_bit_range(inst, 25, 6) << 5) |
+                    (extract_bit_range(inst, 31, 1) << 12),
+                    12
+                )
+            },
+            Some(InstructionFormat::U) => extract_bit_range(inst, 12, 20) << 12,
+            Some(InstructionFormat::J) =>
+            {
+                sign_extend(
+                    (extract_bit_range(inst, 12, 8) << 12) |
+                    (extract_bit_range(inst, 20, 1) << 11) |
+                    (extract_bit_range(inst, 21, 10) << 1) |
+                    (extract_bit_range(inst, 31, 1) << 20),
+                    20
+                )
+            },
+            // R Format and unknown opcodes have no immediate
+            _ => 0
         };
 
+        let operation = Operation::decode(opcode, funct3, funct7, rs1, rs2, rd, extract_bit_range(inst, 20, 12));
+
         Self
         {
             opcode,
@@ -100,7 +219,103 @@ impl Instruction
             rs2,
             funct7,
             funct3,
-            immediate
+            immediate,
+            operation
         }
     }
+
+    /// Encode the instruction back into the u32 it was decoded from
+    pub fn encode(&self) -> u32
+    {
+        let opcode = self.opcode as u32;
+        let rd = (self.rd as u32) << 7;
+        let funct3 = (self.funct3 as u32) << 12;
+        let rs1 = (self.rs1 as u32) << 15;
+        let rs2 = (self.rs2 as u32) << 20;
+        let funct7 = (self.funct7 as u32) << 25;
+        let imm = self.immediate;
+
+        match InstructionFormat::from_opcode(self.opcode)
+        {
+            Some(InstructionFormat::I) => opcode | rd | funct3 | rs1 | (extract_bit_range(imm, 0, 12) << 20),
+            Some(InstructionFormat::S) =>
+            {
+                opcode | (extract_bit_range(imm, 0, 5) << 7) | funct3 | rs1 | rs2 | (extract_bit_range(imm, 5, 7) << 25)
+            },
+            Some(InstructionFormat::B) =>
+            {
+                opcode | (extract_bit_range(imm, 11, 1) << 7) | (extract_bit_range(imm, 1, 4) << 8) | funct3 | rs1 | rs2 |
+                    (extract_bit_range(imm, 5, 6) << 25) | (extract_bit_range(imm, 12, 1) << 31)
+            },
+            Some(InstructionFormat::U) => opcode | rd | (imm & 0xFFFFF000),
+            Some(InstructionFormat::J) =>
+            {
+                opcode | rd | (extract_bit_range(imm, 12, 8) << 12) | (extract_bit_range(imm, 11, 1) << 20) |
+                    (extract_bit_range(imm, 1, 10) << 21) | (extract_bit_range(imm, 20, 1) << 31)
+            },
+            // R Format and unknown opcodes keep every bit in the register and funct fields
+            _ => opcode | rd | funct3 | rs1 | rs2 | funct7
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use super::super::encoder;
+
+    #[test]
+    fn encoded_instructions_decode_back()
+    {
+        let cases =
+        [
+            (encoder::add(10, 11, 12), Operation::Add, 0),
+            (encoder::addi(10, 11, -2048), Operation::Addi, -2048),
+            (encoder::addi(10, 11, 2047), Operation::Addi, 2047),
+            (encoder::lw(10, 2, -4), Operation::Lw, -4),
+            (encoder::sw(10, 2, -2048), Operation::Sw, -2048),
+            (encoder::sh(31, 1, 2047), Operation::Sh, 2047),
+            (encoder::beq(10, 0, -4096), Operation::Beq, -4096),
+            (encoder::bgeu(1, 2, 4094), Operation::Bgeu, 4094),
+            (encoder::jal(1, -(1 << 20)), Operation::Jal, -(1 << 20)),
+            (encoder::jal(0, (1 << 20) - 2), Operation::Jal, (1 << 20) - 2),
+            (encoder::jalr(1, 5, -1), Operation::Jalr, -1),
+            (encoder::lui(5, 0xFFFFF), Operation::Lui, 0xFFFFF000u32 as i32),
+            (encoder::auipc(8, 1), Operation::Auipc, 0x1000)
+        ];
+
+        for (word, operation, immediate) in cases
+        {
+            let word = word.unwrap();
+            let inst = Instruction::new(word);
+
+            assert_eq!(inst.operation, operation, "0x{:08X}", word);
+            assert_eq!(inst.immediate as i32, immediate, "0x{:08X}", word);
+            assert_eq!(inst.encode(), word);
+        }
+    }
+
+    #[test]
+    fn every_word_encodes_back_to_itself()
+    {
+        // Every format keeps all 32 bits between its fields, so any word survives decoding
+        let mut word: u32 = 1;
+
+        for _ in 0..100000
+        {
+            word = word.wrapping_mul(1664525).wrapping_add(1013904223);
+            assert_eq!(Instruction::new(word).encode(), word, "0x{:08X}", word);
+        }
+    }
+
+    #[test]
+    fn fields_which_do_not_fit_are_rejected()
+    {
+        assert_eq!(encoder::addi(10, 11, 2048), Err(EncodeError::ImmediateOutOfRange(2048, -2048, 2047)));
+        assert_eq!(encoder::add(32, 0, 0), Err(EncodeError::RegisterOutOfRange(32)));
+        assert_eq!(encoder::beq(0, 0, 3), Err(EncodeError::MisalignedOffset(3)));
+        assert_eq!(encoder::jal(0, 1 << 20), Err(EncodeError::ImmediateOutOfRange(1 << 20, -(1 << 20), (1 << 20) - 2)));
+        assert_eq!(encoder::slli(1, 1, 32), Err(EncodeError::ImmediateOutOfRange(32, 0, 31)));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,57 @@
+use super::super::MemoryAccess;
+use super::LoaderError;
+
+/// Check that every byte of a range an image is about to be written to is mapped (and that the range does not wrap around)
+pub(super) fn check_mapped<M: MemoryAccess + ?Sized>(memory: &M, addr: u32, size: usize) -> Result<(), LoaderError>
+{
+    if addr as u64 + size as u64 > 1 << 32 || !memory.is_mapped(addr, size as u32)
+    {
+        return Err(LoaderError::Unmapped(addr, size as u32));
+    }
+
+    Ok(())
+}
+
+/// Load a raw binary image into memory starting at the base address
+pub fn load_binary<M: MemoryAccess + ?Sized>(memory: &mut M, base: u32, data: &[u8]) -> Result<(), LoaderError>
+{
+    check_mapped(memory, base, data.len())?;
+
+    for (i, val) in data.iter().enumerate()
+    {
+        memory.write_byte(base.wrapping_add(i as u32), *val);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use super::super::super::{MotherboardMemory, MemoryRegion, Ram512k};
+
+    #[test]
+    fn data_is_written_from_the_base()
+    {
+        let mut memory = Box::new(Ram512k::new());
+        load_binary(memory.as_mut(), 0x1234, &[1, 2, 3]).unwrap();
+
+        assert_eq!((0x1233..0x1238).map(|addr| memory.read_byte(addr)).collect::<Vec<_>>(), vec![0, 1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn data_outside_of_the_map_is_rejected()
+    {
+        let ram = Rc::new(RefCell::new(Ram512k::new()));
+        let mut memory = MotherboardMemory::new(vec![MemoryRegion::new("ram", 0, 0x80000, ram)]).unwrap();
+
+        assert_eq!(load_binary(&mut memory, 0x7FFFE, &[1, 2, 3, 4]), Err(LoaderError::Unmapped(0x7FFFE, 4)));
+        assert_eq!(load_binary(&mut memory, 0x40000000, &[1]), Err(LoaderError::Unmapped(0x40000000, 1)));
+        assert_eq!(load_binary(&mut memory, 0xFFFFFFFF, &[1, 2]), Err(LoaderError::Unmapped(0xFFFFFFFF, 2)));
+        assert_eq!(memory.read_byte(0x7FFFE), 0);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,280 @@
+use super::super::MemoryAccess;
+use super::LoaderError;
+use super::binary::check_mapped;
+
+const ELF_MAGIC: [u8; 4] = [0x7F, b'E', b'L', b'F'];
+const ELF_CLASS_32: u8 = 1;
+const ELF_DATA_LITTLE_ENDIAN: u8 = 1;
+const ELF_TYPE_EXECUTABLE: u16 = 2;
+const ELF_MACHINE_RISCV: u16 = 243;
+
+const ELF_HEADER_SIZE: usize = 52;
+const PROGRAM_HEADER_SIZE: usize = 32;
+const PT_LOAD: u32 = 1;
+
+/// Read a little endian u16 out of an image
+fn read_u16(data: &[u8], offset: usize, what: &'static str) -> Result<u16, LoaderError>
+{
+    match data.get(offset..offset + 2)
+    {
+        Some(bytes) => Ok((bytes[0] as u16) | ((bytes[1] as u16) << 8)),
+        None => Err(LoaderError::Truncated(what))
+    }
+}
+
+/// Read a little endian u32 out of an image
+fn read_u32(data: &[u8], offset: usize, what: &'static str) -> Result<u32, LoaderError>
+{
+    match data.get(offset..offset + 4)
+    {
+        Some(bytes) => Ok((bytes[0] as u32) | ((bytes[1] as u32) << 8) |
+                          ((bytes[2] as u32) << 16) | ((bytes[3] as u32) << 24)),
+        None => Err(LoaderError::Truncated(what))
+    }
+}
+
+/// Loadable segment of an ELF file
+#[derive(Debug, Clone, Copy)]
+pub struct ElfSegment
+{
+    pub offset: u32,
+    pub address: u32,
+    pub file_size: u32,
+    pub memory_size: u32
+}
+
+/// Parsed RV32 ELF executable
+#[derive(Debug, Clone)]
+pub struct ElfFile
+{
+    pub entry: u32,
+    pub segments: Vec<ElfSegment>
+}
+
+impl ElfFile
+{
+    /// Parse the headers of an ELF file, checking that it is a little endian RV32 executable
+    pub fn parse(data: &[u8]) -> Result<Self, LoaderError>
+    {
+        if data.len() < ELF_HEADER_SIZE
+        {
+            return Err(LoaderError::Truncated("ELF header"));
+        }
+
+        if data[0..4] != ELF_MAGIC
+        {
+            return Err(LoaderError::BadMagic);
+        }
+
+        if data[4] != ELF_CLASS_32
+        {
+            return Err(LoaderError::WrongClass(data[4]));
+        }
+
+        if data[5] != ELF_DATA_LITTLE_ENDIAN
+        {
+            return Err(LoaderError::WrongEndianness(data[5]));
+        }
+
+        let machine = read_u16(data, 18, "ELF header")?;
+        if machine != ELF_MACHINE_RISCV
+        {
+            return Err(LoaderError::WrongMachine(machine));
+        }
+
+        let kind = read_u16(data, 16, "ELF header")?;
+        if kind != ELF_TYPE_EXECUTABLE
+        {
+            return Err(LoaderError::WrongType(kind));
+        }
+
+        let entry = read_u32(data, 24, "ELF header")?;
+        let ph_offset = read_u32(data, 28, "ELF header")? as usize;
+        let ph_size = read_u16(data, 42, "ELF header")? as usize;
+        let ph_count = read_u16(data, 44, "ELF header")? as usize;
+
+        if ph_count > 0 && ph_size < PROGRAM_HEADER_SIZE
+        {
+            return Err(LoaderError::BadSegment(0, "program header entries are too small"));
+        }
+
+        let mut segments = Vec::new();
+
+        for i in 0..ph_count
+        {
+            let header = ph_offset + i * ph_size;
+
+            if read_u32(data, header, "program header")? != PT_LOAD
+            {
+                continue;
+            }
+
+            let segment = ElfSegment
+            {
+                offset: read_u32(data, header + 4, "program header")?,
+                address: read_u32(data, header + 12, "program header")?,
+                file_size: read_u32(data, header + 16, "program header")?,
+                memory_size: read_u32(data, header + 20, "program header")?
+            };
+
+            if segment.file_size > segment.memory_size
+            {
+                return Err(LoaderError::BadSegment(i, "file size is larger than memory size"));
+            }
+
+            if segment.address as u64 + segment.memory_size as u64 > 1 << 32
+            {
+                return Err(LoaderError::BadSegment(i, "segment extends past the end of the address space"));
+            }
+
+            if (segment.offset as usize).checked_add(segment.file_size as usize).is_none_or(|end| end > data.len())
+            {
+                return Err(LoaderError::BadSegment(i, "segment data extends past the end of the file"));
+            }
+
+            segments.push(segment);
+        }
+
+        Ok(Self
+        {
+            entry,
+            segments
+        })
+    }
+
+    /// Copy every loadable segment into memory at its physical address, zero filling past the file data (.bss). Nothing is
+    /// written unless every segment lies inside of the memory map
+    pub fn load_into<M: MemoryAccess + ?Sized>(&self, memory: &mut M, data: &[u8]) -> Result<(), LoaderError>
+    {
+        for segment in &self.segments
+        {
+            check_mapped(memory, segment.address, segment.memory_size as usize)?;
+        }
+
+        for segment in &self.segments
+        {
+            let start = segment.offset as usize;
+            let file_data = &data[start..start + segment.file_size as usize];
+
+            for i in 0..segment.memory_size
+            {
+                let val = if i < segment.file_size {file_data[i as usize]} else {0};
+                memory.write_byte(segment.address.wrapping_add(i), val);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Load an ELF executable into memory, returning its entry point
+pub fn load_elf<M: MemoryAccess + ?Sized>(memory: &mut M, data: &[u8]) -> Result<u32, LoaderError>
+{
+    let elf = ElfFile::parse(data)?;
+
+    elf.load_into(memory, data)?;
+
+    Ok(elf.entry)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use super::super::super::{MotherboardMemory, MemoryRegion, Ram512k};
+
+    /// Build a little endian RV32 executable with a single loadable segment holding the data
+    fn image(address: u32, data: &[u8], memory_size: u32) -> Vec<u8>
+    {
+        let mut image = vec![0; ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE];
+        let mut put = |offset: usize, bytes: &[u8]| image[offset..offset + bytes.len()].copy_from_slice(bytes);
+
+        put(0, &ELF_MAGIC);
+        put(4, &[ELF_CLASS_32, ELF_DATA_LITTLE_ENDIAN, 1]);
+        put(16, &ELF_TYPE_EXECUTABLE.to_le_bytes());
+        put(18, &ELF_MACHINE_RISCV.to_le_bytes());
+        put(20, &1u32.to_le_bytes());
+        put(24, &address.to_le_bytes());
+        put(28, &(ELF_HEADER_SIZE as u32).to_le_bytes());
+        put(40, &(ELF_HEADER_SIZE as u16).to_le_bytes());
+        put(42, &(PROGRAM_HEADER_SIZE as u16).to_le_bytes());
+        put(44, &1u16.to_le_bytes());
+
+        let header = ELF_HEADER_SIZE;
+        put(header, &PT_LOAD.to_le_bytes());
+        put(header + 4, &((ELF_HEADER_SIZE + PROGRAM_HEADER_SIZE) as u32).to_le_bytes());
+        put(header + 8, &address.to_le_bytes());
+        put(header + 12, &address.to_le_bytes());
+        put(header + 16, &(data.len() as u32).to_le_bytes());
+        put(header + 20, &memory_size.to_le_bytes());
+
+        image.extend_from_slice(data);
+        image
+    }
+
+    #[test]
+    fn segments_are_loaded_and_zero_filled()
+    {
+        let mut memory = Box::new(Ram512k::new());
+        for addr in 0x200..0x210
+        {
+            memory.write_byte(addr, 0xFF);
+        }
+
+        let entry = load_elf(memory.as_mut(), &image(0x200, &[1, 2, 3, 4], 0x10)).unwrap();
+
+        assert_eq!(entry, 0x200);
+        assert_eq!((0x200..0x204).map(|addr| memory.read_byte(addr)).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert!((0x204..0x210).all(|addr| memory.read_byte(addr) == 0));
+    }
+
+    #[test]
+    fn images_for_other_targets_are_rejected()
+    {
+        let cases: [(usize, &[u8], LoaderError); 4] =
+        [
+            (4, &[2], LoaderError::WrongClass(2)),
+            (5, &[2], LoaderError::WrongEndianness(2)),
+            (18, &[62, 0], LoaderError::WrongMachine(62)),
+            (16, &[3, 0], LoaderError::WrongType(3))
+        ];
+
+        for (offset, bytes, error) in cases
+        {
+            let mut data = image(0, &[0; 4], 4);
+            data[offset..offset + bytes.len()].copy_from_slice(bytes);
+
+            assert_eq!(ElfFile::parse(&data).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn truncated_images_are_rejected()
+    {
+        let data = image(0, &[0; 4], 4);
+
+        assert_eq!(ElfFile::parse(&data[..ELF_HEADER_SIZE - 1]).unwrap_err(), LoaderError::Truncated("ELF header"));
+        assert_eq!(ElfFile::parse(&data[..ELF_HEADER_SIZE + 8]).unwrap_err(), LoaderError::Truncated("program header"));
+        assert_eq!(ElfFile::parse(&data[..data.len() - 1]).unwrap_err(),
+                   LoaderError::BadSegment(0, "segment data extends past the end of the file"));
+
+        let mut data = data;
+        data[ELF_HEADER_SIZE + 4..ELF_HEADER_SIZE + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(ElfFile::parse(&data).unwrap_err(), LoaderError::BadSegment(0, "segment data extends past the end of the file"));
+    }
+    #[test]
+    fn segments_larger_than_memory_are_rejected()
+    {
+        assert_eq!(ElfFile::parse(&image(0x200, &[1, 2, 3, 4], u32::MAX)).unwrap_err(),
+                   LoaderError::BadSegment(0, "segment extends past the end of the address space"));
+
+        let ram = Rc::new(RefCell::new(Ram512k::new()));
+        let mut memory = MotherboardMemory::new(vec![MemoryRegion::new("ram", 0, 0x80000, ram)]).unwrap();
+
+        assert_eq!(load_elf(&mut memory, &image(0x200, &[1, 2, 3, 4], 0xFFFFFE00)), Err(LoaderError::Unmapped(0x200, 0xFFFFFE00)));
+        assert_eq!(memory.read_byte(0x200), 0);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// Errors which can occur while loading a program image into memory
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoaderError
+{
+    /// The image ended before a structure could be fully read
+    Truncated(&'static str),
+    /// The image does not start with the ELF magic number
+    BadMagic,
+    /// The ELF file is not 32 bit (holds the EI_CLASS byte)
+    WrongClass(u8),
+    /// The ELF file is not little endian (holds the EI_DATA byte)
+    WrongEndianness(u8),
+    /// The ELF file is not built for RISC-V (holds the e_machine field)
+    WrongMachine(u16),
+    /// The ELF file is not an executable (holds the e_type field)
+    WrongType(u16),
+    /// A program header is malformed
+    BadSegment(usize, &'static str),
+    /// A line of a text image is malformed (holds the line number)
+    BadRecord(usize, &'static str),
+    /// The checksum of a line of a text image does not match (holds the line number, expected and found checksums)
+    BadChecksum(usize, u8, u8),
+    /// Part of the image lies outside of the memory map (holds the address and size of the data)
+    Unmapped(u32, u32)
+}
+
+impl fmt::Display for LoaderError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            LoaderError::Truncated(what) => write!(f, "Image truncated while reading {}", what),
+            LoaderError::BadMagic => write!(f, "Not an ELF file (bad magic number)"),
+            LoaderError::WrongClass(class) => write!(f, "Wrong ELF class {}, expected 32 bit (1)", class),
+            LoaderError::WrongEndianness(data) => write!(f, "Wrong ELF endianness {}, expected little endian (1)", data),
+            LoaderError::WrongMachine(machine) => write!(f, "Wrong ELF machine {}, expected RISC-V (243)", machine),
+            LoaderError::WrongType(kind) => write!(f, "Wrong ELF type {}, expected an executable (2) as shared objects and position independent executables (3) are not supported", kind),
+            LoaderError::BadSegment(index, reason) => write!(f, "Bad program header {}: {}", index, reason),
+            LoaderError::BadRecord(line, reason) => write!(f, "Line {}: {}", line, reason),
+            LoaderError::BadChecksum(line, expected, found) => write!(f, "Line {}: bad checksum 0x{:02X}, expected 0x{:02X}", line, found, expected),
+            LoaderError::Unmapped(addr, size) => write!(f, "0x{:X} bytes at 0x{:08X} lie outside of the memory map", size, addr)
+        }
+    }
+}
+
+impl std::error::Error for LoaderError {}
\ No newline at end of file
@@ -0,0 +1,156 @@
+use super::super::MemoryAccess;
+use super::LoaderError;
+use super::binary::check_mapped;
+
+/// Decode a string of hex digit pairs into bytes
+pub(super) fn parse_hex_bytes(line: usize, text: &str) -> Result<Vec<u8>, LoaderError>
+{
+    if !text.len().is_multiple_of(2)
+    {
+        return Err(LoaderError::BadRecord(line, "odd number of hex digits"));
+    }
+
+    (0..text.len()).step_by(2)
+        .map(|i| text.get(i..i + 2)
+                     .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                     .ok_or(LoaderError::BadRecord(line, "invalid hex digit")))
+        .collect()
+}
+
+/// Load an Intel HEX image into memory, offsetting every record by the base address, returning the start address if the image has one, offset the same way
+pub fn load_ihex<M: MemoryAccess + ?Sized>(memory: &mut M, base: u32, text: &str) -> Result<Option<u32>, LoaderError>
+{
+    let mut upper_address: u32 = 0;
+    let mut start = None;
+
+    for (i, raw_line) in text.lines().enumerate()
+    {
+        let line = i + 1;
+        let record_text = raw_line.trim();
+
+        if record_text.is_empty()
+        {
+            continue;
+        }
+
+        let record_text = match record_text.strip_prefix(':')
+        {
+            Some(rest) => rest,
+            None => return Err(LoaderError::BadRecord(line, "record does not start with ':'"))
+        };
+
+        let record = parse_hex_bytes(line, record_text)?;
+
+        if record.len() < 5 || record.len() != record[0] as usize + 5
+        {
+            return Err(LoaderError::BadRecord(line, "record length does not match its byte count"));
+        }
+
+        // The checksum is the two's complement of the sum of every other byte
+        let (body, checksum) = record.split_at(record.len() - 1);
+        let expected = body.iter().fold(0u8, |acc, v| acc.wrapping_add(*v)).wrapping_neg();
+
+        if expected != checksum[0]
+        {
+            return Err(LoaderError::BadChecksum(line, expected, checksum[0]));
+        }
+
+        let offset = ((record[1] as u32) << 8) | (record[2] as u32);
+        let data = &body[4..];
+
+        match record[3]
+        {
+            // Data
+            0x00 =>
+            {
+                let addr = base.wrapping_add(upper_address).wrapping_add(offset);
+                check_mapped(memory, addr, data.len())?;
+
+                for (j, val) in data.iter().enumerate()
+                {
+                    memory.write_byte(addr.wrapping_add(j as u32), *val);
+                }
+            },
+            // End of file
+            0x01 => break,
+            // Extended segment address
+            0x02 if data.len() == 2 => upper_address = (((data[0] as u32) << 8) | (data[1] as u32)) << 4,
+            // Start segment address (CS:IP)
+            0x03 if data.len() == 4 =>
+            {
+                let cs = ((data[0] as u32) << 8) | (data[1] as u32);
+                let ip = ((data[2] as u32) << 8) | (data[3] as u32);
+                start = Some(base.wrapping_add(cs << 4).wrapping_add(ip));
+            },
+            // Extended linear address
+            0x04 if data.len() == 2 => upper_address = (((data[0] as u32) << 8) | (data[1] as u32)) << 16,
+            // Start linear address
+            0x05 if data.len() == 4 =>
+            {
+                start = Some(base.wrapping_add(((data[0] as u32) << 24) | ((data[1] as u32) << 16) | ((data[2] as u32) << 8) | (data[3] as u32)));
+            },
+            0x02..=0x05 => return Err(LoaderError::BadRecord(line, "wrong data length for record type")),
+            _ => return Err(LoaderError::BadRecord(line, "unknown record type"))
+        }
+    }
+
+    Ok(start)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use super::super::super::{MotherboardMemory, MemoryRegion, Ram512k};
+
+    #[test]
+    fn start_address_is_offset_like_the_data()
+    {
+        let mut memory = Box::new(Ram512k::new());
+        let start = load_ihex(memory.as_mut(), 0x100, ":0400000001020304F2\n:0400000500000010E7\n:00000001FF\n").unwrap();
+
+        assert_eq!(memory.read_byte(0x100), 0x01);
+        assert_eq!(memory.read_byte(0x103), 0x04);
+        assert_eq!(start, Some(0x110));
+    }
+
+    #[test]
+    fn extended_addresses_are_offset_by_the_base()
+    {
+        let mut memory = Box::new(Ram512k::new());
+        load_ihex(memory.as_mut(), 0x100, ":020000021000EC\n:0200100055AAEF\n:020000040002F8\n:0200100066BBCD\n:00000001FF\n").unwrap();
+
+        assert_eq!((memory.read_byte(0x10110), memory.read_byte(0x10111)), (0x55, 0xAA));
+        assert_eq!((memory.read_byte(0x20110), memory.read_byte(0x20111)), (0x66, 0xBB));
+    }
+
+    #[test]
+    fn malformed_records_are_rejected()
+    {
+        let cases =
+        [
+            (":0400000001020304F3", LoaderError::BadChecksum(1, 0xF2, 0xF3)),
+            (":0500000001020304F2", LoaderError::BadRecord(1, "record length does not match its byte count")),
+            (":00000006FA", LoaderError::BadRecord(1, "unknown record type")),
+            (":04000000010203G4F2", LoaderError::BadRecord(1, "invalid hex digit")),
+            ("0400000001020304F2", LoaderError::BadRecord(1, "record does not start with ':'"))
+        ];
+
+        for (text, error) in cases
+        {
+            let mut memory = Box::new(Ram512k::new());
+            assert_eq!(load_ihex(memory.as_mut(), 0, text), Err(error), "{}", text);
+        }
+    }
+    #[test]
+    fn data_outside_of_the_map_is_rejected()
+    {
+        let ram = Rc::new(RefCell::new(Ram512k::new()));
+        let mut memory = MotherboardMemory::new(vec![MemoryRegion::new("ram", 0, 0x80000, ram)]).unwrap();
+
+        assert_eq!(load_ihex(&mut memory, 0x40000000, ":0400000001020304F2\n:00000001FF\n"), Err(LoaderError::Unmapped(0x40000000, 4)));
+    }
+}
\ No newline at end of file
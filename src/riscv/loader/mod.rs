@@ -0,0 +1,11 @@
+pub mod binary;
+pub mod elf;
+pub mod error;
+pub mod ihex;
+pub mod srec;
+
+pub use binary::*;
+pub use elf::*;
+pub use error::*;
+pub use ihex::*;
+pub use srec::*;
\ No newline at end of file
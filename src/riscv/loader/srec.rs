@@ -0,0 +1,148 @@
+use super::super::MemoryAccess;
+use super::LoaderError;
+use super::binary::check_mapped;
+use super::ihex::parse_hex_bytes;
+
+/// Load a Motorola S-record image into memory, offsetting every record by the base address, returning the start address if the image has one, offset the same way
+pub fn load_srec<M: MemoryAccess + ?Sized>(memory: &mut M, base: u32, text: &str) -> Result<Option<u32>, LoaderError>
+{
+    let mut data_records: u32 = 0;
+    let mut start = None;
+
+    for (i, raw_line) in text.lines().enumerate()
+    {
+        let line = i + 1;
+        let record_text = raw_line.trim();
+
+        if record_text.is_empty()
+        {
+            continue;
+        }
+
+        let mut chars = record_text.chars();
+
+        if chars.next() != Some('S')
+        {
+            return Err(LoaderError::BadRecord(line, "record does not start with 'S'"));
+        }
+
+        let kind = match chars.next().and_then(|c| c.to_digit(10))
+        {
+            Some(kind) => kind,
+            None => return Err(LoaderError::BadRecord(line, "invalid record type"))
+        };
+
+        let record = parse_hex_bytes(line, &record_text[2..])?;
+
+        if record.len() < 2 || record.len() != record[0] as usize + 1
+        {
+            return Err(LoaderError::BadRecord(line, "record length does not match its byte count"));
+        }
+
+        // The checksum is the one's complement of the sum of the count, address and data bytes
+        let (body, checksum) = record.split_at(record.len() - 1);
+        let expected = !body.iter().fold(0u8, |acc, v| acc.wrapping_add(*v));
+
+        if expected != checksum[0]
+        {
+            return Err(LoaderError::BadChecksum(line, expected, checksum[0]));
+        }
+
+        let address_size = match kind
+        {
+            0 | 1 | 5 | 9 => 2,
+            2 | 6 | 8 => 3,
+            3 | 7 => 4,
+            _ => return Err(LoaderError::BadRecord(line, "unknown record type"))
+        };
+
+        if body.len() < 1 + address_size
+        {
+            return Err(LoaderError::BadRecord(line, "record is too short for its address"));
+        }
+
+        let address = body[1..1 + address_size].iter().fold(0u32, |acc, v| (acc << 8) | (*v as u32));
+        let data = &body[1 + address_size..];
+
+        match kind
+        {
+            // Header
+            0 => {},
+            // Data
+            1..=3 =>
+            {
+                let addr = base.wrapping_add(address);
+                check_mapped(memory, addr, data.len())?;
+
+                for (j, val) in data.iter().enumerate()
+                {
+                    memory.write_byte(addr.wrapping_add(j as u32), *val);
+                }
+
+                data_records += 1;
+            },
+            // Record count
+            5 | 6 =>
+            {
+                if address != data_records
+                {
+                    return Err(LoaderError::BadRecord(line, "record count does not match the number of data records"));
+                }
+            },
+            // Start address (terminates the image)
+            _ =>
+            {
+                start = Some(base.wrapping_add(address));
+                break;
+            }
+        }
+    }
+
+    Ok(start)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use super::super::super::Ram512k;
+
+    #[test]
+    fn start_address_is_offset_like_the_data()
+    {
+        let mut memory = Box::new(Ram512k::new());
+        let start = load_srec(memory.as_mut(), 0x100, "S107000001020304EE\nS9030010EC\n").unwrap();
+
+        assert_eq!(memory.read_byte(0x100), 0x01);
+        assert_eq!(memory.read_byte(0x103), 0x04);
+        assert_eq!(start, Some(0x110));
+    }
+    #[test]
+    fn longer_addresses_are_offset_by_the_base()
+    {
+        let mut memory = Box::new(Ram512k::new());
+        load_srec(memory.as_mut(), 0x100, "S20601001055AAE9\nS3070002001066BBC5\n").unwrap();
+
+        assert_eq!((memory.read_byte(0x10110), memory.read_byte(0x10111)), (0x55, 0xAA));
+        assert_eq!((memory.read_byte(0x20110), memory.read_byte(0x20111)), (0x66, 0xBB));
+    }
+
+    #[test]
+    fn malformed_records_are_rejected()
+    {
+        let cases =
+        [
+            ("S107000001020304EF", LoaderError::BadChecksum(1, 0xEE, 0xEF)),
+            ("S108000001020304EE", LoaderError::BadRecord(1, "record length does not match its byte count")),
+            ("S4030000FC", LoaderError::BadRecord(1, "unknown record type")),
+            ("S10700000102030GEE", LoaderError::BadRecord(1, "invalid hex digit")),
+            ("X107000001020304EE", LoaderError::BadRecord(1, "record does not start with 'S'"))
+        ];
+
+        for (text, error) in cases
+        {
+            let mut memory = Box::new(Ram512k::new());
+            assert_eq!(load_srec(memory.as_mut(), 0, text), Err(error), "{}", text);
+        }
+    }
+}
\ No newline at end of file
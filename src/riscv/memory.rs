@@ -1,3 +1,11 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use super::{Clint, CLINT_BASE, CLINT_SIZE};
+use super::{Plic, PLIC_BASE, PLIC_SIZE};
+use super::{Device, AccessWidth};
+
 /// Trait for memory access (by individual bytes)
 pub trait MemoryAccess
 {
@@ -6,6 +14,24 @@ pub trait MemoryAccess
 
     /// Write a byte to memory
     fn write_byte(&mut self, addr: u32, data: u8);
+
+    /// Read a byte without any side effects, for inspecting memory (reading memory never has any, unlike a peripheral)
+    fn peek_byte(&self, addr: u32) -> u8
+    {
+        self.read_byte(addr)
+    }
+
+    /// Write a byte from outside of the running board, as a programmer filling a chip before power on (reaches read only memory)
+    fn program_byte(&mut self, addr: u32, data: u8)
+    {
+        self.write_byte(addr, data);
+    }
+
+    /// Check if every byte of an access is decoded to something (memory with no gaps always is)
+    fn is_mapped(&self, _addr: u32, _size: u32) -> bool
+    {
+        true
+    }
 }
 
 /// Trait for memory access (by individual 16 bits at a time)
@@ -119,24 +145,299 @@ impl MemoryAccess for Rom1m
     {
         
     }
+
+    fn program_byte(&mut self, addr: u32, data: u8)
+    {
+        self.memory[(addr & 0xFFFFF) as usize] = data;
+    }
+}
+
+/// Memory seen through a programmer, so an image written to it is programmed into read only memory as well
+pub struct MemoryProgrammer<'a, M: MemoryAccess + ?Sized>
+{
+    memory: &'a mut M
+}
+
+impl<'a, M: MemoryAccess + ?Sized> MemoryProgrammer<'a, M>
+{
+    /// Generate a new MemoryProgrammer writing to memory
+    pub fn new(memory: &'a mut M) -> Self
+    {
+        Self
+        {
+            memory
+        }
+    }
+}
+
+impl<M: MemoryAccess + ?Sized> MemoryAccess for MemoryProgrammer<'_, M>
+{
+    fn read_byte(&self, addr: u32) -> u8
+    {
+        self.memory.read_byte(addr)
+    }
+
+    fn write_byte(&mut self, addr: u32, data: u8)
+    {
+        self.memory.program_byte(addr, data);
+    }
+
+    fn is_mapped(&self, addr: u32, size: u32) -> bool
+    {
+        self.memory.is_mapped(addr, size)
+    }
+}
+
+/// Kind of device which can be placed in the memory map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegionKind
+{
+    Ram512k,
+    Ram1m,
+    Rom1m,
+    Clint,
+    Plic
 }
 
-/// Motherboard Memory Mapper
+impl RegionKind
+{
+    /// Parse a device name given in a memory map
+    pub fn from_name(name: &str) -> Option<Self>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "ram512k" => Some(RegionKind::Ram512k),
+            "ram1m" => Some(RegionKind::Ram1m),
+            "rom1m" => Some(RegionKind::Rom1m),
+            "clint" => Some(RegionKind::Clint),
+            "plic" => Some(RegionKind::Plic),
+            _ => None
+        }
+    }
+
+    /// Get the size of the address range the device takes
+    pub fn size(&self) -> u32
+    {
+        match self
+        {
+            RegionKind::Ram512k => 0x80000,
+            RegionKind::Ram1m | RegionKind::Rom1m => 0x100000,
+            RegionKind::Clint => CLINT_SIZE,
+            RegionKind::Plic => PLIC_SIZE
+        }
+    }
+}
+
+/// Memory map of the board before any is configured, two 512k ram chips at the bottom of memory and the interrupt controllers
+pub const DEFAULT_MEMORY_MAP: [(RegionKind, u32); 4] =
+[
+    (RegionKind::Ram512k, 0x00000),
+    (RegionKind::Ram512k, 0x80000),
+    (RegionKind::Clint, CLINT_BASE),
+    (RegionKind::Plic, PLIC_BASE)
+];
+
+/// Error generated while building a memory map
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoryMapError
+{
+    /// A region runs past the end of the address space (holds its name and base)
+    OutOfRange(String, u32),
+    /// Two regions share addresses (holds their names and bases)
+    Overlap(String, u32, String, u32)
+}
+
+impl fmt::Display for MemoryMapError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            MemoryMapError::OutOfRange(name, base) => write!(f, "Region {} at 0x{:08X} runs past the end of the address space", name, base),
+            MemoryMapError::Overlap(first, first_base, second, second_base) =>
+                write!(f, "Region {} at 0x{:08X} overlaps region {} at 0x{:08X}", first, first_base, second, second_base)
+        }
+    }
+}
+
+impl std::error::Error for MemoryMapError {}
+
+/// What the addresses of a region are decoded to
+#[derive(Clone)]
+pub enum RegionTarget
+{
+    /// Memory, accessed a byte at a time
+    Memory(Rc<RefCell<dyn MemoryAccess>>),
+    /// A peripheral, which sees each access whole when it falls inside the region
+    Device(Rc<RefCell<dyn Device>>)
+}
+
+/// A range of addresses decoded to a device, which is given the offset into the range
+pub struct MemoryRegion
+{
+    name: String,
+    base: u32,
+    size: u32,
+    target: RegionTarget
+}
+
+impl MemoryRegion
+{
+    /// Generate a new MemoryRegion covering size bytes from base
+    pub fn new(name: &str, base: u32, size: u32, memory: Rc<RefCell<dyn MemoryAccess>>) -> Self
+    {
+        Self
+        {
+            name: name.to_string(),
+            base,
+            size,
+            target: RegionTarget::Memory(memory)
+        }
+    }
+
+    /// Generate a new MemoryRegion covering size bytes from base, decoded to a peripheral
+    pub fn device(name: &str, base: u32, size: u32, device: Rc<RefCell<dyn Device>>) -> Self
+    {
+        Self
+        {
+            name: name.to_string(),
+            base,
+            size,
+            target: RegionTarget::Device(device)
+        }
+    }
+
+    /// Check if the region decodes an address
+    fn contains(&self, addr: u32) -> bool
+    {
+        addr.wrapping_sub(self.base) < self.size
+    }
+}
+
+/// Motherboard Memory Mapper (decodes addresses to the regions of the memory map, nothing is decoded outside of them)
 pub struct MotherboardMemory
 {
-    ram0: Ram512k,
-    ram1: Ram512k
+    regions: Vec<MemoryRegion>
 }
 
 impl MotherboardMemory
 {
-    /// Generate a new MotherboardMemory
-    pub fn new() -> Self
+    /// Generate a new MotherboardMemory from its regions, which must fit in the address space without overlapping
+    pub fn new(regions: Vec<MemoryRegion>) -> Result<Self, MemoryMapError>
     {
-        Self
+        let mut memory = Self
+        {
+            regions: Vec::new()
+        };
+
+        for region in regions
+        {
+            memory.add_region(region)?;
+        }
+
+        Ok(memory)
+    }
+
+    /// Add a region to the memory map, which must fit in the address space without overlapping the regions already there
+    pub fn add_region(&mut self, region: MemoryRegion) -> Result<(), MemoryMapError>
+    {
+        if region.size == 0 || region.base.checked_add(region.size - 1).is_none()
+        {
+            return Err(MemoryMapError::OutOfRange(region.name.clone(), region.base));
+        }
+
+        // Either region containing the base of the other means they share addresses
+        if let Some(other) = self.regions.iter().find(|other| other.contains(region.base) || region.contains(other.base))
+        {
+            return Err(MemoryMapError::Overlap(other.name.clone(), other.base, region.name.clone(), region.base));
+        }
+
+        self.regions.push(region);
+
+        Ok(())
+    }
+
+    /// Generate a new MotherboardMemory from a memory map, with new memory chips and the given interrupt controllers
+    pub fn from_map(map: &[(RegionKind, u32)], clint: Rc<RefCell<Clint>>, plic: Rc<RefCell<Plic>>) -> Result<Self, MemoryMapError>
+    {
+        let regions = map.iter().map(|(kind, base)|
+        {
+            let name = format!("{:?}", kind).to_ascii_lowercase();
+
+            match kind
+            {
+                RegionKind::Ram512k => MemoryRegion::new(&name, *base, kind.size(), Rc::new(RefCell::new(Ram512k::new()))),
+                RegionKind::Ram1m => MemoryRegion::new(&name, *base, kind.size(), Rc::new(RefCell::new(Ram1m::new()))),
+                RegionKind::Rom1m => MemoryRegion::new(&name, *base, kind.size(), Rc::new(RefCell::new(Rom1m::new()))),
+                RegionKind::Clint => MemoryRegion::device(&name, *base, kind.size(), clint.clone()),
+                RegionKind::Plic => MemoryRegion::device(&name, *base, kind.size(), plic.clone())
+            }
+        }).collect();
+
+        Self::new(regions)
+    }
+
+    /// Find the region decoding an address
+    fn region(&self, addr: u32) -> Option<&MemoryRegion>
+    {
+        self.regions.iter().find(|region| region.contains(addr))
+    }
+
+    /// Find the peripheral an access of the given width goes to whole, with the offset of the access into it
+    fn device_access(&self, addr: u32, width: AccessWidth) -> Option<(&Rc<RefCell<dyn Device>>, u32)>
+    {
+        // An access running off the end of the region is split into bytes instead
+        match self.region(addr)
+        {
+            Some(region) if region.contains(addr.wrapping_add(width.bytes() - 1)) => match &region.target
+            {
+                RegionTarget::Device(device) => Some((device, addr - region.base)),
+                RegionTarget::Memory(_) => None
+            },
+            _ => None
+        }
+    }
+
+    /// Get the peripherals in the memory map
+    fn devices(&self) -> impl Iterator<Item = &Rc<RefCell<dyn Device>>>
+    {
+        self.regions.iter().filter_map(|region| match &region.target
+        {
+            RegionTarget::Device(device) => Some(device),
+            RegionTarget::Memory(_) => None
+        })
+    }
+
+    /// Advance the peripherals by one clock cycle
+    pub fn tick(&mut self)
+    {
+        for device in self.devices()
         {
-            ram0: Ram512k::new(),
-            ram1: Ram512k::new()
+            device.borrow_mut().tick();
+        }
+    }
+
+    /// Advance the peripherals by a number of clock cycles at once
+    pub fn advance(&mut self, clocks: u64)
+    {
+        for device in self.devices()
+        {
+            device.borrow_mut().advance(clocks);
+        }
+    }
+
+    /// Get the number of clock cycles until a peripheral next changes on its own (None if none will)
+    pub fn clocks_until_event(&self) -> Option<u64>
+    {
+        self.devices().filter_map(|device| device.borrow().clocks_until_event()).min()
+    }
+
+    /// Return the peripherals to their power on state (memory keeps its contents)
+    pub fn reset_devices(&mut self)
+    {
+        for device in self.devices()
+        {
+            device.borrow_mut().reset();
         }
     }
 }
@@ -145,26 +446,76 @@ impl MemoryAccess for MotherboardMemory
 {
     fn read_byte(&self, addr: u32) -> u8
     {
-        if addr & 0x80000 > 0
+        // Unmapped addresses read as zero (the cpu raises an access fault before getting here)
+        match self.region(addr)
         {
-            self.ram1.read_byte(addr)
+            Some(region) => match &region.target
+            {
+                RegionTarget::Memory(memory) => memory.borrow().read_byte(addr - region.base),
+                RegionTarget::Device(device) => device.borrow_mut().read(addr - region.base, AccessWidth::Byte) as u8
+            },
+            None => 0
         }
-        else
+    }
+
+    fn peek_byte(&self, addr: u32) -> u8
+    {
+        match self.region(addr)
         {
-            self.ram0.read_byte(addr)
+            Some(region) => match &region.target
+            {
+                RegionTarget::Memory(memory) => memory.borrow().peek_byte(addr - region.base),
+                RegionTarget::Device(device) => device.borrow().peek(addr - region.base, AccessWidth::Byte) as u8
+            },
+            None => 0
         }
     }
 
     fn write_byte(&mut self, addr: u32, data: u8)
     {
-        if addr & 0x80000 > 0
+        if let Some(region) = self.region(addr)
         {
-            self.ram1.write_byte(addr, data)
+            match &region.target
+            {
+                RegionTarget::Memory(memory) => memory.borrow_mut().write_byte(addr - region.base, data),
+                RegionTarget::Device(device) => device.borrow_mut().write(addr - region.base, AccessWidth::Byte, data as u32)
+            }
         }
-        else
+    }
+
+    fn program_byte(&mut self, addr: u32, data: u8)
+    {
+        if let Some(region) = self.region(addr)
+        {
+            match &region.target
+            {
+                RegionTarget::Memory(memory) => memory.borrow_mut().program_byte(addr - region.base, data),
+                RegionTarget::Device(device) => device.borrow_mut().write(addr - region.base, AccessWidth::Byte, data as u32)
+            }
+        }
+    }
+
+    fn is_mapped(&self, addr: u32, size: u32) -> bool
+    {
+        // Step over whole regions rather than single bytes, as an image being loaded can be as large as the address space
+        let mut addr = addr;
+        let mut remaining = size;
+
+        while remaining > 0
         {
-            self.ram0.write_byte(addr, data)
+            match self.region(addr)
+            {
+                Some(region) =>
+                {
+                    let covered = (region.size - (addr - region.base)).min(remaining);
+                    addr = addr.wrapping_add(covered);
+                    remaining -= covered;
+                },
+                None => return false
+            }
         }
+
+        true
     }
 }
 
@@ -172,11 +523,21 @@ impl MemoryAccess16 for MotherboardMemory
 {
     fn read_u16(&self, addr: u32) -> u16
     {
+        if let Some((device, offset)) = self.device_access(addr, AccessWidth::Half)
+        {
+            return device.borrow_mut().read(offset, AccessWidth::Half) as u16;
+        }
+
         (self.read_byte(addr) as u16) | ((self.read_byte(addr.wrapping_add(1)) as u16) << 8)
     }
 
     fn write_u16(&mut self, addr: u32, data: u16)
     {
+        if let Some((device, offset)) = self.device_access(addr, AccessWidth::Half)
+        {
+            return device.borrow_mut().write(offset, AccessWidth::Half, data as u32);
+        }
+
         self.write_byte(addr, (data & 0x000000FF) as u8);
         self.write_byte(addr.wrapping_add(1), ((data & 0x0000FF00) >> 8) as u8);
     }
@@ -186,6 +547,11 @@ impl MemoryAccess32 for MotherboardMemory
 {
     fn read_u32(&self, addr: u32) -> u32
     {
+        if let Some((device, offset)) = self.device_access(addr, AccessWidth::Word)
+        {
+            return device.borrow_mut().read(offset, AccessWidth::Word);
+        }
+
         (self.read_byte(addr) as u32) | ((self.read_byte(addr.wrapping_add(1)) as u32) << 8) |
          ((self.read_byte(addr.wrapping_add(2)) as u32) << 16) |
          ((self.read_byte(addr.wrapping_add(3)) as u32) << 24)
@@ -193,6 +559,11 @@ impl MemoryAccess32 for MotherboardMemory
 
     fn write_u32(&mut self, addr: u32, data: u32)
     {
+        if let Some((device, offset)) = self.device_access(addr, AccessWidth::Word)
+        {
+            return device.borrow_mut().write(offset, AccessWidth::Word, data);
+        }
+
         self.write_byte(addr, (data & 0x000000FF) as u8);
         self.write_byte(addr.wrapping_add(1), ((data & 0x0000FF00) >> 8) as u8);
         self.write_byte(addr.wrapping_add(2), ((data & 0x00FF0000) >> 16) as u8);
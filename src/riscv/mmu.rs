@@ -0,0 +1,156 @@
+use super::{MemoryAccess32, Pmp};
+use super::{Privilege, TrapCause};
+use super::SATP_PPN;
+
+/// Valid bit of a page table entry
+const PTE_V: u32 = 1 << 0;
+
+/// Readable bit of a page table entry
+const PTE_R: u32 = 1 << 1;
+
+/// Writable bit of a page table entry
+const PTE_W: u32 = 1 << 2;
+
+/// Executable bit of a page table entry
+const PTE_X: u32 = 1 << 3;
+
+/// User accessible bit of a page table entry
+const PTE_U: u32 = 1 << 4;
+
+/// Accessed bit of a page table entry
+const PTE_A: u32 = 1 << 6;
+
+/// Dirty bit of a page table entry
+const PTE_D: u32 = 1 << 7;
+
+/// Size of a page (and of a page table)
+pub const PAGE_SIZE: u32 = 0x1000;
+
+/// Kind of memory access being translated
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccessType
+{
+    Fetch,
+    Load,
+    Store
+}
+
+impl AccessType
+{
+    /// Get the page fault raised by a failed translation of this kind of access
+    pub fn page_fault(&self) -> TrapCause
+    {
+        match self
+        {
+            AccessType::Fetch => TrapCause::InstructionPageFault,
+            AccessType::Load => TrapCause::LoadPageFault,
+            AccessType::Store => TrapCause::StorePageFault
+        }
+    }
+
+    /// Get the access fault raised when PMP does not permit this kind of access
+    pub fn access_fault(&self) -> TrapCause
+    {
+        match self
+        {
+            AccessType::Fetch => TrapCause::InstructionAccessFault,
+            AccessType::Load => TrapCause::LoadAccessFault,
+            AccessType::Store => TrapCause::StoreAccessFault
+        }
+    }
+}
+
+/// Sv32 page table walker (there is no TLB, so every access walks the tables in memory)
+pub struct PageTableWalker<'a>
+{
+    /// Value of satp holding the root page table
+    pub satp: u32,
+    /// Privilege the access is made at
+    pub privilege: Privilege,
+    /// Supervisor mode may load and store to user pages (mstatus.SUM)
+    pub sum: bool,
+    /// Loads may read executable pages (mstatus.MXR)
+    pub mxr: bool,
+    /// PMP the page table accesses are checked against
+    pub pmp: &'a Pmp
+}
+
+impl PageTableWalker<'_>
+{
+    /// Translate a virtual address to a physical address, setting the accessed and dirty bits of the leaf entry
+    pub fn translate(&self, memory: &mut dyn MemoryAccess32, addr: u32, access: AccessType) -> Result<u32, TrapCause>
+    {
+        let fault = Err(access.page_fault());
+
+        // Physical page numbers are 22 bits, the bits above the 32 bit address bus are dropped
+        let mut table = (self.satp & SATP_PPN) << 12;
+
+        for level in (0..2).rev()
+        {
+            let vpn = (addr >> (12 + 10 * level)) & 0x3FF;
+            let pte_addr = table.wrapping_add(4 * vpn);
+
+            // Page table accesses are made at supervisor level, a PMP violation or unmapped table raises the access fault of the original access
+            if !self.pmp.check(pte_addr, 4, AccessType::Load, Privilege::Supervisor) || !memory.is_mapped(pte_addr, 4)
+            {
+                return Err(access.access_fault());
+            }
+
+            let pte = memory.read_u32(pte_addr);
+
+            // Invalid, or writable without being readable (reserved)
+            if pte & PTE_V == 0 || (pte & PTE_R == 0 && pte & PTE_W != 0)
+            {
+                return fault;
+            }
+
+            // Neither readable nor executable points to the next level of the table
+            if pte & (PTE_R | PTE_X) == 0
+            {
+                table = (pte >> 10) << 12;
+                continue;
+            }
+
+            let permitted = match access
+            {
+                AccessType::Fetch => pte & PTE_X != 0,
+                AccessType::Load => pte & PTE_R != 0 || (self.mxr && pte & PTE_X != 0),
+                AccessType::Store => pte & PTE_W != 0
+            };
+
+            // User mode may only use user pages, supervisor mode may never execute them and only loads and stores with SUM set
+            let privileged = match self.privilege
+            {
+                Privilege::User => pte & PTE_U != 0,
+                _ => pte & PTE_U == 0 || (self.sum && access != AccessType::Fetch)
+            };
+
+            // A megapage must be aligned to 4MiB
+            let misaligned = level == 1 && (pte >> 10) & 0x3FF != 0;
+
+            if !permitted || !privileged || misaligned
+            {
+                return fault;
+            }
+
+            let updated = pte | PTE_A | if access == AccessType::Store {PTE_D} else {0};
+
+            if updated != pte
+            {
+                if !self.pmp.check(pte_addr, 4, AccessType::Store, Privilege::Supervisor)
+                {
+                    return Err(access.access_fault());
+                }
+
+                memory.write_u32(pte_addr, updated);
+            }
+
+            let offset_mask = (1 << (12 + 10 * level)) - 1;
+
+            return Ok((((pte >> 10) << 12) & !offset_mask) | (addr & offset_mask));
+        }
+
+        // The last level entry was a pointer
+        fault
+    }
+}
\ No newline at end of file
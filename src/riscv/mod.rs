@@ -1,15 +1,42 @@
 pub mod alu;
+pub mod assembler;
 pub mod csr;
 pub mod bus;
 pub mod chip;
+pub mod clint;
+pub mod compressed;
+pub mod counters;
+pub mod device;
+pub mod disassembler;
+pub mod encoder;
 pub mod instruction;
+pub mod loader;
 pub mod memory;
+pub mod mmu;
+pub mod operation;
+pub mod plic;
+pub mod pmp;
 pub mod register;
+pub mod timer;
+pub mod trap;
 
 pub use alu::*;
+pub use assembler::*;
 pub use csr::*;
 pub use bus::*;
 pub use chip::*;
+pub use clint::*;
+pub use compressed::*;
+pub use counters::*;
+pub use device::*;
+pub use disassembler::*;
 pub use instruction::*;
+pub use loader::*;
 pub use memory::*;
-pub use register::*;
\ No newline at end of file
+pub use mmu::*;
+pub use operation::*;
+pub use plic::*;
+pub use pmp::*;
+pub use register::*;
+pub use timer::*;
+pub use trap::*;
\ No newline at end of file
@@ -0,0 +1,453 @@
+/// Encoding format of an instruction (decides how the immediate is laid out)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InstructionFormat
+{
+    R,
+    I,
+    S,
+    B,
+    U,
+    J
+}
+
+impl InstructionFormat
+{
+    /// Get the format used by an opcode, if the opcode is known
+    pub fn from_opcode(opcode: u8) -> Option<Self>
+    {
+        match opcode
+        {
+            0b0110011 | 0b0101111 => Some(InstructionFormat::R),
+            0b0010011 | 0b1100111 | 0b0000011 | 0b1110011 | 0b0001111 => Some(InstructionFormat::I),
+            0b0100011 => Some(InstructionFormat::S),
+            0b1100011 => Some(InstructionFormat::B),
+            0b0110111 | 0b0010111 => Some(InstructionFormat::U),
+            0b1101111 => Some(InstructionFormat::J),
+            _ => None
+        }
+    }
+}
+
+/// Decoded operation of an instruction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation
+{
+    // Upper immediates
+    Lui,
+    Auipc,
+
+    // Jumps
+    Jal,
+    Jalr,
+
+    // Branches
+    Beq,
+    Bne,
+    Blt,
+    Bge,
+    Bltu,
+    Bgeu,
+
+    // Loads
+    Lb,
+    Lh,
+    Lw,
+    Lbu,
+    Lhu,
+
+    // Stores
+    Sb,
+    Sh,
+    Sw,
+
+    // Arithmatic Immediate
+    Addi,
+    Slti,
+    Sltiu,
+    Xori,
+    Ori,
+    Andi,
+    Slli,
+    Srli,
+    Srai,
+
+    // Arithmatic Register
+    Add,
+    Sub,
+    Sll,
+    Slt,
+    Sltu,
+    Xor,
+    Srl,
+    Sra,
+    Or,
+    And,
+
+    // RV32M
+    Mul,
+    Mulh,
+    Mulhsu,
+    Mulhu,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+
+    // RV32A
+    LrW,
+    ScW,
+    AmoswapW,
+    AmoaddW,
+    AmoxorW,
+    AmoandW,
+    AmoorW,
+    AmominW,
+    AmomaxW,
+    AmominuW,
+    AmomaxuW,
+
+    // Memory ordering (Fence.i is from Zifencei)
+    Fence,
+    FenceTso,
+    FenceI,
+
+    // System
+    Ecall,
+    Ebreak,
+    Sret,
+    Mret,
+    Wfi,
+    SfenceVma,
+
+    // Zicsr
+    Csrrw,
+    Csrrs,
+    Csrrc,
+    Csrrwi,
+    Csrrsi,
+    Csrrci,
+
+    /// Any encoding which is not a defined instruction
+    Illegal
+}
+
+impl Operation
+{
+    /// Decode the operation from the fields of an instruction (funct12 is the upper 12 bits of the instruction)
+    pub fn decode(opcode: u8, funct3: u8, funct7: u8, rs1: u8, rs2: u8, rd: u8, funct12: u32) -> Self
+    {
+        match (opcode, funct3)
+        {
+            (0b0110111, _) => Operation::Lui,
+            (0b0010111, _) => Operation::Auipc,
+            (0b1101111, _) => Operation::Jal,
+            (0b1100111, 0b000) => Operation::Jalr,
+
+            (0b1100011, 0b000) => Operation::Beq,
+            (0b1100011, 0b001) => Operation::Bne,
+            (0b1100011, 0b100) => Operation::Blt,
+            (0b1100011, 0b101) => Operation::Bge,
+            (0b1100011, 0b110) => Operation::Bltu,
+            (0b1100011, 0b111) => Operation::Bgeu,
+
+            (0b0000011, 0b000) => Operation::Lb,
+            (0b0000011, 0b001) => Operation::Lh,
+            (0b0000011, 0b010) => Operation::Lw,
+            (0b0000011, 0b100) => Operation::Lbu,
+            (0b0000011, 0b101) => Operation::Lhu,
+
+            (0b0100011, 0b000) => Operation::Sb,
+            (0b0100011, 0b001) => Operation::Sh,
+            (0b0100011, 0b010) => Operation::Sw,
+
+            (0b0010011, 0b000) => Operation::Addi,
+            (0b0010011, 0b010) => Operation::Slti,
+            (0b0010011, 0b011) => Operation::Sltiu,
+            (0b0010011, 0b100) => Operation::Xori,
+            (0b0010011, 0b110) => Operation::Ori,
+            (0b0010011, 0b111) => Operation::Andi,
+            // Shift amounts above 31 and any other funct7 are reserved
+            (0b0010011, 0b001) if funct7 == 0b0000000 => Operation::Slli,
+            (0b0010011, 0b101) if funct7 == 0b0000000 => Operation::Srli,
+            (0b0010011, 0b101) if funct7 == 0b0100000 => Operation::Srai,
+
+            (0b0110011, _) => match (funct3, funct7)
+            {
+                (0b000, 0b0000000) => Operation::Add,
+                (0b000, 0b0100000) => Operation::Sub,
+                (0b001, 0b0000000) => Operation::Sll,
+                (0b010, 0b0000000) => Operation::Slt,
+                (0b011, 0b0000000) => Operation::Sltu,
+                (0b100, 0b0000000) => Operation::Xor,
+                (0b101, 0b0000000) => Operation::Srl,
+                (0b101, 0b0100000) => Operation::Sra,
+                (0b110, 0b0000000) => Operation::Or,
+                (0b111, 0b0000000) => Operation::And,
+                (0b000, 0b0000001) => Operation::Mul,
+                (0b001, 0b0000001) => Operation::Mulh,
+                (0b010, 0b0000001) => Operation::Mulhsu,
+                (0b011, 0b0000001) => Operation::Mulhu,
+                (0b100, 0b0000001) => Operation::Div,
+                (0b101, 0b0000001) => Operation::Divu,
+                (0b110, 0b0000001) => Operation::Rem,
+                (0b111, 0b0000001) => Operation::Remu,
+                _ => Operation::Illegal
+            },
+
+            // The lower two bits of funct7 are the aq and rl ordering bits
+            (0b0101111, 0b010) => match funct7 >> 2
+            {
+                0b00010 if rs2 == 0 => Operation::LrW,
+                0b00011 => Operation::ScW,
+                0b00001 => Operation::AmoswapW,
+                0b00000 => Operation::AmoaddW,
+                0b00100 => Operation::AmoxorW,
+                0b01100 => Operation::AmoandW,
+                0b01000 => Operation::AmoorW,
+                0b10000 => Operation::AmominW,
+                0b10100 => Operation::AmomaxW,
+                0b11000 => Operation::AmominuW,
+                0b11100 => Operation::AmomaxuW,
+                _ => Operation::Illegal
+            },
+
+            // The unused fields of the fences are ignored rather than reserved
+            (0b0001111, 0b000) if funct12 == 0x833 => Operation::FenceTso,
+            (0b0001111, 0b000) => Operation::Fence,
+            (0b0001111, 0b001) => Operation::FenceI,
+
+            (0b1110011, 0b000) if funct7 == 0b0001001 && rd == 0 => Operation::SfenceVma,
+            (0b1110011, 0b000) if rs1 == 0 && rd == 0 => match funct12
+            {
+                0x000 => Operation::Ecall,
+                0x001 => Operation::Ebreak,
+                0x102 => Operation::Sret,
+                0x302 => Operation::Mret,
+                0x105 => Operation::Wfi,
+                _ => Operation::Illegal
+            },
+            (0b1110011, 0b001) => Operation::Csrrw,
+            (0b1110011, 0b010) => Operation::Csrrs,
+            (0b1110011, 0b011) => Operation::Csrrc,
+            (0b1110011, 0b101) => Operation::Csrrwi,
+            (0b1110011, 0b110) => Operation::Csrrsi,
+            (0b1110011, 0b111) => Operation::Csrrci,
+
+            _ => Operation::Illegal
+        }
+    }
+
+    /// Get the assembly mnemonic of the operation
+    pub fn mnemonic(&self) -> &'static str
+    {
+        match self
+        {
+            Operation::Lui => "lui",
+            Operation::Auipc => "auipc",
+            Operation::Jal => "jal",
+            Operation::Jalr => "jalr",
+            Operation::Beq => "beq",
+            Operation::Bne => "bne",
+            Operation::Blt => "blt",
+            Operation::Bge => "bge",
+            Operation::Bltu => "bltu",
+            Operation::Bgeu => "bgeu",
+            Operation::Lb => "lb",
+            Operation::Lh => "lh",
+            Operation::Lw => "lw",
+            Operation::Lbu => "lbu",
+            Operation::Lhu => "lhu",
+            Operation::Sb => "sb",
+            Operation::Sh => "sh",
+            Operation::Sw => "sw",
+            Operation::Addi => "addi",
+            Operation::Slti => "slti",
+            Operation::Sltiu => "sltiu",
+            Operation::Xori => "xori",
+            Operation::Ori => "ori",
+            Operation::Andi => "andi",
+            Operation::Slli => "slli",
+            Operation::Srli => "srli",
+            Operation::Srai => "srai",
+            Operation::Add => "add",
+            Operation::Sub => "sub",
+            Operation::Sll => "sll",
+            Operation::Slt => "slt",
+            Operation::Sltu => "sltu",
+            Operation::Xor => "xor",
+            Operation::Srl => "srl",
+            Operation::Sra => "sra",
+            Operation::Or => "or",
+            Operation::And => "and",
+            Operation::Mul => "mul",
+            Operation::Mulh => "mulh",
+            Operation::Mulhsu => "mulhsu",
+            Operation::Mulhu => "mulhu",
+            Operation::Div => "div",
+            Operation::Divu => "divu",
+            Operation::Rem => "rem",
+            Operation::Remu => "remu",
+            Operation::LrW => "lr.w",
+            Operation::ScW => "sc.w",
+            Operation::AmoswapW => "amoswap.w",
+            Operation::AmoaddW => "amoadd.w",
+            Operation::AmoxorW => "amoxor.w",
+            Operation::AmoandW => "amoand.w",
+            Operation::AmoorW => "amoor.w",
+            Operation::AmominW => "amomin.w",
+            Operation::AmomaxW => "amomax.w",
+            Operation::AmominuW => "amominu.w",
+            Operation::AmomaxuW => "amomaxu.w",
+            Operation::Fence => "fence",
+            Operation::FenceTso => "fence.tso",
+            Operation::FenceI => "fence.i",
+            Operation::Ecall => "ecall",
+            Operation::Ebreak => "ebreak",
+            Operation::Sret => "sret",
+            Operation::Mret => "mret",
+            Operation::Wfi => "wfi",
+            Operation::SfenceVma => "sfence.vma",
+            Operation::Csrrw => "csrrw",
+            Operation::Csrrs => "csrrs",
+            Operation::Csrrc => "csrrc",
+            Operation::Csrrwi => "csrrwi",
+            Operation::Csrrsi => "csrrsi",
+            Operation::Csrrci => "csrrci",
+            Operation::Illegal => "illegal"
+        }
+    }
+
+    /// Get the ALU mode and sub flag which compute the result of an arithmatic operation
+    pub fn alu_mode(&self) -> Option<(usize, bool)>
+    {
+        match self
+        {
+            Operation::Add | Operation::Addi => Some((0b000, false)),
+            Operation::Sub => Some((0b000, true)),
+            Operation::Sll | Operation::Slli => Some((0b001, false)),
+            Operation::Slt | Operation::Slti => Some((0b010, false)),
+            Operation::Sltu | Operation::Sltiu => Some((0b011, false)),
+            Operation::Xor | Operation::Xori => Some((0b100, false)),
+            Operation::Srl | Operation::Srli => Some((0b101, false)),
+            Operation::Sra | Operation::Srai => Some((0b101, true)),
+            Operation::Or | Operation::Ori => Some((0b110, false)),
+            Operation::And | Operation::Andi => Some((0b111, false)),
+            Operation::Mul => Some((0b1000, false)),
+            Operation::Mulh => Some((0b1001, false)),
+            Operation::Mulhsu => Some((0b1010, false)),
+            Operation::Mulhu => Some((0b1011, false)),
+            Operation::Div => Some((0b1100, false)),
+            Operation::Divu => Some((0b1101, false)),
+            Operation::Rem => Some((0b1110, false)),
+            Operation::Remu => Some((0b1111, false)),
+            // Swap adds the source register to zero in place of the loaded value
+            Operation::AmoswapW | Operation::AmoaddW => Some((0b000, false)),
+            Operation::AmoxorW => Some((0b100, false)),
+            Operation::AmoandW => Some((0b111, false)),
+            Operation::AmoorW => Some((0b110, false)),
+            Operation::AmominW => Some((0b10000, false)),
+            Operation::AmomaxW => Some((0b10001, false)),
+            Operation::AmominuW => Some((0b10010, false)),
+            Operation::AmomaxuW => Some((0b10011, false)),
+            // Write adds the new value to zero, clear ands the old value with the inverted new value
+            Operation::Csrrw | Operation::Csrrwi => Some((0b000, false)),
+            Operation::Csrrs | Operation::Csrrsi => Some((0b110, false)),
+            Operation::Csrrc | Operation::Csrrci => Some((0b111, true)),
+            _ => None
+        }
+    }
+
+    /// Get the memory mode of a load or store operation (0b00: Byte, 0b01: Half Word, 0b10: Word, oring 0b100 makes the result unsigned)
+    pub fn memory_mode(&self) -> Option<u8>
+    {
+        match self
+        {
+            Operation::Lb | Operation::Sb => Some(0b000),
+            Operation::Lh | Operation::Sh => Some(0b001),
+            Operation::Lw | Operation::Sw | Operation::LrW | Operation::ScW | Operation::AmoswapW | Operation::AmoaddW |
+            Operation::AmoxorW | Operation::AmoandW | Operation::AmoorW | Operation::AmominW | Operation::AmomaxW |
+            Operation::AmominuW | Operation::AmomaxuW => Some(0b010),
+            Operation::Lbu => Some(0b100),
+            Operation::Lhu => Some(0b101),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Decode the operation of an instruction word, splitting it into its fields
+    fn decode(word: u32) -> Operation
+    {
+        Operation::decode((word & 0x7F) as u8, ((word >> 12) & 0x7) as u8, (word >> 25) as u8,
+                          ((word >> 15) & 0x1F) as u8, ((word >> 20) & 0x1F) as u8, ((word >> 7) & 0x1F) as u8, word >> 20)
+    }
+
+    #[test]
+    fn decodes_base_integer_operations()
+    {
+        assert_eq!(decode(0x001002B7), Operation::Lui);
+        assert_eq!(decode(0x00000417), Operation::Auipc);
+        assert_eq!(decode(0x0000006F), Operation::Jal);
+        assert_eq!(decode(0x00028067), Operation::Jalr);
+        assert_eq!(decode(0x00000063), Operation::Beq);
+        assert_eq!(decode(0x00007063), Operation::Bgeu);
+        assert_eq!(decode(0x0002A303), Operation::Lw);
+        assert_eq!(decode(0x0002C303), Operation::Lbu);
+        assert_eq!(decode(0x0062A023), Operation::Sw);
+        assert_eq!(decode(0xFFF00513), Operation::Addi);
+        assert_eq!(decode(0x01F51513), Operation::Slli);
+        assert_eq!(decode(0x40155513), Operation::Srai);
+        assert_eq!(decode(0x00B50533), Operation::Add);
+        assert_eq!(decode(0x40B50533), Operation::Sub);
+        assert_eq!(decode(0x40B55533), Operation::Sra);
+    }
+
+    #[test]
+    fn decodes_extension_operations()
+    {
+        assert_eq!(decode(0x02B50533), Operation::Mul);
+        assert_eq!(decode(0x02B57533), Operation::Remu);
+        assert_eq!(decode(0x1005A52F), Operation::LrW);
+        assert_eq!(decode(0x18C5A52F), Operation::ScW);
+        assert_eq!(decode(0x0EC5A52F), Operation::AmoswapW);
+        assert_eq!(decode(0xE0C5A52F), Operation::AmomaxuW);
+        assert_eq!(decode(0x34059573), Operation::Csrrw);
+        assert_eq!(decode(0x30047073), Operation::Csrrci);
+        assert_eq!(decode(0x0FF0000F), Operation::Fence);
+        assert_eq!(decode(0x8330000F), Operation::FenceTso);
+        assert_eq!(decode(0x0000100F), Operation::FenceI);
+    }
+
+    #[test]
+    fn decodes_system_operations()
+    {
+        assert_eq!(decode(0x00000073), Operation::Ecall);
+        assert_eq!(decode(0x00100073), Operation::Ebreak);
+        assert_eq!(decode(0x10200073), Operation::Sret);
+        assert_eq!(decode(0x30200073), Operation::Mret);
+        assert_eq!(decode(0x10500073), Operation::Wfi);
+        assert_eq!(decode(0x12B50073), Operation::SfenceVma);
+    }
+
+    #[test]
+    fn reserved_encodings_are_illegal()
+    {
+        assert_eq!(decode(0x00000000), Operation::Illegal);
+        assert_eq!(decode(0xFFFFFFFF), Operation::Illegal);
+        // ld, a jalr with funct3 set and a branch with funct3 010
+        assert_eq!(decode(0x0002B303), Operation::Illegal);
+        assert_eq!(decode(0x00029067), Operation::Illegal);
+        assert_eq!(decode(0x00002063), Operation::Illegal);
+        // Shift amounts above 31 and unknown funct7 values
+        assert_eq!(decode(0x02051513), Operation::Illegal);
+        assert_eq!(decode(0x80B50533), Operation::Illegal);
+        // lr.w with rs2 set, an ecall writing rd and dret
+        assert_eq!(decode(0x1015A52F), Operation::Illegal);
+        assert_eq!(decode(0x000000F3), Operation::Illegal);
+        assert_eq!(decode(0x7B200073), Operation::Illegal);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,268 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::{Device, AccessWidth};
+
+/// Base address of the PLIC in the memory map
+pub const PLIC_BASE: u32 = 0x0C00_0000;
+
+/// Size of the PLIC address range
+pub const PLIC_SIZE: u32 = 0x0400_0000;
+
+/// Number of interrupt sources, including the reserved source 0
+pub const PLIC_SOURCES: u32 = 32;
+
+/// Highest priority a source can be given
+const MAX_PRIORITY: u32 = 7;
+
+/// Offset of the source priority registers
+const PRIORITY_OFFSET: u32 = 0x00_0000;
+
+/// Offset of the pending bits
+const PENDING_OFFSET: u32 = 0x00_1000;
+
+/// Offset of the enable bits for the hart's machine mode context
+const ENABLE_OFFSET: u32 = 0x00_2000;
+
+/// Offset of the priority threshold for the hart's machine mode context
+const THRESHOLD_OFFSET: u32 = 0x20_0000;
+
+/// Offset of the claim / complete register for the hart's machine mode context
+const CLAIM_OFFSET: u32 = 0x20_0004;
+
+/// Platform Level Interrupt Controller (routes device interrupts to the machine external interrupt of a single hart)
+pub struct Plic
+{
+    priority: [u32; PLIC_SOURCES as usize],
+    enable: u32,
+    threshold: u32,
+
+    levels: u32,
+
+    pending: u32,
+    claimed: u32
+}
+
+impl Plic
+{
+    /// Generate a new Plic, with every source disabled
+    pub fn new() -> Self
+    {
+        Self
+        {
+            priority: [0; PLIC_SOURCES as usize],
+            enable: 0,
+            threshold: 0,
+
+            levels: 0,
+
+            pending: 0,
+            claimed: 0
+        }
+    }
+
+    /// Return the registers to their power on values, with the sources still held high left pending
+    pub fn reset(&mut self)
+    {
+        *self = Self
+        {
+            levels: self.levels,
+            pending: self.levels,
+            ..Self::new()
+        };
+    }
+
+    /// Trigger an edge on an interrupt source, making it pending unless it has been claimed and not completed
+    pub fn trigger_interrupt(&mut self, source: u32)
+    {
+        if source > 0 && source < PLIC_SOURCES && self.claimed & (1 << source) == 0
+        {
+            self.pending |= 1 << source;
+        }
+    }
+
+    /// Set the level of a level triggered interrupt source, which stays pending while it is high
+    pub fn set_interrupt_level(&mut self, source: u32, level: bool)
+    {
+        if source == 0 || source >= PLIC_SOURCES
+        {
+            return;
+        }
+
+        if level
+        {
+            self.levels |= 1 << source;
+            self.trigger_interrupt(source);
+        }
+        else
+        {
+            self.levels &= !(1 << source);
+        }
+    }
+
+    /// Get the pending bits of all sources
+    pub fn get_pending(&self) -> u32
+    {
+        self.pending
+    }
+
+    /// Get the bits of the sources which have been claimed and not completed
+    pub fn get_claimed(&self) -> u32
+    {
+        self.claimed
+    }
+
+    /// Find the enabled pending source with the highest priority above the threshold (the lowest id wins a tie)
+    fn best_source(&self) -> u32
+    {
+        let candidates = self.pending & self.enable;
+        let mut best = 0;
+
+        for source in 1..PLIC_SOURCES
+        {
+            if candidates & (1 << source) != 0 && self.priority[source as usize] > self.threshold &&
+               (best == 0 || self.priority[source as usize] > self.priority[best as usize])
+            {
+                best = source;
+            }
+        }
+
+        best
+    }
+
+    /// Check if the machine external interrupt is pending
+    pub fn external_interrupt(&self) -> bool
+    {
+        self.best_source() != 0
+    }
+
+    /// Claim the best pending source, returning its id (or 0 if there is none)
+    fn claim(&mut self) -> u32
+    {
+        let source = self.best_source();
+
+        if source != 0
+        {
+            self.pending &= !(1 << source);
+            self.claimed |= 1 << source;
+        }
+
+        source
+    }
+
+    /// Complete the handling of a claimed source, letting it become pending again
+    fn complete(&mut self, source: u32)
+    {
+        if source == 0 || source >= PLIC_SOURCES || self.enable & (1 << source) == 0
+        {
+            return;
+        }
+
+        self.claimed &= !(1 << source);
+
+        // A level triggered source which is still high is pending again straight away
+        if self.levels & (1 << source) != 0
+        {
+            self.trigger_interrupt(source);
+        }
+    }
+}
+
+impl Plic
+{
+    /// Get the value of the register at a word offset, where the claim register shows the source a claim would give
+    fn register(&self, offset: u32) -> u32
+    {
+        match offset
+        {
+            CLAIM_OFFSET => self.best_source(),
+            THRESHOLD_OFFSET => self.threshold,
+            PENDING_OFFSET => self.pending,
+            ENABLE_OFFSET => self.enable,
+            offset if offset < PRIORITY_OFFSET + 4 * PLIC_SOURCES => self.priority[(offset / 4) as usize],
+            _ => 0
+        }
+    }
+}
+
+impl Device for Plic
+{
+    fn read(&mut self, offset: u32, width: AccessWidth) -> u32
+    {
+        // Any read of the low byte of the claim register claims the source, source ids fit in that byte
+        if offset == CLAIM_OFFSET
+        {
+            return self.claim();
+        }
+
+        self.peek(offset, width)
+    }
+
+    fn peek(&self, offset: u32, width: AccessWidth) -> u32
+    {
+        (self.register(offset & !0b11) >> (8 * (offset & 0b11))) & width.mask()
+    }
+
+    fn write(&mut self, offset: u32, width: AccessWidth, data: u32)
+    {
+        let shift = 8 * (offset & 0b11);
+        let lanes = width.mask() << shift;
+
+        // Replace the bytes of a 32 bit register the access covers
+        let replace = |value: u32| (value & !lanes) | ((data << shift) & lanes);
+
+        match offset & !0b11
+        {
+            CLAIM_OFFSET if shift == 0 => self.complete(data & width.mask()),
+            THRESHOLD_OFFSET => self.threshold = replace(self.threshold).min(MAX_PRIORITY),
+            // Source 0 does not exist, so its enable bit is hardwired to zero
+            ENABLE_OFFSET => self.enable = replace(self.enable) & !1,
+            // Source 0 has no priority register, and the pending bits are read only
+            offset if offset > PRIORITY_OFFSET && offset < PRIORITY_OFFSET + 4 * PLIC_SOURCES =>
+            {
+                let source = (offset / 4) as usize;
+                self.priority[source] = replace(self.priority[source]).min(MAX_PRIORITY);
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Interrupt line from a device to one source of a shared PLIC
+#[derive(Clone)]
+pub struct InterruptLine
+{
+    plic: Rc<RefCell<Plic>>,
+    source: u32
+}
+
+impl InterruptLine
+{
+    /// Generate a new InterruptLine driving the given source
+    pub fn new(plic: Rc<RefCell<Plic>>, source: u32) -> Self
+    {
+        Self
+        {
+            plic,
+            source
+        }
+    }
+
+    /// Get the source id driven by this line
+    pub fn get_source(&self) -> u32
+    {
+        self.source
+    }
+
+    /// Signal a single interrupt (edge triggered)
+    pub fn trigger(&self)
+    {
+        self.plic.borrow_mut().trigger_interrupt(self.source);
+    }
+
+    /// Drive the line high or low (level triggered)
+    pub fn set_level(&self, level: bool)
+    {
+        self.plic.borrow_mut().set_interrupt_level(self.source, level);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,191 @@
+use super::{AccessType, Privilege};
+
+/// Number of PMP entries
+pub const PMP_ENTRIES: usize = 16;
+
+/// Readable bit of an entry's configuration
+const PMP_R: u8 = 1 << 0;
+
+/// Writable bit of an entry's configuration
+const PMP_W: u8 = 1 << 1;
+
+/// Executable bit of an entry's configuration
+const PMP_X: u8 = 1 << 2;
+
+/// Address matching field of an entry's configuration
+const PMP_A: u8 = 0b11 << 3;
+
+/// Locked bit of an entry's configuration (the entry cannot be changed and applies to machine mode too)
+const PMP_L: u8 = 1 << 7;
+
+/// Address matching mode of a PMP entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PmpMatch
+{
+    /// The entry is disabled
+    Off,
+    /// Top of range, the entry covers from the previous entry's address up to its own
+    Tor,
+    /// Naturally aligned four byte region
+    Na4,
+    /// Naturally aligned power of two region, of at least eight bytes
+    Napot
+}
+
+impl PmpMatch
+{
+    /// Get the address matching mode of an entry's configuration
+    fn from_config(config: u8) -> Self
+    {
+        match (config & PMP_A) >> 3
+        {
+            0b00 => PmpMatch::Off,
+            0b01 => PmpMatch::Tor,
+            0b10 => PmpMatch::Na4,
+            _ => PmpMatch::Napot
+        }
+    }
+}
+
+/// Physical Memory Protection unit
+pub struct Pmp
+{
+    config: [u8; PMP_ENTRIES],
+    address: [u32; PMP_ENTRIES]
+}
+
+impl Pmp
+{
+    /// Generate a new Pmp, with every entry disabled
+    pub fn new() -> Self
+    {
+        Self
+        {
+            config: [0; PMP_ENTRIES],
+            address: [0; PMP_ENTRIES]
+        }
+    }
+
+    /// Read a pmpcfg register, which packs the configuration of four entries
+    pub fn read_config(&self, index: usize) -> u32
+    {
+        (0..4).fold(0, |value, byte| value | ((self.config[4 * index + byte] as u32) << (8 * byte)))
+    }
+
+    /// Write a pmpcfg register, leaving the configuration of locked entries
+    pub fn write_config(&mut self, index: usize, value: u32)
+    {
+        for byte in 0..4
+        {
+            let entry = 4 * index + byte;
+
+            if self.config[entry] & PMP_L != 0
+            {
+                continue;
+            }
+
+            // Bits 6:5 are reserved, and writable without readable is reserved so is not kept
+            let mut config = (value >> (8 * byte)) as u8 & !0b0110_0000;
+
+            if config & (PMP_R | PMP_W) == PMP_W
+            {
+                config &= !PMP_W;
+            }
+
+            self.config[entry] = config;
+        }
+    }
+
+    /// Read a pmpaddr register (bits 33:2 of an address)
+    pub fn read_address(&self, entry: usize) -> u32
+    {
+        self.address[entry]
+    }
+
+    /// Write a pmpaddr register, unless the entry is locked or is the bottom of a locked top of range entry
+    pub fn write_address(&mut self, entry: usize, value: u32)
+    {
+        let locked = self.config[entry] & PMP_L != 0;
+        let bounds_locked = entry + 1 < PMP_ENTRIES && self.config[entry + 1] & PMP_L != 0 &&
+                            PmpMatch::from_config(self.config[entry + 1]) == PmpMatch::Tor;
+
+        if !locked && !bounds_locked
+        {
+            self.address[entry] = value;
+        }
+    }
+
+    /// Get the range of byte addresses covered by an entry (the end is exclusive), or None if it is disabled
+    fn range(&self, entry: usize) -> Option<(u64, u64)>
+    {
+        let address = self.address[entry] as u64;
+
+        match PmpMatch::from_config(self.config[entry])
+        {
+            PmpMatch::Off => None,
+            PmpMatch::Tor =>
+            {
+                let bottom = if entry == 0 {0} else {(self.address[entry - 1] as u64) << 2};
+
+                Some((bottom, address << 2))
+            },
+            PmpMatch::Na4 => Some((address << 2, (address << 2) + 4)),
+            PmpMatch::Napot =>
+            {
+                // The number of trailing ones gives the size, 2^(ones + 3) bytes
+                let ones = self.address[entry].trailing_ones() as u64;
+                let base = (address & !((1 << ones) - 1)) << 2;
+
+                Some((base, base + (1 << (ones + 3))))
+            }
+        }
+    }
+
+    /// Check if an access of some bytes at a physical address is permitted for a privilege level
+    pub fn check(&self, addr: u32, size: u32, access: AccessType, privilege: Privilege) -> bool
+    {
+        let start = addr as u64;
+        let end = start + size as u64;
+
+        // The lowest numbered entry which matches any byte of the access decides
+        for entry in 0..PMP_ENTRIES
+        {
+            let (bottom, top) = match self.range(entry)
+            {
+                Some(range) => range,
+                None => continue
+            };
+
+            if end <= bottom || start >= top
+            {
+                continue;
+            }
+
+            // Every byte of the access must be inside the entry
+            if start < bottom || end > top
+            {
+                return false;
+            }
+
+            let config = self.config[entry];
+
+            // Machine mode is only held to locked entries
+            if privilege == Privilege::Machine && config & PMP_L == 0
+            {
+                return true;
+            }
+
+            let permission = match access
+            {
+                AccessType::Fetch => PMP_X,
+                AccessType::Load => PMP_R,
+                AccessType::Store => PMP_W
+            };
+
+            return config & permission != 0;
+        }
+
+        // Machine mode may access anything no entry matches, supervisor and user mode nothing once any entry is enabled
+        privilege == Privilege::Machine || self.config.iter().all(|config| config & PMP_A == 0)
+    }
+}
\ No newline at end of file
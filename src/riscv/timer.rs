@@ -0,0 +1,167 @@
+use super::{Device, AccessWidth, RegisterBank, BankRegister};
+use super::InterruptLine;
+
+/// Size of the timer address range
+pub const TIMER_SIZE: u32 = 0x1000;
+
+/// Offset of the control register
+const CONTROL_OFFSET: u32 = 0x00;
+
+/// Offset of the value loaded into the count when a periodic timer expires
+const RELOAD_OFFSET: u32 = 0x04;
+
+/// Offset of the clocks left until the timer expires
+const COUNT_OFFSET: u32 = 0x08;
+
+/// Offset of the status register (write ones to clear)
+const STATUS_OFFSET: u32 = 0x0C;
+
+/// Offset of the number of expiries while the last was still flagged (cleared by reading)
+const OVERRUNS_OFFSET: u32 = 0x10;
+
+/// Control bit counting the timer down
+pub const TIMER_ENABLE: u32 = 1 << 0;
+
+/// Control bit reloading the count when the timer expires, rather than stopping
+pub const TIMER_PERIODIC: u32 = 1 << 1;
+
+/// Control bit raising the interrupt line while the timer is flagged as expired
+pub const TIMER_INTERRUPT_ENABLE: u32 = 1 << 2;
+
+/// Status bit flagging the timer has expired
+pub const TIMER_EXPIRED: u32 = 1 << 0;
+
+/// Countdown timer peripheral, raising a level triggered interrupt when it expires
+pub struct Timer
+{
+    registers: RegisterBank,
+    line: InterruptLine
+}
+
+impl Timer
+{
+    /// Generate a new Timer, stopped, which raises the interrupt line
+    pub fn new(line: InterruptLine) -> Self
+    {
+        Self
+        {
+            registers: RegisterBank::new(&
+            [
+                (CONTROL_OFFSET, BankRegister::value(0, TIMER_ENABLE | TIMER_PERIODIC | TIMER_INTERRUPT_ENABLE)),
+                (RELOAD_OFFSET, BankRegister::value(0, u32::MAX)),
+                (COUNT_OFFSET, BankRegister::value(0, u32::MAX)),
+                (STATUS_OFFSET, BankRegister::write_one_to_clear(TIMER_EXPIRED)),
+                (OVERRUNS_OFFSET, BankRegister::clear_on_read(u32::MAX))
+            ]),
+            line
+        }
+    }
+
+    /// Drive the interrupt line from the status and control registers
+    fn update_line(&self)
+    {
+        let control = self.registers.get(CONTROL_OFFSET);
+
+        self.line.set_level(control & TIMER_INTERRUPT_ENABLE != 0 && self.registers.get(STATUS_OFFSET) & TIMER_EXPIRED != 0);
+    }
+
+    /// Flag the timer as expired, then reload or stop it
+    fn expire(&mut self)
+    {
+        if self.registers.get(STATUS_OFFSET) & TIMER_EXPIRED != 0
+        {
+            self.registers.set(OVERRUNS_OFFSET, self.registers.get(OVERRUNS_OFFSET).saturating_add(1));
+        }
+
+        self.registers.set_bits(STATUS_OFFSET, TIMER_EXPIRED);
+
+        let control = self.registers.get(CONTROL_OFFSET);
+
+        if control & TIMER_PERIODIC != 0
+        {
+            self.registers.set(COUNT_OFFSET, self.registers.get(RELOAD_OFFSET));
+        }
+        else
+        {
+            self.registers.set(CONTROL_OFFSET, control & !TIMER_ENABLE);
+        }
+
+        self.update_line();
+    }
+}
+
+impl Device for Timer
+{
+    fn read(&mut self, offset: u32, width: AccessWidth) -> u32
+    {
+        self.registers.read(offset, width)
+    }
+
+    fn peek(&self, offset: u32, width: AccessWidth) -> u32
+    {
+        self.registers.peek(offset, width)
+    }
+
+    fn write(&mut self, offset: u32, width: AccessWidth, data: u32)
+    {
+        self.registers.write(offset, width, data);
+        self.update_line();
+    }
+
+    fn tick(&mut self)
+    {
+        if self.registers.get(CONTROL_OFFSET) & TIMER_ENABLE == 0
+        {
+            return;
+        }
+
+        // A count of 0 expires on the next clock, the same as a count of 1
+        let count = self.registers.get(COUNT_OFFSET).saturating_sub(1);
+        self.registers.set(COUNT_OFFSET, count);
+
+        if count == 0
+        {
+            self.expire();
+        }
+    }
+
+    fn advance(&mut self, clocks: u64)
+    {
+        let count = self.registers.get(COUNT_OFFSET) as u64;
+
+        if self.registers.get(CONTROL_OFFSET) & TIMER_ENABLE == 0
+        {
+            return;
+        }
+
+        // Counting down short of expiring needs no clocking
+        if clocks < count
+        {
+            self.registers.set(COUNT_OFFSET, (count - clocks) as u32);
+            return;
+        }
+
+        for _ in 0..clocks
+        {
+            self.tick();
+        }
+    }
+
+    fn clocks_until_event(&self) -> Option<u64>
+    {
+        if self.registers.get(CONTROL_OFFSET) & TIMER_ENABLE != 0
+        {
+            Some(self.registers.get(COUNT_OFFSET).max(1) as u64)
+        }
+        else
+        {
+            None
+        }
+    }
+
+    fn reset(&mut self)
+    {
+        self.registers.reset();
+        self.update_line();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,162 @@
+use std::fmt;
+
+/// Privilege level the hart is running at
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Privilege
+{
+    User,
+    Supervisor,
+    Machine
+}
+
+impl Privilege
+{
+    /// Get the encoding of the privilege level (as used in mstatus.MPP)
+    pub fn bits(&self) -> u32
+    {
+        match self
+        {
+            Privilege::User => 0b00,
+            Privilege::Supervisor => 0b01,
+            Privilege::Machine => 0b11
+        }
+    }
+
+    /// Get the privilege level of an encoding, the reserved encoding 0b10 is treated as machine mode
+    pub fn from_bits(bits: u32) -> Self
+    {
+        match bits & 0b11
+        {
+            0b00 => Privilege::User,
+            0b01 => Privilege::Supervisor,
+            _ => Privilege::Machine
+        }
+    }
+}
+
+/// Cause of a trap (an exception raised by an instruction or an interrupt)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrapCause
+{
+    InstructionAddressMisaligned,
+    InstructionAccessFault,
+    IllegalInstruction,
+    Breakpoint,
+    LoadAddressMisaligned,
+    LoadAccessFault,
+    StoreAddressMisaligned,
+    StoreAccessFault,
+    EnvironmentCallFromUser,
+    EnvironmentCallFromSupervisor,
+    EnvironmentCallFromMachine,
+    InstructionPageFault,
+    LoadPageFault,
+    StorePageFault,
+
+    SupervisorSoftwareInterrupt,
+    SupervisorTimerInterrupt,
+    SupervisorExternalInterrupt,
+    MachineSoftwareInterrupt,
+    MachineTimerInterrupt,
+    MachineExternalInterrupt
+}
+
+impl TrapCause
+{
+    /// Get the exception or interrupt code of the cause
+    pub fn code(&self) -> u32
+    {
+        match self
+        {
+            TrapCause::InstructionAddressMisaligned => 0,
+            TrapCause::InstructionAccessFault => 1,
+            TrapCause::IllegalInstruction => 2,
+            TrapCause::Breakpoint => 3,
+            TrapCause::LoadAddressMisaligned => 4,
+            TrapCause::LoadAccessFault => 5,
+            TrapCause::StoreAddressMisaligned => 6,
+            TrapCause::StoreAccessFault => 7,
+            TrapCause::EnvironmentCallFromUser => 8,
+            TrapCause::EnvironmentCallFromSupervisor => 9,
+            TrapCause::EnvironmentCallFromMachine => 11,
+            TrapCause::InstructionPageFault => 12,
+            TrapCause::LoadPageFault => 13,
+            TrapCause::StorePageFault => 15,
+
+            TrapCause::SupervisorSoftwareInterrupt => 1,
+            TrapCause::SupervisorTimerInterrupt => 5,
+            TrapCause::SupervisorExternalInterrupt => 9,
+            TrapCause::MachineSoftwareInterrupt => 3,
+            TrapCause::MachineTimerInterrupt => 7,
+            TrapCause::MachineExternalInterrupt => 11
+        }
+    }
+
+    /// Check if the cause is an interrupt rather than an exception
+    pub fn is_interrupt(&self) -> bool
+    {
+        matches!(self, TrapCause::SupervisorSoftwareInterrupt | TrapCause::SupervisorTimerInterrupt | TrapCause::SupervisorExternalInterrupt |
+                       TrapCause::MachineSoftwareInterrupt | TrapCause::MachineTimerInterrupt | TrapCause::MachineExternalInterrupt)
+    }
+
+    /// Get the exception raised by an environment call from a privilege level
+    pub fn environment_call(privilege: Privilege) -> Self
+    {
+        match privilege
+        {
+            Privilege::User => TrapCause::EnvironmentCallFromUser,
+            Privilege::Supervisor => TrapCause::EnvironmentCallFromSupervisor,
+            Privilege::Machine => TrapCause::EnvironmentCallFromMachine
+        }
+    }
+
+    /// Get the value written to mcause or scause (the code, with the top bit set for interrupts)
+    pub fn mcause(&self) -> u32
+    {
+        if self.is_interrupt()
+        {
+            (1 << 31) | self.code()
+        }
+        else
+        {
+            self.code()
+        }
+    }
+}
+
+/// Interrupts in the order they are taken when several are pending
+pub const INTERRUPT_PRIORITY: [TrapCause; 6] =
+[
+    TrapCause::MachineExternalInterrupt,
+    TrapCause::MachineSoftwareInterrupt,
+    TrapCause::MachineTimerInterrupt,
+    TrapCause::SupervisorExternalInterrupt,
+    TrapCause::SupervisorSoftwareInterrupt,
+    TrapCause::SupervisorTimerInterrupt
+];
+
+/// Number of traps taken back to back without an instruction retiring before the hart is treated as stuck
+pub const TRAP_LOOP_LIMIT: u32 = 16;
+
+/// Reason the hart cannot make progress (holds the cause of the trap and the address it was taken at)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fault
+{
+    /// A trap was taken before the trap vector was ever written, so there is no handler to run
+    NoTrapHandler(TrapCause, u32),
+    /// Traps kept being taken without an instruction retiring, as the handler traps itself
+    TrapLoop(TrapCause, u32)
+}
+
+impl fmt::Display for Fault
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self
+        {
+            Fault::NoTrapHandler(cause, addr) => write!(f, "{:?} at 0x{:08X} with no trap handler installed", cause, addr),
+            Fault::TrapLoop(cause, addr) =>
+                write!(f, "{} traps taken without an instruction retiring, the last being {:?} at 0x{:08X}", TRAP_LOOP_LIMIT, cause, addr)
+        }
+    }
+}
\ No newline at end of file
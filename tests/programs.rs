@@ -0,0 +1,172 @@
+use std::process::{Command, Output};
+
+/// Run a program from the programs directory with the given options, giving the output of the emulator
+fn emulate(program: &str, options: &[&str]) -> Output
+{
+    Command::new(env!("CARGO_BIN_EXE_riscv"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg(format!("programs/{}", program))
+        .args(options)
+        .output()
+        .expect("the emulator runs")
+}
+
+/// Run a program from the programs directory with the given options, giving the final value of a0
+fn run(program: &str, options: &[&str]) -> u32
+{
+    let output = emulate(program, options);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{} exited with {}:\n{}{}", program, output.status, stdout, String::from_utf8_lossy(&output.stderr));
+
+    let value = stdout.split("a0: 0x").nth(1).and_then(|rest| rest.get(..8)).expect("the final state lists a0");
+
+    u32::from_str_radix(value, 16).expect("a0 is printed in hex")
+}
+
+/// Run a program from the programs directory with the given options, giving the code the emulator exits with
+fn exit_code(program: &str, options: &[&str]) -> Option<i32>
+{
+    emulate(program, options).status.code()
+}
+
+/// Run a self checking program, which leaves 0 in a0 on success or the number of the failing test
+fn check(program: &str, options: &[&str])
+{
+    assert_eq!(run(program, options), 0, "{} failed the test numbered by a0", program);
+}
+
+#[test]
+fn rv32i()
+{
+    check("rv32i_test.s", &["-e"]);
+}
+
+#[test]
+fn rv32m()
+{
+    check("rv32m_test.s", &["-e"]);
+}
+
+#[test]
+fn rv32a()
+{
+    check("rv32a_test.s", &["-e"]);
+}
+
+#[test]
+fn rv32c()
+{
+    check("rv32c_test.s", &["-e"]);
+}
+
+#[test]
+fn zicsr()
+{
+    check("zicsr_test.s", &["-e"]);
+}
+
+#[test]
+fn fence()
+{
+    check("fence_test.s", &["-e"]);
+}
+
+#[test]
+fn illegal_instructions()
+{
+    check("illegal_test.s", &["-e"]);
+}
+
+#[test]
+fn misaligned_accesses()
+{
+    check("misaligned_test.s", &["-e"]);
+}
+
+#[test]
+fn machine_traps()
+{
+    check("trap_test.s", &["-i", "1000"]);
+}
+
+#[test]
+fn clint()
+{
+    check("clint_test.s", &["-e"]);
+}
+
+#[test]
+fn plic()
+{
+    check("plic_test.s", &["-e", "-q", "3@200", "-q", "5@800", "-q", "6@1400", "-q", "7@1400"]);
+}
+
+#[test]
+fn sv32()
+{
+    check("sv32_test.s", &["-i", "2000"]);
+}
+
+#[test]
+fn csr_registry()
+{
+    check("csr_test.s", &["-i", "1000"]);
+}
+
+#[test]
+fn pmp()
+{
+    check("pmp_test.s", &["-i", "1000"]);
+}
+
+#[test]
+fn counters()
+{
+    check("counter_test.s", &["-i", "1000"]);
+}
+
+#[test]
+fn wfi()
+{
+    check("wfi_test.s", &["-i", "1000", "-q", "3@200000"]);
+}
+
+#[test]
+fn memory_map()
+{
+    check("memmap_test.s", &["-i", "1000", "-M", "ram512k@0,ram1m@0x80000000,rom1m@0x20000000,clint@0x40000000"]);
+}
+
+#[test]
+fn boot_rom_and_warm_reset()
+{
+    check("reset_test.s", &["-M", "ram512k@0,ram512k@0x80000,rom1m@0x20000000,clint@0x02000000",
+                            "-R", "0x20000000", "-b", "programs/boot_rom.s", "-w", "1000", "-i", "2000"]);
+}
+
+#[test]
+fn timer_device()
+{
+    check("timer_test.s", &["-T", "0x10000000@4", "-i", "1000"]);
+}
+
+#[test]
+fn trap_handler_at_zero()
+{
+    check("zero_vector_test.s", &["-e"]);
+}
+
+#[test]
+fn unhandled_traps_fault()
+{
+    // The cycle limit only stops the run if the fault is missed
+    assert_eq!(exit_code("no_handler.s", &["-c", "100000"]), Some(1));
+    assert_eq!(exit_code("trap_loop.s", &["-c", "100000"]), Some(1));
+}
+
+#[test]
+fn fibonacci()
+{
+    assert_eq!(run("fibonacci.s", &["-e"]), 0x22);
+}
\ No newline at end of file